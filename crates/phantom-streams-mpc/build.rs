@@ -0,0 +1,23 @@
+// Copies the hash helpers out of the encrypted instructions, so the tests in
+// `hash` compile the circuit's own code and check it against the mirror.
+// They're everything after the HELPER FUNCTIONS banner, up to the closing
+// brace of the `circuits` module.
+
+use std::{env, fs, path::Path};
+
+const CIRCUITS: &str = "../../encrypted-ixs/src/lib.rs";
+const BANNER: &str = "// HELPER FUNCTIONS";
+
+fn main() {
+    println!("cargo:rerun-if-changed={CIRCUITS}");
+
+    let source = fs::read_to_string(CIRCUITS).expect("encrypted-ixs source");
+    let start = source.find(BANNER).expect("HELPER FUNCTIONS banner");
+    // Skip the banner's closing rule
+    let start = start + source[start..].find("// ====").expect("banner rule");
+    let start = start + source[start..].find('\n').expect("banner rule line");
+    let end = source.rfind('}').expect("end of circuits module");
+
+    let out = Path::new(&env::var("OUT_DIR").unwrap()).join("circuit_helpers.rs");
+    fs::write(out, &source[start..end]).expect("write circuit helpers");
+}
@@ -0,0 +1,193 @@
+// Phantom Streams - Ballot queue of a royalty vote
+//
+// Ballots are cast onto the end of a vote's queue and folded into its
+// encrypted tally one at a time, in order, so each fold starts from the
+// tally the previous one produced. At most one fold is in flight; the vote
+// records its pending computation so that:
+// - only that computation's callback advances the queue,
+// - cancelling it after the timeout releases the queue, and the same
+//   ballot can be folded again (its record can then be closed without
+//   leaving the vote pointing at it),
+// - the result is only revealed once every ballot cast has been folded.
+
+use borsh::{BorshDeserialize, BorshSerialize};
+
+/// Why a queue operation was refused
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum QueueError {
+    /// Counter would overflow
+    Overflow,
+    /// Every ballot cast has been folded
+    NoBallotsQueued,
+    /// Another fold is in flight
+    FoldInFlight,
+    /// Computation isn't the fold in flight
+    NotInFlight,
+    /// Ballots are still waiting to be folded
+    BallotsPending,
+}
+
+/// A vote's ballot queue. `K` is the account key type
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct BallotQueue<K> {
+    /// Ballots folded into the tally so far; also the index of the next
+    /// ballot to fold
+    pub tally_version: u64,
+    /// Ballots cast; those from `tally_version` on are still queued
+    pub ballots_cast: u64,
+    /// Pending computation of the fold in flight, if any
+    pub fold_in_flight: Option<K>,
+}
+
+/// Serialized size of a `BallotQueue` with 32-byte keys
+pub const BALLOT_QUEUE_SIZE: usize = 8 + 8 + (1 + 32);
+
+impl<K: PartialEq> BallotQueue<K> {
+    /// Add a ballot to the end of the queue, returning its index
+    pub fn cast(&mut self) -> Result<u64, QueueError> {
+        let index = self.ballots_cast;
+        self.ballots_cast = index.checked_add(1).ok_or(QueueError::Overflow)?;
+        Ok(index)
+    }
+
+    /// Start folding the next ballot with the computation `record`,
+    /// returning the ballot's index
+    pub fn start_fold(&mut self, record: K) -> Result<u64, QueueError> {
+        if self.fold_in_flight.is_some() {
+            return Err(QueueError::FoldInFlight);
+        }
+        if self.tally_version >= self.ballots_cast {
+            return Err(QueueError::NoBallotsQueued);
+        }
+        self.fold_in_flight = Some(record);
+        Ok(self.tally_version)
+    }
+
+    /// Consume the ballot `record` was folding, whether or not it was
+    /// counted, returning its index
+    pub fn finish_fold(&mut self, record: &K) -> Result<u64, QueueError> {
+        self.require_in_flight(record)?;
+        let index = self.tally_version;
+        self.tally_version = index.checked_add(1).ok_or(QueueError::Overflow)?;
+        self.fold_in_flight = None;
+        Ok(index)
+    }
+
+    /// Release the queue from `record`, a fold that was cancelled, so the
+    /// same ballot can be folded again
+    pub fn abandon_fold(&mut self, record: &K) -> Result<(), QueueError> {
+        self.require_in_flight(record)?;
+        self.fold_in_flight = None;
+        Ok(())
+    }
+
+    /// Check every ballot cast is in the tally
+    pub fn check_reveal(&self) -> Result<(), QueueError> {
+        if self.tally_version != self.ballots_cast || self.fold_in_flight.is_some() {
+            return Err(QueueError::BallotsPending);
+        }
+        Ok(())
+    }
+
+    fn require_in_flight(&self, record: &K) -> Result<(), QueueError> {
+        match &self.fold_in_flight {
+            Some(in_flight) if in_flight == record => Ok(()),
+            _ => Err(QueueError::NotInFlight),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::computation::{
+        ComputationKind, ComputationStatus, QueuedComputation, Rejection, COMPUTATION_TIMEOUT_SLOTS,
+    };
+
+    const VOTE: [u8; 32] = [7u8; 32];
+
+    fn fold_record(queued_slot: u64) -> QueuedComputation<[u8; 32]> {
+        QueuedComputation {
+            kind: ComputationKind::CastVote,
+            status: ComputationStatus::Queued,
+            requester: [1u8; 32],
+            fee_payer: [1u8; 32],
+            fee: 0,
+            target: VOTE,
+            snapshot: [0u8; 32],
+            computation_offset: queued_slot,
+            queued_slot,
+        }
+    }
+
+    #[test]
+    fn folds_ballots_in_order() {
+        let mut queue = BallotQueue::<[u8; 32]>::default();
+        assert_eq!(queue.cast(), Ok(0));
+        assert_eq!(queue.cast(), Ok(1));
+
+        assert_eq!(queue.start_fold([1; 32]), Ok(0));
+        assert_eq!(queue.start_fold([2; 32]), Err(QueueError::FoldInFlight));
+        assert_eq!(queue.finish_fold(&[2; 32]), Err(QueueError::NotInFlight));
+        assert_eq!(queue.finish_fold(&[1; 32]), Ok(0));
+
+        assert_eq!(queue.check_reveal(), Err(QueueError::BallotsPending));
+        assert_eq!(queue.start_fold([2; 32]), Ok(1));
+        assert_eq!(queue.finish_fold(&[2; 32]), Ok(1));
+        assert_eq!(queue.start_fold([3; 32]), Err(QueueError::NoBallotsQueued));
+        assert_eq!(queue.check_reveal(), Ok(()));
+    }
+
+    #[test]
+    fn cancel_close_fold_reveal() {
+        let mut queue = BallotQueue::<[u8; 32]>::default();
+        queue.cast().unwrap();
+
+        // A fold is queued and its callback never arrives
+        let first = [1u8; 32];
+        let mut record = fold_record(100);
+        queue.start_fold(first).unwrap();
+        assert_eq!(queue.check_reveal(), Err(QueueError::BallotsPending));
+
+        // Cancel marks the record and releases the queue from it
+        assert_eq!(record.cancel(100 + COMPUTATION_TIMEOUT_SLOTS), Ok(0));
+        queue.abandon_fold(&first).unwrap();
+        assert_eq!(queue.fold_in_flight, None);
+
+        // The record is resolved, so it can be closed, and its late
+        // callback is rejected
+        assert_eq!(record.status, ComputationStatus::TimedOut);
+        assert_eq!(
+            record.check_callback(ComputationKind::CastVote, &VOTE, &[0u8; 32]),
+            Err(Rejection::NotQueued)
+        );
+
+        // Nothing points at the closed record, so the same ballot is
+        // folded again and the vote can be revealed
+        let second = [2u8; 32];
+        assert_eq!(queue.start_fold(second), Ok(0));
+        assert_eq!(queue.finish_fold(&second), Ok(0));
+        assert_eq!(queue.check_reveal(), Ok(()));
+    }
+
+    #[test]
+    fn only_the_fold_in_flight_is_abandoned() {
+        let mut queue = BallotQueue::<[u8; 32]>::default();
+        queue.cast().unwrap();
+        assert_eq!(queue.abandon_fold(&[1; 32]), Err(QueueError::NotInFlight));
+
+        queue.start_fold([1; 32]).unwrap();
+        assert_eq!(queue.abandon_fold(&[2; 32]), Err(QueueError::NotInFlight));
+        assert_eq!(queue.fold_in_flight, Some([1; 32]));
+    }
+
+    #[test]
+    fn queue_size_matches_serialized_length() {
+        let queue = BallotQueue {
+            tally_version: 1,
+            ballots_cast: 2,
+            fold_in_flight: Some([1u8; 32]),
+        };
+        assert_eq!(queue.try_to_vec().unwrap().len(), BALLOT_QUEUE_SIZE);
+    }
+}
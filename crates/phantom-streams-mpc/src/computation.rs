@@ -0,0 +1,281 @@
+// Phantom Streams - Queued Arcium computations
+//
+// The Arcium program records every computation it queues, keyed by the
+// computation's Arcium account. A callback is only applied when:
+// - the cluster signed its outputs for that computation account (checked
+//   by the program with `verify_output`; the account is derived from the
+//   MXE and the offset recorded here, so another one can't be swapped in),
+// - the record is still `Queued`, so a replayed or cancelled callback is
+//   rejected,
+// - the record was queued for the same encrypted instruction and target,
+// - the state the inputs were read from (Merkle root, tally version) is
+//   still current.
+//
+// A computation whose callback never arrives can be cancelled by anyone once
+// it has waited COMPUTATION_TIMEOUT_SLOTS, so a bogus or stuck computation
+// can't hold up a vote's fold queue for good.
+//
+// The Arcium fee is escrowed in the record when the computation is queued.
+// It's paid out to Arcium when the callback resolves the computation, and
+// refunded to whoever put it up when the computation is cancelled, so a
+// stuck job costs its requester nothing but time.
+
+use borsh::{BorshDeserialize, BorshSerialize};
+
+/// Slots a queued computation may wait for its callback before anyone can
+/// cancel it (~10 minutes)
+pub const COMPUTATION_TIMEOUT_SLOTS: u64 = 1_500;
+
+/// Encrypted instruction a pending computation runs
+#[derive(BorshSerialize, BorshDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ComputationKind {
+    VerifyOwnership,
+    InitVoteTally,
+    CastVote,
+    RevealResult,
+}
+
+/// Where a queued computation is in its lifecycle
+#[derive(BorshSerialize, BorshDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ComputationStatus {
+    /// Waiting for its callback
+    Queued,
+    /// Callback applied the result
+    Completed,
+    /// Callback arrived, but the MPC instruction reported failure
+    Failed,
+    /// Cancelled after the timeout; a late callback is rejected
+    TimedOut,
+}
+
+/// Why a callback or cancellation was refused
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Rejection {
+    /// Record is for another encrypted instruction or target
+    WrongComputation,
+    /// Record was already resolved or cancelled
+    NotQueued,
+    /// Inputs changed after the computation was queued
+    Stale,
+    /// Cancelled before COMPUTATION_TIMEOUT_SLOTS have passed
+    NotTimedOut,
+}
+
+/// Computation queued with Arcium, as recorded when it was queued. `K` is
+/// the account key type
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct QueuedComputation<K> {
+    pub kind: ComputationKind,
+    pub status: ComputationStatus,
+    /// Paid the record's rent, returned when the record is closed
+    pub requester: K,
+    /// Put up the escrowed fee, refunded to it if the computation is
+    /// cancelled
+    pub fee_payer: K,
+    /// Arcium fee held in the record until the computation is resolved
+    pub fee: u64,
+    /// Account the result applies to (the state for verifications)
+    pub target: K,
+    /// State the inputs were read from: the Merkle root for verifications,
+    /// the tally snapshot for votes
+    pub snapshot: [u8; 32],
+    /// Offset the Arcium computation account is derived from
+    pub computation_offset: u64,
+    pub queued_slot: u64,
+}
+
+/// Serialized size of a `QueuedComputation` with 32-byte keys
+pub const QUEUED_COMPUTATION_SIZE: usize = 1 + 1 + 32 + 32 + 8 + 32 + 32 + 8 + 8;
+
+impl<K: PartialEq> QueuedComputation<K> {
+    /// Check a callback for `kind` on `target` can apply this computation's
+    /// result, given the target's current `snapshot`
+    pub fn check_callback(
+        &self,
+        kind: ComputationKind,
+        target: &K,
+        snapshot: &[u8; 32],
+    ) -> Result<(), Rejection> {
+        if self.kind != kind || self.target != *target {
+            return Err(Rejection::WrongComputation);
+        }
+        if self.status != ComputationStatus::Queued {
+            return Err(Rejection::NotQueued);
+        }
+        if self.snapshot != *snapshot {
+            return Err(Rejection::Stale);
+        }
+        Ok(())
+    }
+
+    /// Resolve a computation whose callback was accepted, as `Completed`
+    /// or `Failed`. Returns the escrowed fee, now owed to Arcium
+    pub fn resolve(&mut self, completed: bool) -> u64 {
+        self.status = if completed {
+            ComputationStatus::Completed
+        } else {
+            ComputationStatus::Failed
+        };
+        core::mem::take(&mut self.fee)
+    }
+
+    /// Mark a computation whose callback never arrived as timed out, at
+    /// `slot`. Its callback is rejected from then on. Returns the escrowed
+    /// fee, to refund to `fee_payer`
+    pub fn cancel(&mut self, slot: u64) -> Result<u64, Rejection> {
+        if self.status != ComputationStatus::Queued {
+            return Err(Rejection::NotQueued);
+        }
+        if slot < self.queued_slot.saturating_add(COMPUTATION_TIMEOUT_SLOTS) {
+            return Err(Rejection::NotTimedOut);
+        }
+        self.status = ComputationStatus::TimedOut;
+        Ok(core::mem::take(&mut self.fee))
+    }
+}
+
+/// Snapshot of a vote's encrypted tally: its version, bumped each time a
+/// ballot is folded in
+pub fn tally_snapshot(tally_version: u64) -> [u8; 32] {
+    let mut snapshot = [0u8; 32];
+    snapshot[..8].copy_from_slice(&tally_version.to_le_bytes());
+    snapshot
+}
+
+/// Nullifier revealed by an MPC instruction as PDA seed bytes
+/// (little-endian limbs, lowest first)
+pub fn nullifier_bytes(nullifier: &[u64; 4]) -> [u8; 32] {
+    let mut bytes = [0u8; 32];
+    for (chunk, limb) in bytes.chunks_exact_mut(8).zip(nullifier) {
+        chunk.copy_from_slice(&limb.to_le_bytes());
+    }
+    bytes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KIND: ComputationKind = ComputationKind::CastVote;
+    const TARGET: [u8; 32] = [7u8; 32];
+
+    fn queued() -> QueuedComputation<[u8; 32]> {
+        QueuedComputation {
+            kind: KIND,
+            status: ComputationStatus::Queued,
+            requester: [1u8; 32],
+            fee_payer: [2u8; 32],
+            fee: 500,
+            target: TARGET,
+            snapshot: [1u8; 32],
+            computation_offset: 42,
+            queued_slot: 100,
+        }
+    }
+
+    #[test]
+    fn accepts_matching_callback() {
+        assert_eq!(queued().check_callback(KIND, &TARGET, &[1u8; 32]), Ok(()));
+    }
+
+    #[test]
+    fn rejects_callback_for_another_instruction_or_target() {
+        let record = queued();
+        assert_eq!(
+            record.check_callback(ComputationKind::RevealResult, &TARGET, &[1u8; 32]),
+            Err(Rejection::WrongComputation)
+        );
+        assert_eq!(
+            record.check_callback(KIND, &[8u8; 32], &[1u8; 32]),
+            Err(Rejection::WrongComputation)
+        );
+    }
+
+    #[test]
+    fn rejects_replayed_or_cancelled_callback() {
+        for status in [
+            ComputationStatus::Completed,
+            ComputationStatus::Failed,
+            ComputationStatus::TimedOut,
+        ] {
+            let mut record = queued();
+            record.status = status;
+            assert_eq!(
+                record.check_callback(KIND, &TARGET, &[1u8; 32]),
+                Err(Rejection::NotQueued)
+            );
+        }
+    }
+
+    #[test]
+    fn fold_is_stale_once_tally_version_moves() {
+        let mut record = queued();
+        record.snapshot = tally_snapshot(4);
+        assert_eq!(
+            record.check_callback(KIND, &TARGET, &tally_snapshot(4)),
+            Ok(())
+        );
+        assert_eq!(
+            record.check_callback(KIND, &TARGET, &tally_snapshot(5)),
+            Err(Rejection::Stale)
+        );
+    }
+
+    #[test]
+    fn cancels_only_after_timeout() {
+        let mut record = queued();
+        let deadline = record.queued_slot + COMPUTATION_TIMEOUT_SLOTS;
+        assert_eq!(record.cancel(deadline - 1), Err(Rejection::NotTimedOut));
+        assert_eq!(record.status, ComputationStatus::Queued);
+
+        assert_eq!(record.cancel(deadline), Ok(500));
+        assert_eq!(record.status, ComputationStatus::TimedOut);
+        assert_eq!(
+            record.check_callback(KIND, &TARGET, &[1u8; 32]),
+            Err(Rejection::NotQueued)
+        );
+    }
+
+    #[test]
+    fn fee_is_released_once() {
+        let mut record = queued();
+        assert_eq!(record.resolve(false), 500);
+        assert_eq!(record.status, ComputationStatus::Failed);
+        assert_eq!(record.fee, 0);
+        assert_eq!(
+            record.cancel(record.queued_slot + COMPUTATION_TIMEOUT_SLOTS),
+            Err(Rejection::NotQueued)
+        );
+
+        let mut record = queued();
+        let deadline = record.queued_slot + COMPUTATION_TIMEOUT_SLOTS;
+        assert_eq!(record.cancel(deadline), Ok(500));
+        assert_eq!(record.fee, 0);
+    }
+
+    #[test]
+    fn resolved_computation_cannot_be_cancelled() {
+        let mut record = queued();
+        record.status = ComputationStatus::Completed;
+        assert_eq!(
+            record.cancel(record.queued_slot + COMPUTATION_TIMEOUT_SLOTS),
+            Err(Rejection::NotQueued)
+        );
+    }
+
+    #[test]
+    fn record_size_matches_serialized_length() {
+        let len = queued().try_to_vec().unwrap().len();
+        assert_eq!(len, QUEUED_COMPUTATION_SIZE);
+    }
+
+    #[test]
+    fn nullifier_bytes_are_distinct_per_limb() {
+        assert_eq!(nullifier_bytes(&[1, 0, 0, 0])[0], 1);
+        assert_eq!(nullifier_bytes(&[0, 0, 0, 1])[24], 1);
+        assert_ne!(
+            nullifier_bytes(&[1, 0, 0, 0]),
+            nullifier_bytes(&[0, 1, 0, 0])
+        );
+    }
+}
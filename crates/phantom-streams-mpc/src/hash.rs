@@ -0,0 +1,267 @@
+// Phantom Streams - Hashes used inside the encrypted instructions
+//
+// Mirrors the helpers in encrypted-ixs so the values the circuits reveal or
+// compare can be checked here; the tests compile the circuits' own helpers
+// (copied out by build.rs) and check the mirror against them. Every hash is SHA3-256 over a domain tag and
+// a fixed number of elements (four little-endian u64 limbs each); a value
+// that fits in one limb, like a weight, is absorbed as its own element
+// rather than mixed into another. Fixed arity and per-use domain tags keep
+// inputs for different purposes from colliding.
+
+use sha3::{Digest, Sha3_256};
+
+/// Elements absorbed by every hash; unused ones are zero
+pub const HASH_ARITY: usize = 3;
+
+/// Depth of the rights registry and holdings trees
+pub const TREE_DEPTH: usize = 20;
+
+/// Domain tag for ownership nullifiers ("phantoms")
+pub const DOMAIN_NULLIFIER: u64 = 0x7068616e746f6d73;
+
+/// Domain tag for rights registry leaves ("rightslf")
+pub const DOMAIN_LEAF: u64 = 0x7269676874736c66;
+
+/// Domain tag for Merkle nodes ("merkleno")
+pub const DOMAIN_NODE: u64 = 0x6d65726b6c656e6f;
+
+/// Domain tag for holder commitments ("holdrcmt")
+pub const DOMAIN_HOLDER: u64 = 0x686f6c6472636d74;
+
+/// Domain tag for holdings leaves ("holdings")
+pub const DOMAIN_HOLDING: u64 = 0x686f6c64696e6773;
+
+/// Domain tag for vote nullifiers ("phantomv")
+pub const DOMAIN_VOTE_NULLIFIER: u64 = 0x7068616e746f6d76;
+
+/// SHA3-256 of `domain` followed by `elements`, as four little-endian limbs
+pub fn hash_elements(domain: u64, elements: [[u64; 4]; HASH_ARITY]) -> [u64; 4] {
+    let mut hasher = Sha3_256::new();
+    hasher.update(domain.to_le_bytes());
+    for limb in elements.iter().flatten() {
+        hasher.update(limb.to_le_bytes());
+    }
+    let digest = hasher.finalize();
+
+    let mut out = [0u64; 4];
+    for (limb, chunk) in out.iter_mut().zip(digest.chunks_exact(8)) {
+        *limb = u64::from_le_bytes(chunk.try_into().unwrap());
+    }
+    out
+}
+
+/// Nullifier revealed by `verify_ownership`, one per wallet and track
+///
+/// One-way, so the revealed value can't be inverted to the wallet hash. It
+/// isn't hiding: anyone who can guess the wallet and track can recompute it
+pub fn ownership_nullifier(wallet_hash: &[u64; 4], track_id: &[u64; 4]) -> [u64; 4] {
+    hash_elements(DOMAIN_NULLIFIER, [*wallet_hash, *track_id, [0; 4]])
+}
+
+/// Rights registry leaf for a (wallet, token, track) claim
+pub fn registry_leaf(wallet_hash: &[u64; 4], token_id: &[u64; 4], track_id: &[u64; 4]) -> [u64; 4] {
+    hash_elements(DOMAIN_LEAF, [*wallet_hash, *token_id, *track_id])
+}
+
+/// Parent of two Merkle nodes
+pub fn hash_pair(left: &[u64; 4], right: &[u64; 4]) -> [u64; 4] {
+    hash_elements(DOMAIN_NODE, [*left, *right, [0; 4]])
+}
+
+/// Root reached from `leaf` by a proof (`indices[i]` is 0 when the node at
+/// level `i` is a left child)
+pub fn merkle_root(
+    leaf: &[u64; 4],
+    path: &[[u64; 4]; TREE_DEPTH],
+    indices: &[u8; TREE_DEPTH],
+) -> [u64; 4] {
+    path.iter()
+        .zip(indices)
+        .fold(*leaf, |node, (sibling, index)| {
+            if *index == 0 {
+                hash_pair(&node, sibling)
+            } else {
+                hash_pair(sibling, &node)
+            }
+        })
+}
+
+/// Commitment to a holder secret; holdings trees are built from these, so
+/// the secret itself never leaves the holder or the MPC
+pub fn holder_commitment(holder_secret: &[u64; 4]) -> [u64; 4] {
+    hash_elements(DOMAIN_HOLDER, [*holder_secret, [0; 4], [0; 4]])
+}
+
+/// Holdings leaf committing a holder to its voting weight. The weight is
+/// absorbed as its own element
+pub fn holding_leaf(commitment: &[u64; 4], weight: u64) -> [u64; 4] {
+    hash_elements(DOMAIN_HOLDING, [*commitment, [weight, 0, 0, 0], [0; 4]])
+}
+
+/// Nullifier revealed by `cast_royalty_vote`, one per holding and vote.
+/// Derived from the secret the leaf commits to, so it can't be computed
+/// from the holdings tree
+pub fn vote_nullifier(holder_secret: &[u64; 4], vote_id: &[u64; 4]) -> [u64; 4] {
+    hash_elements(DOMAIN_VOTE_NULLIFIER, [*holder_secret, *vote_id, [0; 4]])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WALLET: [u64; 4] = [1, 2, 3, 4];
+    const TRACK: [u64; 4] = [5, 6, 7, 8];
+
+    #[test]
+    fn ownership_nullifier_is_per_wallet_and_track() {
+        let nullifier = ownership_nullifier(&WALLET, &TRACK);
+        assert_eq!(nullifier, ownership_nullifier(&WALLET, &TRACK));
+        assert_ne!(nullifier, ownership_nullifier(&[1, 2, 3, 5], &TRACK));
+        assert_ne!(nullifier, ownership_nullifier(&WALLET, &[5, 6, 7, 9]));
+        // Swapping inputs doesn't collide, unlike an xor combination
+        assert_ne!(nullifier, ownership_nullifier(&TRACK, &WALLET));
+    }
+
+    const SECRET: [u64; 4] = [11, 12, 13, 14];
+    const VOTE_ID: [u64; 4] = [21, 22, 23, 24];
+
+    /// Holdings tree with `leaf` at index 0 and `other` at index 1, and
+    /// the proof for index 0
+    fn two_leaf_tree(
+        leaf: &[u64; 4],
+        other: &[u64; 4],
+    ) -> ([u64; 4], [[u64; 4]; TREE_DEPTH], [u8; TREE_DEPTH]) {
+        let mut path = [[0u64; 4]; TREE_DEPTH];
+        path[0] = *other;
+        let indices = [0u8; TREE_DEPTH];
+        (merkle_root(leaf, &path, &indices), path, indices)
+    }
+
+    #[test]
+    fn forged_holding_is_rejected() {
+        let commitment = holder_commitment(&SECRET);
+        let weight = 10;
+        let forged_weight = 1_000_000;
+
+        let (root, path, indices) = two_leaf_tree(
+            &holding_leaf(&commitment, weight),
+            &holding_leaf(&[9; 4], 5),
+        );
+        let proven = |commitment: &[u64; 4], weight| {
+            merkle_root(&holding_leaf(commitment, weight), &path, &indices) == root
+        };
+        assert!(proven(&commitment, weight));
+        // Neither a bigger weight for the holder, nor another commitment
+        // with the holder's weight or the sibling's
+        assert!(!proven(&commitment, forged_weight));
+        assert!(!proven(&holder_commitment(&[11, 12, 13, 15]), weight));
+        assert!(!proven(&[9; 4], weight));
+    }
+
+    #[test]
+    fn vote_nullifier_is_per_secret_and_vote() {
+        let nullifier = vote_nullifier(&SECRET, &VOTE_ID);
+        assert_ne!(nullifier, vote_nullifier(&SECRET, &[21, 22, 23, 25]));
+        assert_ne!(nullifier, vote_nullifier(&[11, 12, 13, 15], &VOTE_ID));
+        // Not computable from what the holdings tree publishes
+        let commitment = holder_commitment(&SECRET);
+        assert_ne!(nullifier, vote_nullifier(&commitment, &VOTE_ID));
+        assert_ne!(nullifier, holding_leaf(&commitment, 10));
+    }
+
+    #[test]
+    fn registry_proof_reaches_root() {
+        let leaf = registry_leaf(&WALLET, &[3; 4], &TRACK);
+        let (root, path, indices) = two_leaf_tree(&leaf, &[7; 4]);
+        assert_eq!(root, merkle_root(&leaf, &path, &indices));
+        // Swapping the claimed token and track is a different leaf
+        assert_ne!(
+            root,
+            merkle_root(&registry_leaf(&WALLET, &TRACK, &[3; 4]), &path, &indices)
+        );
+    }
+
+    #[test]
+    fn domain_separates_equal_inputs() {
+        let elements = [WALLET, TRACK, [0; 4]];
+        assert_ne!(
+            hash_elements(DOMAIN_NULLIFIER, elements),
+            hash_elements(DOMAIN_NULLIFIER + 1, elements)
+        );
+    }
+
+    /// The circuits' hash helpers, compiled against the sha3 crate
+    #[allow(dead_code, clippy::needless_range_loop)]
+    mod circuit {
+        /// Stand-in for Arcis' SHA3-256 gadget
+        #[allow(non_camel_case_types)]
+        struct SHA3_256;
+
+        impl SHA3_256 {
+            fn new() -> Self {
+                SHA3_256
+            }
+
+            fn digest(&self, bytes: &[u8]) -> [u8; 32] {
+                use sha3::{Digest, Sha3_256};
+                Sha3_256::digest(bytes).into()
+            }
+        }
+
+        include!(concat!(env!("OUT_DIR"), "/circuit_helpers.rs"));
+
+        mod mirror {
+            use super::*;
+            use crate::hash;
+
+            const A: [u64; 4] = [1, 2, 3, 4];
+            const B: [u64; 4] = [5, 6, 7, 8];
+            const C: [u64; 4] = [u64::MAX, 0, 1 << 63, 9];
+
+            #[test]
+            fn domains_match() {
+                assert_eq!(HASH_ARITY, hash::HASH_ARITY);
+                assert_eq!(DOMAIN_NULLIFIER, hash::DOMAIN_NULLIFIER);
+                assert_eq!(DOMAIN_LEAF, hash::DOMAIN_LEAF);
+                assert_eq!(DOMAIN_NODE, hash::DOMAIN_NODE);
+                assert_eq!(DOMAIN_HOLDER, hash::DOMAIN_HOLDER);
+                assert_eq!(DOMAIN_HOLDING, hash::DOMAIN_HOLDING);
+                assert_eq!(DOMAIN_VOTE_NULLIFIER, hash::DOMAIN_VOTE_NULLIFIER);
+            }
+
+            #[test]
+            fn hashes_match() {
+                assert_eq!(
+                    hash_elements(7, [A, B, C]),
+                    hash::hash_elements(7, [A, B, C])
+                );
+                assert_eq!(compute_nullifier(&A, &B), hash::ownership_nullifier(&A, &B));
+                assert_eq!(
+                    compute_leaf_hash(&A, &B, &C),
+                    hash::registry_leaf(&A, &B, &C)
+                );
+                assert_eq!(hash_pair(&A, &C), hash::hash_pair(&A, &C));
+                assert_eq!(compute_holder_commitment(&C), hash::holder_commitment(&C));
+                assert_eq!(
+                    compute_holding_leaf(&C, 42),
+                    hash::holding_leaf(&hash::holder_commitment(&C), 42)
+                );
+                assert_eq!(compute_vote_nullifier(&C, &A), hash::vote_nullifier(&C, &A));
+            }
+
+            #[test]
+            fn merkle_roots_match() {
+                let mut path = [[0u64; 4]; hash::TREE_DEPTH];
+                let mut indices = [0u8; hash::TREE_DEPTH];
+                for (level, (node, index)) in path.iter_mut().zip(&mut indices).enumerate() {
+                    *node = [level as u64, 1, 2, 3];
+                    *index = (level % 3 == 1) as u8;
+                }
+                assert_eq!(
+                    compute_merkle_root(&A, &path, &indices),
+                    hash::merkle_root(&A, &path, &indices)
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,14 @@
+// Phantom Streams - Arcium integration rules
+//
+// The Arcium program (programs/phantom-streams-arcium) and the encrypted
+// instructions (encrypted-ixs) build with Arcium's toolchain, outside this
+// workspace. The rules they depend on for safety live here instead, free of
+// Anchor and Arcium, so they're compiled and tested with the workspace:
+// - `computation`: which callbacks a queued computation accepts, and when
+//   it can be cancelled
+// - `ballots`: the order ballots are folded into a vote's tally
+// - `hash`: the hashes the encrypted instructions reveal or compare
+
+pub mod ballots;
+pub mod computation;
+pub mod hash;
@@ -44,8 +44,15 @@ mod circuits {
     pub struct RoyaltyVote {
         /// Encrypted vote choice (0-255 for different split options)
         pub choice: u8,
-        /// Voter's rights weight
+        /// Voter's rights weight, proven against the vote's holdings root
         pub weight: u64,
+        /// Secret the voter's holdings leaf commits to; the tree only holds
+        /// its commitment, and the vote nullifier is derived from it
+        pub holder_secret: [u64; 4],
+        /// Merkle proof of the (holder commitment, weight) holdings leaf
+        pub merkle_path: [[u64; 4]; 20],
+        /// Path direction indicators (0 = left, 1 = right)
+        pub merkle_indices: [u8; 20],
     }
 
     /// Aggregated vote tally (stays encrypted until reveal)
@@ -72,12 +79,17 @@ mod circuits {
     /// - merkle_root: Current root of the rights registry (public)
     ///
     /// Outputs:
-    /// - VerificationResult with is_valid bool and nullifier
+    /// - VerificationResult with is_valid bool and nullifier, encrypted
+    ///   to the owner
+    /// - is_valid and nullifier in plaintext, which the callback uses to
+    ///   spend the nullifier. The nullifier is a one-way hash, so it can't
+    ///   be inverted to the wallet hash, but it isn't hiding: anyone who
+    ///   can guess the wallet and track can recompute it and link them
     #[instruction]
     pub fn verify_ownership(
         input_ctxt: Enc<Shared, RightsOwnership>,
         merkle_root: [u64; 4],
-    ) -> Enc<Shared, VerificationResult> {
+    ) -> (Enc<Shared, VerificationResult>, bool, [u64; 4]) {
         // Decrypt input within MPC (nodes see secret shares, not actual data)
         let ownership = input_ctxt.to_arcis();
 
@@ -100,7 +112,7 @@ mod circuits {
         let roots_match = compare_hashes(&computed_root, &merkle_root);
 
         // Step 4: Compute nullifier (prevents double-verification)
-        // nullifier = H(wallet_hash || track_id || "phantom")
+        // nullifier = SHA3("phantoms" || wallet_hash || track_id)
         let nullifier = compute_nullifier(
             &ownership.wallet_hash,
             &ownership.track_id,
@@ -112,8 +124,13 @@ mod circuits {
             nullifier,
         };
 
-        // Encrypt result back to the owner
-        input_ctxt.owner.from_arcis(result)
+        // Encrypt result back to the owner, and reveal what the program
+        // needs to act on
+        (
+            input_ctxt.owner.from_arcis(result),
+            roots_match.reveal(),
+            nullifier.reveal(),
+        )
     }
 
     /// Initialize empty vote tally for royalty decisions
@@ -132,28 +149,54 @@ mod circuits {
     /// Cast encrypted vote for royalty split decision
     /// 
     /// Inputs:
-    /// - vote: Encrypted vote choice and weight
+    /// - vote: Encrypted vote choice, weight and holdings proof
     /// - current_tally: Current encrypted vote counts
+    /// - vote_id: Vote being cast in (public)
+    /// - holdings_root: Root of the vote's (holder commitment, weight)
+    ///   holdings tree (public)
     ///
-    /// Output:
-    /// - Updated encrypted tally
+    /// Outputs:
+    /// - Updated encrypted tally (unchanged if the weight isn't proven)
+    /// - Whether the weight was proven, in plaintext
+    /// - Per-vote nullifier in plaintext; the program stores it so each
+    ///   holding is counted once per vote. It's a hash of the holder
+    ///   secret, so it can't be linked to the holding without the secret
     #[instruction]
     pub fn cast_royalty_vote(
         vote_ctxt: Enc<Shared, RoyaltyVote>,
         tally_ctxt: Enc<Mxe, VoteTally>,
-    ) -> Enc<Mxe, VoteTally> {
+        vote_id: [u64; 4],
+        holdings_root: [u64; 4],
+    ) -> (Enc<Mxe, VoteTally>, bool, [u64; 4]) {
         let vote = vote_ctxt.to_arcis();
         let mut tally = tally_ctxt.to_arcis();
 
-        // Increment the chosen option (with bounds check)
+        // Step 1: Prove the weight is the voter's committed holding
+        let leaf = compute_holding_leaf(&vote.holder_secret, vote.weight);
+        let computed_root = compute_merkle_root(
+            &leaf,
+            &vote.merkle_path,
+            &vote.merkle_indices,
+        );
+        let weight_proven = compare_hashes(&computed_root, &holdings_root);
+
+        // Step 2: Increment the chosen option (with bounds check)
         let choice_idx = vote.choice as usize;
-        if choice_idx < 8 {
+        let counted = weight_proven && choice_idx < 8;
+        if counted {
             tally.counts[choice_idx] += vote.weight;
             tally.total_weight += vote.weight;
         }
 
+        // Step 3: One nullifier per holding and vote
+        let nullifier = compute_vote_nullifier(&vote.holder_secret, &vote_id);
+
         // Re-encrypt updated tally
-        Mxe.from_arcis(tally)
+        (
+            Mxe.from_arcis(tally),
+            counted.reveal(),
+            nullifier.reveal(),
+        )
     }
 
     /// Reveal vote results (only callable by authorized party)
@@ -206,21 +249,13 @@ mod circuits {
     // HELPER FUNCTIONS
     // ========================================
 
-    /// Simple hash combining function (placeholder for Poseidon/Pedersen)
+    /// Rights registry leaf for a (wallet, token, track) claim
     fn compute_leaf_hash(
         wallet: &[u64; 4],
         token: &[u64; 4],
         track: &[u64; 4],
     ) -> [u64; 4] {
-        // In production: use Poseidon or Pedersen hash
-        // This is a simplified XOR-based combination for demo
-        let mut result = [0u64; 4];
-        for i in 0..4 {
-            result[i] = wallet[i] ^ token[i] ^ track[i];
-            // Add some mixing
-            result[i] = result[i].wrapping_mul(0x9e3779b97f4a7c15);
-        }
-        result
+        hash_elements(DOMAIN_LEAF, [*wallet, *token, *track])
     }
 
     /// Compute Merkle root from leaf and path
@@ -248,13 +283,7 @@ mod circuits {
 
     /// Hash two nodes together
     fn hash_pair(left: &[u64; 4], right: &[u64; 4]) -> [u64; 4] {
-        let mut result = [0u64; 4];
-        for i in 0..4 {
-            result[i] = left[i] ^ right[i];
-            result[i] = result[i].wrapping_mul(0x517cc1b727220a95);
-            result[i] ^= result[i] >> 32;
-        }
-        result
+        hash_elements(DOMAIN_NODE, [*left, *right, [0u64; 4]])
     }
 
     /// Compare two hashes for equality
@@ -262,20 +291,79 @@ mod circuits {
         a[0] == b[0] && a[1] == b[1] && a[2] == b[2] && a[3] == b[3]
     }
 
+    /// Commitment to a holder secret, as published in holdings leaves
+    fn compute_holder_commitment(holder_secret: &[u64; 4]) -> [u64; 4] {
+        hash_elements(DOMAIN_HOLDER, [*holder_secret, [0u64; 4], [0u64; 4]])
+    }
+
+    /// Holdings leaf committing a holder secret to its voting weight
+    ///
+    /// The weight is absorbed as its own element, so no other (secret,
+    /// weight) pair reaches the same leaf
+    fn compute_holding_leaf(holder_secret: &[u64; 4], weight: u64) -> [u64; 4] {
+        let commitment = compute_holder_commitment(holder_secret);
+        hash_elements(DOMAIN_HOLDING, [commitment, [weight, 0, 0, 0], [0u64; 4]])
+    }
+
+    /// Compute per-vote nullifier so a holding is counted once per vote
+    ///
+    /// Derived from the secret the leaf commits to, not anything public, so
+    /// the revealed value doesn't identify the holding
+    fn compute_vote_nullifier(holder_secret: &[u64; 4], vote_id: &[u64; 4]) -> [u64; 4] {
+        hash_elements(DOMAIN_VOTE_NULLIFIER, [*holder_secret, *vote_id, [0u64; 4]])
+    }
+
     /// Compute nullifier to prevent replay attacks
+    ///
+    /// Revealed, so it must be one-way: an xor-and-multiply combination
+    /// inverts straight back to the wallet hash
     fn compute_nullifier(wallet: &[u64; 4], track: &[u64; 4]) -> [u64; 4] {
-        // Domain separator: "phantom" as u64s
-        let domain: [u64; 4] = [
-            0x7068616e746f6d73, // "phantoms"
-            0x747265616d730000, // "treams"
-            0x0000000000000000,
-            0x0000000000000001, // version
-        ];
+        hash_elements(DOMAIN_NULLIFIER, [*wallet, *track, [0u64; 4]])
+    }
+
+    /// Elements absorbed by every hash; unused ones are zero
+    const HASH_ARITY: usize = 3;
+
+    /// Domain tag for ownership nullifiers ("phantoms")
+    const DOMAIN_NULLIFIER: u64 = 0x7068616e746f6d73;
+
+    /// Domain tag for rights registry leaves ("rightslf")
+    const DOMAIN_LEAF: u64 = 0x7269676874736c66;
+
+    /// Domain tag for Merkle nodes ("merkleno")
+    const DOMAIN_NODE: u64 = 0x6d65726b6c656e6f;
+
+    /// Domain tag for holder commitments ("holdrcmt")
+    const DOMAIN_HOLDER: u64 = 0x686f6c6472636d74;
+
+    /// Domain tag for holdings leaves ("holdings")
+    const DOMAIN_HOLDING: u64 = 0x686f6c64696e6773;
+
+    /// Domain tag for vote nullifiers ("phantomv")
+    const DOMAIN_VOTE_NULLIFIER: u64 = 0x7068616e746f6d76;
+
+    /// SHA3-256 of a domain tag followed by a fixed number of elements
+    /// (four little-endian u64 limbs each), as four little-endian limbs
+    ///
+    /// Mirrored in phantom_streams_mpc::hash, whose tests compile this
+    /// section (HELPER FUNCTIONS to the end of the module) and check the
+    /// mirror against it
+    fn hash_elements(domain: u64, elements: [[u64; 4]; HASH_ARITY]) -> [u64; 4] {
+        let mut bytes = [0u8; 8 + 32 * HASH_ARITY];
+        bytes[..8].copy_from_slice(&domain.to_le_bytes());
+        for i in 0..HASH_ARITY {
+            for j in 0..4 {
+                let at = 8 + 32 * i + 8 * j;
+                bytes[at..at + 8].copy_from_slice(&elements[i][j].to_le_bytes());
+            }
+        }
 
+        let digest = SHA3_256::new().digest(&bytes);
         let mut result = [0u64; 4];
         for i in 0..4 {
-            result[i] = wallet[i] ^ track[i] ^ domain[i];
-            result[i] = result[i].wrapping_mul(0x85ebca6b);
+            let mut limb = [0u8; 8];
+            limb.copy_from_slice(&digest[8 * i..8 * i + 8]);
+            result[i] = u64::from_le_bytes(limb);
         }
         result
     }
@@ -0,0 +1,58 @@
+// Phantom Streams - Arcium callback checks
+//
+// Callbacks are `#[arcium_callback]` instructions: their outputs are only
+// used after `verify_output` checks the cluster signed them for the
+// computation account, which is derived from the MXE and the offset the
+// computation was queued with. The record checks (still queued, same
+// instruction and target, inputs unchanged) live in
+// `phantom_streams_mpc::computation`, where they're tested, along with the
+// timeout rule for cancelling, and the ballot queue rules in
+// `phantom_streams_mpc::ballots`; this maps their rejections to program
+// errors.
+
+use anchor_lang::prelude::*;
+use phantom_streams_mpc::ballots::QueueError;
+use phantom_streams_mpc::computation::{self, Rejection};
+
+use crate::{ComputationKind, PendingComputation, PhantomError, Vote};
+
+pub use phantom_streams_mpc::computation::nullifier_bytes;
+
+/// Check a pending computation record matches the callback it's used in
+pub fn check_pending(
+    pending: &PendingComputation,
+    kind: ComputationKind,
+    target: &Pubkey,
+    snapshot: &[u8; 32],
+) -> Result<()> {
+    pending
+        .computation
+        .check_callback(kind, target, snapshot)
+        .map_err(rejection_error)
+}
+
+/// Program error for a rejected callback or cancellation
+pub fn rejection_error(rejection: Rejection) -> Error {
+    match rejection {
+        Rejection::WrongComputation => error!(PhantomError::InvalidCallback),
+        Rejection::NotQueued => error!(PhantomError::ComputationNotQueued),
+        Rejection::Stale => error!(PhantomError::StaleComputation),
+        Rejection::NotTimedOut => error!(PhantomError::ComputationNotTimedOut),
+    }
+}
+
+/// Program error for a refused ballot queue operation
+pub fn queue_error(error: QueueError) -> Error {
+    match error {
+        QueueError::Overflow => error!(PhantomError::Overflow),
+        QueueError::NoBallotsQueued => error!(PhantomError::NoBallotsQueued),
+        QueueError::FoldInFlight => error!(PhantomError::FoldInFlight),
+        QueueError::NotInFlight => error!(PhantomError::FoldNotInFlight),
+        QueueError::BallotsPending => error!(PhantomError::BallotsPending),
+    }
+}
+
+/// Snapshot of a vote's encrypted tally
+pub fn tally_snapshot(vote: &Vote) -> [u8; 32] {
+    computation::tally_snapshot(vote.ballots.tally_version)
+}
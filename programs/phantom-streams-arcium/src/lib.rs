@@ -5,8 +5,17 @@
 // For Solana Privacy Hackathon 2026
 
 use anchor_lang::prelude::*;
+use anchor_lang::solana_program::sysvar::instructions as sysvar_instructions;
+use anchor_lang::system_program;
 use arcium_anchor::prelude::*;
 
+pub mod callback;
+
+pub use phantom_streams_mpc::ballots::BallotQueue;
+pub use phantom_streams_mpc::computation::{
+    ComputationKind, ComputationStatus, QueuedComputation, COMPUTATION_TIMEOUT_SLOTS,
+};
+
 // Computation definition offsets for each encrypted instruction
 const COMP_DEF_OFFSET_VERIFY_OWNERSHIP: u32 = comp_def_offset("verify_ownership");
 const COMP_DEF_OFFSET_INIT_VOTE_TALLY: u32 = comp_def_offset("init_vote_tally");
@@ -30,6 +39,7 @@ pub mod phantom_streams {
         state.authority = ctx.accounts.authority.key();
         state.merkle_root = [0u8; 32];
         state.verification_count = 0;
+        state.computation_fee = 0;
         state.bump = ctx.bumps.state;
 
         msg!("Phantom Streams initialized with Arcium MPC");
@@ -91,6 +101,36 @@ pub mod phantom_streams {
         Ok(())
     }
 
+    /// Set the Arcium fee escrowed for each computation (only authority)
+    ///
+    /// Requesters put it up when they queue a computation, and voters when
+    /// they cast a ballot, for the fold that counts it; it's paid to
+    /// Arcium's fee pool when the callback arrives, or refunded if the
+    /// computation is cancelled. Records already queued keep their fee
+    pub fn set_computation_fee(
+        ctx: Context<UpdateRoot>,
+        fee: u64,
+    ) -> Result<()> {
+        let state = &mut ctx.accounts.state;
+
+        require!(
+            ctx.accounts.authority.key() == state.authority,
+            PhantomError::Unauthorized
+        );
+
+        let old_fee = state.computation_fee;
+        state.computation_fee = fee;
+
+        emit!(ComputationFeeUpdated {
+            old_fee,
+            new_fee: fee,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        msg!("Computation fee set to {} lamports", fee);
+        Ok(())
+    }
+
     // ========================================
     // PRIVATE OWNERSHIP VERIFICATION (via Arcium MPC)
     // ========================================
@@ -101,6 +141,7 @@ pub mod phantom_streams {
     /// Arcium nodes verify it without seeing the actual wallet
     pub fn verify_ownership(
         ctx: Context<VerifyOwnership>,
+        computation_offset: u64,       // Derives the Arcium computation account
         encrypted_ownership: Vec<u8>,  // Encrypted RightsOwnership struct
         nonce: [u8; 16],               // Encryption nonce
     ) -> Result<()> {
@@ -109,37 +150,117 @@ pub mod phantom_streams {
         // Queue the computation with Arcium
         queue_computation(
             ctx.accounts.arcium_accounts(),
+            computation_offset,
             COMP_DEF_OFFSET_VERIFY_OWNERSHIP,
             &encrypted_ownership,
             &nonce,
             &state.merkle_root,  // Public input: current merkle root
         )?;
 
+        // Record it so only its callback is accepted, and only while the
+        // root it was checked against is current
+        let fee = state.computation_fee;
+        ctx.accounts.pending_computation.set_inner(PendingComputation {
+            computation: QueuedComputation {
+                kind: ComputationKind::VerifyOwnership,
+                status: ComputationStatus::Queued,
+                requester: ctx.accounts.payer.key(),
+                fee_payer: ctx.accounts.payer.key(),
+                fee,
+                target: state.key(),
+                snapshot: state.merkle_root,
+                computation_offset,
+                queued_slot: Clock::get()?.slot,
+            },
+            bump: ctx.bumps.pending_computation,
+        });
+        escrow_fee(
+            &ctx.accounts.pending_computation.to_account_info(),
+            &ctx.accounts.payer.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
+            fee,
+        )?;
+
         msg!("Ownership verification queued with Arcium MPC");
         Ok(())
     }
 
     /// Callback from Arcium after MPC verification completes
+    ///
+    /// The validity bit and nullifier are plaintext outputs of the MPC
+    /// instruction, signed by the cluster for this computation account, so
+    /// neither can be chosen by whoever delivers the result
+    #[arcium_callback(encrypted_ix = "verify_ownership")]
     pub fn verify_ownership_callback(
         ctx: Context<VerifyOwnershipCallback>,
-        encrypted_result: Vec<u8>,  // Encrypted VerificationResult
-        nonce: [u8; 16],
-        nullifier_hash: [u8; 32],   // Extracted from result for nullifier tracking
+        output: SignedComputationOutputs<VerifyOwnershipOutput>,
     ) -> Result<()> {
-        let state = &mut ctx.accounts.state;
-        let nullifier = &mut ctx.accounts.nullifier;
+        // Must be the signed result of a verification we queued
+        let VerifyOwnershipOutput { field_0: result } = output
+            .verify_output(&ctx.accounts.cluster_account, &ctx.accounts.computation_account)
+            .map_err(|_| PhantomError::InvalidComputationOutput)?;
+        let encrypted_result = result.field_0.ciphertexts.concat();  // Encrypted VerificationResult
+        let is_valid = result.field_1;                                // Revealed: Merkle proof checked out
+        let nullifier = result.field_2;                               // Revealed: nullifier for replay tracking
+        callback::check_pending(
+            &ctx.accounts.pending_computation,
+            ComputationKind::VerifyOwnership,
+            &ctx.accounts.state.key(),
+            &ctx.accounts.state.merkle_root,
+        )?;
+
+        let nullifier_hash = callback::nullifier_bytes(&nullifier);
+
+        // A failed verification spends nothing
+        if !is_valid {
+            let fee = ctx.accounts.pending_computation.computation.resolve(false);
+            release_fee(
+                &ctx.accounts.pending_computation.to_account_info(),
+                &ctx.accounts.pool_account.to_account_info(),
+                fee,
+            )?;
+            emit!(OwnershipRejected {
+                nullifier_hash,
+                encrypted_result,
+                timestamp: Clock::get()?.unix_timestamp,
+            });
+            msg!("Ownership verification failed in Arcium MPC");
+            return Ok(());
+        }
 
         // Check nullifier hasn't been used
-        require!(!nullifier.is_used, PhantomError::NullifierAlreadyUsed);
+        let (nullifier_key, nullifier_bump) = Pubkey::find_program_address(
+            &[b"nullifier", nullifier_hash.as_ref()],
+            &crate::ID,
+        );
+        let nullifier_info = ctx.accounts.nullifier.to_account_info();
+        require_keys_eq!(nullifier_info.key(), nullifier_key, PhantomError::InvalidNullifierAccount);
+        require!(nullifier_info.data_is_empty(), PhantomError::NullifierAlreadyUsed);
 
         // Mark nullifier as used
-        nullifier.is_used = true;
-        nullifier.hash = nullifier_hash;
-        nullifier.used_at = Clock::get()?.unix_timestamp;
-        nullifier.bump = ctx.bumps.nullifier;
+        create_nullifier(
+            &nullifier_info,
+            &ctx.accounts.payer.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
+            &[b"nullifier", nullifier_hash.as_ref(), &[nullifier_bump]],
+        )?;
+        let record = NullifierAccount {
+            is_used: true,
+            hash: nullifier_hash,
+            used_at: Clock::get()?.unix_timestamp,
+            bump: nullifier_bump,
+        };
+        record.try_serialize(&mut &mut nullifier_info.try_borrow_mut_data()?[..])?;
 
         // Increment verification count
+        let state = &mut ctx.accounts.state;
         state.verification_count = state.verification_count.saturating_add(1);
+        let fee = ctx.accounts.pending_computation.computation.resolve(true);
+        release_fee(
+            &ctx.accounts.pending_computation.to_account_info(),
+            &ctx.accounts.pool_account.to_account_info(),
+            fee,
+        )?;
 
         // Store encrypted result for user to decrypt
         emit!(OwnershipVerified {
@@ -160,84 +281,341 @@ pub mod phantom_streams {
     /// Create a new royalty vote with encrypted tally
     pub fn create_vote(
         ctx: Context<CreateVote>,
+        computation_offset: u64,
         vote_id: [u8; 32],
         options_count: u8,
         end_time: i64,
+        holdings_root: [u8; 32],  // Root of (holder commitment, weight) leaves
     ) -> Result<()> {
         let vote = &mut ctx.accounts.vote;
         vote.id = vote_id;
         vote.authority = ctx.accounts.authority.key();
         vote.options_count = options_count;
         vote.end_time = end_time;
+        vote.holdings_root = holdings_root;
         vote.is_revealed = false;
+        vote.ballots = BallotQueue::default();
         vote.bump = ctx.bumps.vote;
 
         // Queue init_vote_tally to create encrypted [0,0,0,...] tally
         queue_computation(
             ctx.accounts.arcium_accounts(),
+            computation_offset,
             COMP_DEF_OFFSET_INIT_VOTE_TALLY,
             &[],  // No inputs needed
             &[0u8; 16],
             &[],
         )?;
 
+        let fee = ctx.accounts.state.computation_fee;
+        ctx.accounts.pending_computation.set_inner(PendingComputation {
+            computation: QueuedComputation {
+                kind: ComputationKind::InitVoteTally,
+                status: ComputationStatus::Queued,
+                requester: ctx.accounts.authority.key(),
+                fee_payer: ctx.accounts.authority.key(),
+                fee,
+                target: ctx.accounts.vote.key(),
+                snapshot: callback::tally_snapshot(&ctx.accounts.vote),
+                computation_offset,
+                queued_slot: Clock::get()?.slot,
+            },
+            bump: ctx.bumps.pending_computation,
+        });
+        escrow_fee(
+            &ctx.accounts.pending_computation.to_account_info(),
+            &ctx.accounts.authority.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
+            fee,
+        )?;
+
         msg!("Vote created, initializing encrypted tally");
         Ok(())
     }
 
+    /// Queue the tally initialization again (vote authority only)
+    ///
+    /// For a vote whose init_vote_tally computation was cancelled after
+    /// timing out, since ballots can't be folded until the tally exists.
+    /// Should the original callback still arrive, it's rejected as
+    /// cancelled; if two re-queued ones both arrive, only the first sets
+    /// the tally
+    pub fn requeue_vote_tally(
+        ctx: Context<RequeueVoteTally>,
+        computation_offset: u64,
+    ) -> Result<()> {
+        let vote = &ctx.accounts.vote;
+        require!(
+            ctx.accounts.authority.key() == vote.authority,
+            PhantomError::Unauthorized
+        );
+        require!(vote.encrypted_tally.is_empty(), PhantomError::TallyAlreadyInitialized);
+
+        queue_computation(
+            ctx.accounts.arcium_accounts(),
+            computation_offset,
+            COMP_DEF_OFFSET_INIT_VOTE_TALLY,
+            &[],  // No inputs needed
+            &[0u8; 16],
+            &[],
+        )?;
+
+        let fee = ctx.accounts.state.computation_fee;
+        ctx.accounts.pending_computation.set_inner(PendingComputation {
+            computation: QueuedComputation {
+                kind: ComputationKind::InitVoteTally,
+                status: ComputationStatus::Queued,
+                requester: ctx.accounts.authority.key(),
+                fee_payer: ctx.accounts.authority.key(),
+                fee,
+                target: ctx.accounts.vote.key(),
+                snapshot: callback::tally_snapshot(&ctx.accounts.vote),
+                computation_offset,
+                queued_slot: Clock::get()?.slot,
+            },
+            bump: ctx.bumps.pending_computation,
+        });
+        escrow_fee(
+            &ctx.accounts.pending_computation.to_account_info(),
+            &ctx.accounts.authority.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
+            fee,
+        )?;
+
+        msg!("Vote tally initialization queued again");
+        Ok(())
+    }
+
     /// Callback to receive initialized encrypted tally
+    #[arcium_callback(encrypted_ix = "init_vote_tally")]
     pub fn create_vote_callback(
         ctx: Context<CreateVoteCallback>,
-        encrypted_tally: Vec<u8>,
-        nonce: [u8; 16],
+        output: SignedComputationOutputs<InitVoteTallyOutput>,
     ) -> Result<()> {
+        let InitVoteTallyOutput { field_0: tally } = output
+            .verify_output(&ctx.accounts.cluster_account, &ctx.accounts.computation_account)
+            .map_err(|_| PhantomError::InvalidComputationOutput)?;
+        callback::check_pending(
+            &ctx.accounts.pending_computation,
+            ComputationKind::InitVoteTally,
+            &ctx.accounts.vote.key(),
+            &callback::tally_snapshot(&ctx.accounts.vote),
+        )?;
+
+        // A re-queued initialization may race another one; the first wins
+        let initialized = !ctx.accounts.vote.encrypted_tally.is_empty();
+        let fee = ctx.accounts.pending_computation.computation.resolve(!initialized);
+        release_fee(
+            &ctx.accounts.pending_computation.to_account_info(),
+            &ctx.accounts.pool_account.to_account_info(),
+            fee,
+        )?;
+        if initialized {
+            msg!("Vote tally was already initialized");
+            return Ok(());
+        }
+
         let vote = &mut ctx.accounts.vote;
-        vote.encrypted_tally = encrypted_tally;
-        vote.tally_nonce = nonce;
+        vote.encrypted_tally = tally.ciphertexts.concat();
+        vote.tally_nonce = tally.nonce.to_le_bytes();
 
         msg!("Vote tally initialized");
         Ok(())
     }
 
     /// Cast an encrypted vote
+    ///
+    /// The ballot joins the vote's on-chain queue; `fold_ballot` adds
+    /// ballots to the tally one at a time, in the order they were cast. The
+    /// voter deposits the computation fee for folding it in the ballot, so
+    /// a ballot that's never counted costs whoever cast it, not whoever
+    /// folds the queue
     pub fn cast_vote(
         ctx: Context<CastVote>,
         encrypted_vote: Vec<u8>,  // Encrypted RoyaltyVote
         nonce: [u8; 16],
     ) -> Result<()> {
-        let vote = &ctx.accounts.vote;
+        let vote = &mut ctx.accounts.vote;
 
         // Check vote is still open
         let now = Clock::get()?.unix_timestamp;
         require!(now < vote.end_time, PhantomError::VoteClosed);
         require!(!vote.is_revealed, PhantomError::VoteAlreadyRevealed);
+        require!(
+            encrypted_vote.len() <= Ballot::MAX_VOTE_LEN,
+            PhantomError::InvalidBallot
+        );
+
+        // Append the ballot to the queue
+        let index = vote.ballots.cast().map_err(callback::queue_error)?;
+        ctx.accounts.ballot.set_inner(Ballot {
+            vote: vote.key(),
+            index,
+            voter: ctx.accounts.voter.key(),
+            encrypted_vote,
+            nonce,
+            bump: ctx.bumps.ballot,
+        });
+        escrow_fee(
+            &ctx.accounts.ballot.to_account_info(),
+            &ctx.accounts.voter.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
+            ctx.accounts.state.computation_fee,
+        )?;
+
+        emit!(BallotCast {
+            vote_id: vote.id,
+            index,
+            timestamp: now,
+        });
+
+        msg!("Vote cast, ballot {} queued for the tally", index);
+        Ok(())
+    }
+
+    /// Queue the next ballot to be folded into the encrypted tally
+    ///
+    /// Permissionless. Only one fold is in flight at a time, so each one
+    /// starts from the tally the previous one produced. The fee comes from
+    /// the ballot's deposit; the caller only fronts the record's rent.
+    /// Cancelling a fold whose computation timed out (see
+    /// `cancel_computation`) refunds the deposit to the ballot and releases
+    /// the vote, and the same ballot is folded again here
+    pub fn fold_ballot(
+        ctx: Context<FoldBallot>,
+        computation_offset: u64,
+    ) -> Result<()> {
+        require!(
+            !ctx.accounts.vote.encrypted_tally.is_empty(),
+            PhantomError::TallyNotInitialized
+        );
+
+        // Only one fold at a time; this one is the ballot at the tally version
+        let pending_key = ctx.accounts.pending_computation.key();
+        ctx.accounts.vote.ballots.start_fold(pending_key)
+            .map_err(callback::queue_error)?;
+        let vote = &ctx.accounts.vote;
+        let ballot = &ctx.accounts.ballot;
 
         // Queue cast_royalty_vote computation
-        // Inputs: encrypted_vote + current encrypted_tally
-        let mut inputs = encrypted_vote.clone();
+        // Inputs: ballot's encrypted vote + current encrypted_tally
+        let mut inputs = ballot.encrypted_vote.clone();
         inputs.extend_from_slice(&vote.encrypted_tally);
 
         queue_computation(
             ctx.accounts.arcium_accounts(),
+            computation_offset,
             COMP_DEF_OFFSET_CAST_ROYALTY_VOTE,
             &inputs,
-            &nonce,
-            &[],
+            &ballot.nonce,
+            &[vote.id, vote.holdings_root].concat(),  // Public inputs: nullifier scope and weight root
+        )?;
+
+        // The result replaces the tally it was computed from, so it's only
+        // accepted at the version it was queued against
+        let ballot_info = ballot.to_account_info();
+        let fee = ballot_info
+            .lamports()
+            .saturating_sub(Rent::get()?.minimum_balance(ballot_info.data_len()));
+        ctx.accounts.pending_computation.set_inner(PendingComputation {
+            computation: QueuedComputation {
+                kind: ComputationKind::CastVote,
+                status: ComputationStatus::Queued,
+                requester: ctx.accounts.payer.key(),
+                fee_payer: ballot.key(),
+                fee,
+                target: vote.key(),
+                snapshot: callback::tally_snapshot(vote),
+                computation_offset,
+                queued_slot: Clock::get()?.slot,
+            },
+            bump: ctx.bumps.pending_computation,
+        });
+        release_fee(
+            &ballot_info,
+            &ctx.accounts.pending_computation.to_account_info(),
+            fee,
         )?;
 
-        msg!("Vote cast, updating encrypted tally");
+        msg!("Ballot {} queued for the encrypted tally", ballot.index);
         Ok(())
     }
 
     /// Callback to receive updated encrypted tally after vote
+    ///
+    /// Only counted when the MPC instruction proved the ballot's weight and
+    /// the wallet hasn't voted yet; otherwise the tally is left as it was.
+    /// The ballot is consumed either way and closed, returning its rent to
+    /// the voter
+    #[arcium_callback(encrypted_ix = "cast_royalty_vote")]
     pub fn cast_vote_callback(
         ctx: Context<CastVoteCallback>,
-        new_encrypted_tally: Vec<u8>,
-        nonce: [u8; 16],
+        output: SignedComputationOutputs<CastRoyaltyVoteOutput>,
     ) -> Result<()> {
+        let CastRoyaltyVoteOutput { field_0: result } = output
+            .verify_output(&ctx.accounts.cluster_account, &ctx.accounts.computation_account)
+            .map_err(|_| PhantomError::InvalidComputationOutput)?;
+        let new_tally = result.field_0;
+        let is_valid = result.field_1;        // Revealed: weight proven and choice in range
+        let vote_nullifier = result.field_2;  // Revealed: one per wallet and vote
+        callback::check_pending(
+            &ctx.accounts.pending_computation,
+            ComputationKind::CastVote,
+            &ctx.accounts.vote.key(),
+            &callback::tally_snapshot(&ctx.accounts.vote),
+        )?;
+
+        // Check the wallet hasn't voted in this vote
+        let nullifier_hash = callback::nullifier_bytes(&vote_nullifier);
+        let vote_key = ctx.accounts.vote.key();
+        let (nullifier_key, nullifier_bump) = Pubkey::find_program_address(
+            &[b"vote_nullifier", vote_key.as_ref(), nullifier_hash.as_ref()],
+            &crate::ID,
+        );
+        let nullifier_info = ctx.accounts.vote_nullifier.to_account_info();
+        require_keys_eq!(nullifier_info.key(), nullifier_key, PhantomError::InvalidNullifierAccount);
+        let counted = is_valid && nullifier_info.data_is_empty();
+
+        // The ballot is consumed either way, so the queue keeps moving
+        let pending_key = ctx.accounts.pending_computation.key();
         let vote = &mut ctx.accounts.vote;
-        vote.encrypted_tally = new_encrypted_tally;
-        vote.tally_nonce = nonce;
+        vote.ballots.finish_fold(&pending_key).map_err(callback::queue_error)?;
+
+        let fee = ctx.accounts.pending_computation.computation.resolve(counted);
+        release_fee(
+            &ctx.accounts.pending_computation.to_account_info(),
+            &ctx.accounts.pool_account.to_account_info(),
+            fee,
+        )?;
+
+        if !counted {
+            emit!(VoteRejected {
+                vote_id: vote.id,
+                nullifier_hash,
+                timestamp: Clock::get()?.unix_timestamp,
+            });
+            msg!("Ballot not counted: weight unproven or wallet already voted");
+            return Ok(());
+        }
+
+        // Spend the per-vote nullifier
+        create_nullifier(
+            &nullifier_info,
+            &ctx.accounts.payer.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
+            &[b"vote_nullifier", vote_key.as_ref(), nullifier_hash.as_ref(), &[nullifier_bump]],
+        )?;
+        let record = NullifierAccount {
+            is_used: true,
+            hash: nullifier_hash,
+            used_at: Clock::get()?.unix_timestamp,
+            bump: nullifier_bump,
+        };
+        record.try_serialize(&mut &mut nullifier_info.try_borrow_mut_data()?[..])?;
+
+        let vote = &mut ctx.accounts.vote;
+        vote.encrypted_tally = new_tally.ciphertexts.concat();
+        vote.tally_nonce = new_tally.nonce.to_le_bytes();
 
         emit!(VoteCast {
             vote_id: vote.id,
@@ -251,6 +629,7 @@ pub mod phantom_streams {
     /// Reveal the vote result (authority only, after end_time)
     pub fn reveal_result(
         ctx: Context<RevealResult>,
+        computation_offset: u64,
     ) -> Result<()> {
         let vote = &ctx.accounts.vote;
 
@@ -264,24 +643,70 @@ pub mod phantom_streams {
         require!(now >= vote.end_time, PhantomError::VoteStillOpen);
         require!(!vote.is_revealed, PhantomError::VoteAlreadyRevealed);
 
+        // Every ballot must be in the tally before it's revealed
+        vote.ballots.check_reveal().map_err(callback::queue_error)?;
+
         // Queue reveal computation
         queue_computation(
             ctx.accounts.arcium_accounts(),
+            computation_offset,
             COMP_DEF_OFFSET_REVEAL_VOTE_RESULT,
             &vote.encrypted_tally,
             &vote.tally_nonce,
             &[],
         )?;
 
+        // A vote callback still in flight would change the tally after
+        // this reveal read it
+        let fee = ctx.accounts.state.computation_fee;
+        ctx.accounts.pending_computation.set_inner(PendingComputation {
+            computation: QueuedComputation {
+                kind: ComputationKind::RevealResult,
+                status: ComputationStatus::Queued,
+                requester: ctx.accounts.authority.key(),
+                fee_payer: ctx.accounts.authority.key(),
+                fee,
+                target: vote.key(),
+                snapshot: callback::tally_snapshot(vote),
+                computation_offset,
+                queued_slot: Clock::get()?.slot,
+            },
+            bump: ctx.bumps.pending_computation,
+        });
+        escrow_fee(
+            &ctx.accounts.pending_computation.to_account_info(),
+            &ctx.accounts.authority.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
+            fee,
+        )?;
+
         msg!("Vote reveal queued");
         Ok(())
     }
 
     /// Callback with revealed winner
+    #[arcium_callback(encrypted_ix = "reveal_vote_result")]
     pub fn reveal_result_callback(
         ctx: Context<RevealResultCallback>,
-        winning_option: u8,
+        output: SignedComputationOutputs<RevealVoteResultOutput>,
     ) -> Result<()> {
+        let RevealVoteResultOutput { field_0: winning_option } = output
+            .verify_output(&ctx.accounts.cluster_account, &ctx.accounts.computation_account)
+            .map_err(|_| PhantomError::InvalidComputationOutput)?;
+        callback::check_pending(
+            &ctx.accounts.pending_computation,
+            ComputationKind::RevealResult,
+            &ctx.accounts.vote.key(),
+            &callback::tally_snapshot(&ctx.accounts.vote),
+        )?;
+        require!(!ctx.accounts.vote.is_revealed, PhantomError::VoteAlreadyRevealed);
+
+        let fee = ctx.accounts.pending_computation.computation.resolve(true);
+        release_fee(
+            &ctx.accounts.pending_computation.to_account_info(),
+            &ctx.accounts.pool_account.to_account_info(),
+            fee,
+        )?;
         let vote = &mut ctx.accounts.vote;
         vote.is_revealed = true;
         vote.winning_option = Some(winning_option);
@@ -295,6 +720,79 @@ pub mod phantom_streams {
         msg!("Vote result revealed: option {}", winning_option);
         Ok(())
     }
+
+    // ========================================
+    // PENDING COMPUTATIONS
+    // ========================================
+
+    /// Give up on a computation whose callback never arrived
+    ///
+    /// Permissionless after COMPUTATION_TIMEOUT_SLOTS, so a stuck fold
+    /// can't block a vote until its requester returns. Its callback is
+    /// rejected from then on, and the fee escrowed when it was queued is
+    /// refunded to `fee_payer`. A fold must be cancelled with its `vote`,
+    /// which is released so the same ballot can be folded again and the
+    /// record closed
+    pub fn cancel_computation(
+        ctx: Context<CancelComputation>,
+    ) -> Result<()> {
+        let key = ctx.accounts.pending_computation.key();
+        let pending = &mut ctx.accounts.pending_computation.computation;
+        let refunded = pending
+            .cancel(Clock::get()?.slot)
+            .map_err(callback::rejection_error)?;
+
+        // Release the vote from a fold, or it would wait on this record forever
+        if pending.kind == ComputationKind::CastVote {
+            let vote = ctx.accounts.vote.as_mut()
+                .ok_or(PhantomError::InvalidVoteAccount)?;
+            require_keys_eq!(vote.key(), pending.target, PhantomError::InvalidVoteAccount);
+            vote.ballots.abandon_fold(&key).map_err(callback::queue_error)?;
+        }
+
+        let kind = pending.kind;
+        let requester = pending.requester;
+        release_fee(
+            &ctx.accounts.pending_computation.to_account_info(),
+            &ctx.accounts.fee_payer.to_account_info(),
+            refunded,
+        )?;
+
+        emit!(ComputationCancelled {
+            computation: key,
+            kind,
+            requester,
+            refunded,
+            cancelled_by: ctx.accounts.caller.key(),
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        msg!("Computation timed out; {} lamports refunded", refunded);
+        Ok(())
+    }
+
+    /// Close a resolved computation record, returning the record's rent to
+    /// the requester. Its escrowed fee was already paid to Arcium or
+    /// refunded when it was resolved
+    pub fn close_computation(
+        ctx: Context<CloseComputation>,
+    ) -> Result<()> {
+        let pending = &ctx.accounts.pending_computation;
+        require!(
+            pending.computation.status != ComputationStatus::Queued,
+            PhantomError::ComputationStillQueued
+        );
+
+        emit!(ComputationClosed {
+            computation: pending.key(),
+            requester: pending.computation.requester,
+            rent_returned: pending.to_account_info().lamports(),
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        msg!("Computation record closed; rent returned to requester");
+        Ok(())
+    }
 }
 
 // ========================================
@@ -346,6 +844,7 @@ pub struct UpdateRoot<'info> {
 }
 
 #[derive(Accounts)]
+#[instruction(computation_offset: u64)]
 pub struct VerifyOwnership<'info> {
     #[account(seeds = [b"state"], bump = state.bump)]
     pub state: Account<'info, ProtocolState>,
@@ -367,33 +866,91 @@ pub struct VerifyOwnership<'info> {
     /// CHECK: Arcium program
     pub arcium_program: UncheckedAccount<'info>,
 
+    #[account(address = derive_mxe_pda!())]
+    pub mxe_account: Account<'info, MXEAccount>,
+
+    /// CHECK: Arcium computation account this job is queued in, derived
+    /// from the MXE and `computation_offset`
+    #[account(
+        mut,
+        address = derive_comp_pda!(computation_offset, mxe_account, PhantomError::ClusterNotSet)
+    )]
+    pub computation_account: UncheckedAccount<'info>,
+
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + PendingComputation::SIZE,
+        seeds = [b"pending_computation", computation_account.key().as_ref()],
+        bump
+    )]
+    pub pending_computation: Account<'info, PendingComputation>,
+
     pub system_program: Program<'info, System>,
 }
 
+#[callback_accounts("verify_ownership")]
 #[derive(Accounts)]
-#[instruction(encrypted_result: Vec<u8>, nonce: [u8; 16], nullifier_hash: [u8; 32])]
 pub struct VerifyOwnershipCallback<'info> {
     #[account(mut, seeds = [b"state"], bump = state.bump)]
     pub state: Account<'info, ProtocolState>,
 
-    #[account(
-        init,
-        payer = payer,
-        space = 8 + NullifierAccount::SIZE,
-        seeds = [b"nullifier", nullifier_hash.as_ref()],
-        bump
-    )]
-    pub nullifier: Account<'info, NullifierAccount>,
+    /// CHECK: Nullifier PDA for the revealed nullifier; checked and only
+    /// created in the handler, since an invalid result must not spend it
+    #[account(mut)]
+    pub nullifier: UncheckedAccount<'info>,
 
     #[account(mut)]
     pub payer: Signer<'info>,
 
+    // Callback authentication
+    pub arcium_program: Program<'info, Arcium>,
+
+    #[account(address = derive_comp_def_pda!(COMP_DEF_OFFSET_VERIFY_OWNERSHIP))]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+
+    #[account(address = derive_mxe_pda!())]
+    pub mxe_account: Account<'info, MXEAccount>,
+
+    /// CHECK: Arcium computation account the result was computed in,
+    /// derived from the MXE and the offset it was queued with
+    #[account(
+        address = derive_comp_pda!(
+            pending_computation.computation.computation_offset,
+            mxe_account,
+            PhantomError::ClusterNotSet
+        )
+    )]
+    pub computation_account: UncheckedAccount<'info>,
+
+    /// Cluster whose signature `verify_output` checks
+    #[account(address = derive_cluster_pda!(mxe_account, PhantomError::ClusterNotSet))]
+    pub cluster_account: Account<'info, Cluster>,
+
+    #[account(
+        mut,
+        seeds = [b"pending_computation", computation_account.key().as_ref()],
+        bump = pending_computation.bump
+    )]
+    pub pending_computation: Account<'info, PendingComputation>,
+
+    /// Arcium fee pool the escrowed fee is paid into
+    #[account(mut, address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS)]
+    pub pool_account: Account<'info, FeePool>,
+
+    /// CHECK: Instructions sysvar, checked by the Arcium program
+    #[account(address = sysvar_instructions::ID)]
+    pub instructions_sysvar: UncheckedAccount<'info>,
+
     pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
-#[instruction(vote_id: [u8; 32])]
+#[instruction(computation_offset: u64, vote_id: [u8; 32])]
 pub struct CreateVote<'info> {
+    #[account(seeds = [b"state"], bump = state.bump)]
+    pub state: Account<'info, ProtocolState>,
+
     #[account(
         init,
         payer = authority,
@@ -420,23 +977,164 @@ pub struct CreateVote<'info> {
     /// CHECK: Arcium program
     pub arcium_program: UncheckedAccount<'info>,
 
+    #[account(address = derive_mxe_pda!())]
+    pub mxe_account: Account<'info, MXEAccount>,
+
+    /// CHECK: Arcium computation account this job is queued in, derived
+    /// from the MXE and `computation_offset`
+    #[account(
+        mut,
+        address = derive_comp_pda!(computation_offset, mxe_account, PhantomError::ClusterNotSet)
+    )]
+    pub computation_account: UncheckedAccount<'info>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + PendingComputation::SIZE,
+        seeds = [b"pending_computation", computation_account.key().as_ref()],
+        bump
+    )]
+    pub pending_computation: Account<'info, PendingComputation>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(computation_offset: u64)]
+pub struct RequeueVoteTally<'info> {
+    #[account(seeds = [b"state"], bump = state.bump)]
+    pub state: Account<'info, ProtocolState>,
+
+    pub vote: Account<'info, Vote>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    // Arcium accounts
+    /// CHECK: Arcium mempool
+    #[account(mut)]
+    pub mempool: UncheckedAccount<'info>,
+    
+    /// CHECK: Arcium cluster
+    pub cluster: UncheckedAccount<'info>,
+    
+    /// CHECK: Computation definition
+    pub comp_def: UncheckedAccount<'info>,
+    
+    /// CHECK: Arcium program
+    pub arcium_program: UncheckedAccount<'info>,
+
+    #[account(address = derive_mxe_pda!())]
+    pub mxe_account: Account<'info, MXEAccount>,
+
+    /// CHECK: Arcium computation account this job is queued in, derived
+    /// from the MXE and `computation_offset`
+    #[account(
+        mut,
+        address = derive_comp_pda!(computation_offset, mxe_account, PhantomError::ClusterNotSet)
+    )]
+    pub computation_account: UncheckedAccount<'info>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + PendingComputation::SIZE,
+        seeds = [b"pending_computation", computation_account.key().as_ref()],
+        bump
+    )]
+    pub pending_computation: Account<'info, PendingComputation>,
+
     pub system_program: Program<'info, System>,
 }
 
+#[callback_accounts("init_vote_tally")]
 #[derive(Accounts)]
 pub struct CreateVoteCallback<'info> {
     #[account(mut)]
     pub vote: Account<'info, Vote>,
+
+    // Callback authentication
+    pub arcium_program: Program<'info, Arcium>,
+
+    #[account(address = derive_comp_def_pda!(COMP_DEF_OFFSET_INIT_VOTE_TALLY))]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+
+    #[account(address = derive_mxe_pda!())]
+    pub mxe_account: Account<'info, MXEAccount>,
+
+    /// CHECK: Arcium computation account the result was computed in,
+    /// derived from the MXE and the offset it was queued with
+    #[account(
+        address = derive_comp_pda!(
+            pending_computation.computation.computation_offset,
+            mxe_account,
+            PhantomError::ClusterNotSet
+        )
+    )]
+    pub computation_account: UncheckedAccount<'info>,
+
+    /// Cluster whose signature `verify_output` checks
+    #[account(address = derive_cluster_pda!(mxe_account, PhantomError::ClusterNotSet))]
+    pub cluster_account: Account<'info, Cluster>,
+
+    #[account(
+        mut,
+        seeds = [b"pending_computation", computation_account.key().as_ref()],
+        bump = pending_computation.bump
+    )]
+    pub pending_computation: Account<'info, PendingComputation>,
+
+    /// Arcium fee pool the escrowed fee is paid into
+    #[account(mut, address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS)]
+    pub pool_account: Account<'info, FeePool>,
+
+    /// CHECK: Instructions sysvar, checked by the Arcium program
+    #[account(address = sysvar_instructions::ID)]
+    pub instructions_sysvar: UncheckedAccount<'info>,
 }
 
 #[derive(Accounts)]
 pub struct CastVote<'info> {
+    #[account(seeds = [b"state"], bump = state.bump)]
+    pub state: Account<'info, ProtocolState>,
+
     #[account(mut)]
     pub vote: Account<'info, Vote>,
 
+    #[account(
+        init,
+        payer = voter,
+        space = 8 + Ballot::SIZE,
+        seeds = [b"ballot", vote.key().as_ref(), vote.ballots.ballots_cast.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub ballot: Account<'info, Ballot>,
+
     #[account(mut)]
     pub voter: Signer<'info>,
 
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(computation_offset: u64)]
+pub struct FoldBallot<'info> {
+    #[account(mut)]
+    pub vote: Account<'info, Vote>,
+
+    /// Next ballot to fold, at the tally's current version; its deposit
+    /// pays the fee
+    #[account(
+        mut,
+        seeds = [b"ballot", vote.key().as_ref(), vote.ballots.tally_version.to_le_bytes().as_ref()],
+        bump = ballot.bump
+    )]
+    pub ballot: Account<'info, Ballot>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
     // Arcium accounts
     /// CHECK: Arcium mempool
     #[account(mut)]
@@ -451,20 +1149,110 @@ pub struct CastVote<'info> {
     /// CHECK: Arcium program
     pub arcium_program: UncheckedAccount<'info>,
 
+    #[account(address = derive_mxe_pda!())]
+    pub mxe_account: Account<'info, MXEAccount>,
+
+    /// CHECK: Arcium computation account this job is queued in, derived
+    /// from the MXE and `computation_offset`
+    #[account(
+        mut,
+        address = derive_comp_pda!(computation_offset, mxe_account, PhantomError::ClusterNotSet)
+    )]
+    pub computation_account: UncheckedAccount<'info>,
+
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + PendingComputation::SIZE,
+        seeds = [b"pending_computation", computation_account.key().as_ref()],
+        bump
+    )]
+    pub pending_computation: Account<'info, PendingComputation>,
+
     pub system_program: Program<'info, System>,
 }
 
+#[callback_accounts("cast_royalty_vote")]
 #[derive(Accounts)]
 pub struct CastVoteCallback<'info> {
     #[account(mut)]
     pub vote: Account<'info, Vote>,
+
+    /// Ballot the fold consumed, at the tally's version before this callback
+    #[account(
+        mut,
+        close = voter,
+        has_one = voter,
+        seeds = [b"ballot", vote.key().as_ref(), vote.ballots.tally_version.to_le_bytes().as_ref()],
+        bump = ballot.bump
+    )]
+    pub ballot: Account<'info, Ballot>,
+
+    /// CHECK: Voter who cast the ballot, refunded its rent
+    #[account(mut)]
+    pub voter: UncheckedAccount<'info>,
+
+    /// CHECK: Per-vote nullifier PDA for the revealed nullifier; checked
+    /// and only created in the handler, since an uncounted ballot must not
+    /// spend it
+    #[account(mut)]
+    pub vote_nullifier: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    // Callback authentication
+    pub arcium_program: Program<'info, Arcium>,
+
+    #[account(address = derive_comp_def_pda!(COMP_DEF_OFFSET_CAST_ROYALTY_VOTE))]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+
+    #[account(address = derive_mxe_pda!())]
+    pub mxe_account: Account<'info, MXEAccount>,
+
+    /// CHECK: Arcium computation account the result was computed in,
+    /// derived from the MXE and the offset it was queued with
+    #[account(
+        address = derive_comp_pda!(
+            pending_computation.computation.computation_offset,
+            mxe_account,
+            PhantomError::ClusterNotSet
+        )
+    )]
+    pub computation_account: UncheckedAccount<'info>,
+
+    /// Cluster whose signature `verify_output` checks
+    #[account(address = derive_cluster_pda!(mxe_account, PhantomError::ClusterNotSet))]
+    pub cluster_account: Account<'info, Cluster>,
+
+    #[account(
+        mut,
+        seeds = [b"pending_computation", computation_account.key().as_ref()],
+        bump = pending_computation.bump
+    )]
+    pub pending_computation: Account<'info, PendingComputation>,
+
+    /// Arcium fee pool the escrowed fee is paid into
+    #[account(mut, address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS)]
+    pub pool_account: Account<'info, FeePool>,
+
+    /// CHECK: Instructions sysvar, checked by the Arcium program
+    #[account(address = sysvar_instructions::ID)]
+    pub instructions_sysvar: UncheckedAccount<'info>,
+
+    pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
+#[instruction(computation_offset: u64)]
 pub struct RevealResult<'info> {
+    #[account(seeds = [b"state"], bump = state.bump)]
+    pub state: Account<'info, ProtocolState>,
+
     #[account(mut)]
     pub vote: Account<'info, Vote>,
 
+    #[account(mut)]
     pub authority: Signer<'info>,
 
     // Arcium accounts
@@ -480,12 +1268,201 @@ pub struct RevealResult<'info> {
     
     /// CHECK: Arcium program
     pub arcium_program: UncheckedAccount<'info>,
+
+    #[account(address = derive_mxe_pda!())]
+    pub mxe_account: Account<'info, MXEAccount>,
+
+    /// CHECK: Arcium computation account this job is queued in, derived
+    /// from the MXE and `computation_offset`
+    #[account(
+        mut,
+        address = derive_comp_pda!(computation_offset, mxe_account, PhantomError::ClusterNotSet)
+    )]
+    pub computation_account: UncheckedAccount<'info>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + PendingComputation::SIZE,
+        seeds = [b"pending_computation", computation_account.key().as_ref()],
+        bump
+    )]
+    pub pending_computation: Account<'info, PendingComputation>,
+
+    pub system_program: Program<'info, System>,
 }
 
+#[callback_accounts("reveal_vote_result")]
 #[derive(Accounts)]
 pub struct RevealResultCallback<'info> {
     #[account(mut)]
     pub vote: Account<'info, Vote>,
+
+    // Callback authentication
+    pub arcium_program: Program<'info, Arcium>,
+
+    #[account(address = derive_comp_def_pda!(COMP_DEF_OFFSET_REVEAL_VOTE_RESULT))]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+
+    #[account(address = derive_mxe_pda!())]
+    pub mxe_account: Account<'info, MXEAccount>,
+
+    /// CHECK: Arcium computation account the result was computed in,
+    /// derived from the MXE and the offset it was queued with
+    #[account(
+        address = derive_comp_pda!(
+            pending_computation.computation.computation_offset,
+            mxe_account,
+            PhantomError::ClusterNotSet
+        )
+    )]
+    pub computation_account: UncheckedAccount<'info>,
+
+    /// Cluster whose signature `verify_output` checks
+    #[account(address = derive_cluster_pda!(mxe_account, PhantomError::ClusterNotSet))]
+    pub cluster_account: Account<'info, Cluster>,
+
+    #[account(
+        mut,
+        seeds = [b"pending_computation", computation_account.key().as_ref()],
+        bump = pending_computation.bump
+    )]
+    pub pending_computation: Account<'info, PendingComputation>,
+
+    /// Arcium fee pool the escrowed fee is paid into
+    #[account(mut, address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS)]
+    pub pool_account: Account<'info, FeePool>,
+
+    /// CHECK: Instructions sysvar, checked by the Arcium program
+    #[account(address = sysvar_instructions::ID)]
+    pub instructions_sysvar: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CancelComputation<'info> {
+    #[account(mut)]
+    pub pending_computation: Account<'info, PendingComputation>,
+
+    /// Vote a cancelled fold was folding into; required for CastVote
+    #[account(mut)]
+    pub vote: Option<Account<'info, Vote>>,
+
+    /// CHECK: Refunded the escrowed fee; must be the one that put it up
+    /// (the ballot, for a fold)
+    #[account(mut, address = pending_computation.computation.fee_payer @ PhantomError::Unauthorized)]
+    pub fee_payer: UncheckedAccount<'info>,
+
+    pub caller: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CloseComputation<'info> {
+    #[account(
+        mut,
+        close = requester,
+        constraint = pending_computation.computation.requester == requester.key() @ PhantomError::Unauthorized
+    )]
+    pub pending_computation: Account<'info, PendingComputation>,
+
+    #[account(mut)]
+    pub requester: Signer<'info>,
+}
+
+// ========================================
+// HELPERS
+// ========================================
+
+/// Create a nullifier PDA at `account`, funded by `payer`, the way Anchor's
+/// `init` does: a nullifier address is predictable, so it may already have
+/// been sent lamports, which `create_account` refuses
+fn create_nullifier<'info>(
+    account: &AccountInfo<'info>,
+    payer: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    seeds: &[&[u8]],
+) -> Result<()> {
+    let space = 8 + NullifierAccount::SIZE;
+    let rent = Rent::get()?.minimum_balance(space);
+    let signer_seeds = &[seeds];
+
+    if account.lamports() == 0 {
+        return system_program::create_account(
+            CpiContext::new_with_signer(
+                system_program.clone(),
+                system_program::CreateAccount {
+                    from: payer.clone(),
+                    to: account.clone(),
+                },
+                signer_seeds,
+            ),
+            rent,
+            space as u64,
+            &crate::ID,
+        );
+    }
+
+    let shortfall = rent.saturating_sub(account.lamports());
+    if shortfall > 0 {
+        system_program::transfer(
+            CpiContext::new(
+                system_program.clone(),
+                system_program::Transfer {
+                    from: payer.clone(),
+                    to: account.clone(),
+                },
+            ),
+            shortfall,
+        )?;
+    }
+    system_program::allocate(
+        CpiContext::new_with_signer(
+            system_program.clone(),
+            system_program::Allocate {
+                account_to_allocate: account.clone(),
+            },
+            signer_seeds,
+        ),
+        space as u64,
+    )?;
+    system_program::assign(
+        CpiContext::new_with_signer(
+            system_program.clone(),
+            system_program::Assign {
+                account_to_assign: account.clone(),
+            },
+            signer_seeds,
+        ),
+        &crate::ID,
+    )
+}
+
+/// Move a computation's fee from `payer` into its record's escrow
+fn escrow_fee<'info>(
+    record: &AccountInfo<'info>,
+    payer: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    fee: u64,
+) -> Result<()> {
+    if fee == 0 {
+        return Ok(());
+    }
+    system_program::transfer(
+        CpiContext::new(
+            system_program.clone(),
+            system_program::Transfer {
+                from: payer.clone(),
+                to: record.clone(),
+            },
+        ),
+        fee,
+    )
+}
+
+/// Pay a resolved computation's escrowed fee out of its record
+fn release_fee(record: &AccountInfo, destination: &AccountInfo, fee: u64) -> Result<()> {
+    **record.try_borrow_mut_lamports()? -= fee;
+    **destination.try_borrow_mut_lamports()? += fee;
+    Ok(())
 }
 
 // ========================================
@@ -497,11 +1474,13 @@ pub struct ProtocolState {
     pub authority: Pubkey,
     pub merkle_root: [u8; 32],
     pub verification_count: u64,
+    /// Arcium fee escrowed for each computation queued
+    pub computation_fee: u64,
     pub bump: u8,
 }
 
 impl ProtocolState {
-    pub const SIZE: usize = 32 + 32 + 8 + 1;
+    pub const SIZE: usize = 32 + 32 + 8 + 8 + 1;
 }
 
 #[account]
@@ -526,12 +1505,53 @@ pub struct Vote {
     pub winning_option: Option<u8>,
     pub encrypted_tally: Vec<u8>,
     pub tally_nonce: [u8; 16],
+    /// Root of the (holder commitment, weight) holdings tree ballots prove
+    /// their weight against
+    pub holdings_root: [u8; 32],
+    /// Ballots cast and folded into `encrypted_tally`, and the fold in
+    /// flight. Folding rules live in `phantom_streams_mpc::ballots`
+    pub ballots: BallotQueue<Pubkey>,
     pub bump: u8,
 }
 
 impl Vote {
     // Base size + max tally size (512 bytes for encrypted data)
-    pub const SIZE: usize = 32 + 32 + 1 + 8 + 1 + 2 + 4 + 512 + 16 + 1;
+    pub const SIZE: usize = 32 + 32 + 1 + 8 + 1 + 2 + 4 + 512 + 16 + 32
+        + phantom_streams_mpc::ballots::BALLOT_QUEUE_SIZE + 1;
+}
+
+/// Encrypted vote waiting in a vote's queue to be folded into the tally.
+/// Lamports above rent are the voter's deposit for the fold's fee. Closed
+/// to the voter once folded
+#[account]
+pub struct Ballot {
+    pub vote: Pubkey,
+    /// Position in the queue; folded once the vote's tally version passes it
+    pub index: u64,
+    pub voter: Pubkey,
+    pub encrypted_vote: Vec<u8>,
+    pub nonce: [u8; 16],
+    pub bump: u8,
+}
+
+impl Ballot {
+    // RoyaltyVote with its holdings proof: 106 encrypted 32-byte scalars
+    pub const MAX_VOTE_LEN: usize = 106 * 32;
+    pub const SIZE: usize = 32 + 8 + 32 + 4 + Self::MAX_VOTE_LEN + 16 + 1;
+}
+
+/// Computation queued with Arcium, keyed by its Arcium computation
+/// account, holding its Arcium fee in escrow until it's resolved. Clients
+/// poll `computation.status`; the requester closes it once resolved.
+/// Callback and escrow rules live in `phantom_streams_mpc::computation`
+#[account]
+pub struct PendingComputation {
+    pub computation: QueuedComputation<Pubkey>,
+    pub bump: u8,
+}
+
+impl PendingComputation {
+    pub const SIZE: usize = phantom_streams_mpc::computation::QUEUED_COMPUTATION_SIZE + 1;
 }
 
 // ========================================
@@ -545,6 +1565,13 @@ pub struct MerkleRootUpdated {
     pub timestamp: i64,
 }
 
+#[event]
+pub struct ComputationFeeUpdated {
+    pub old_fee: u64,
+    pub new_fee: u64,
+    pub timestamp: i64,
+}
+
 #[event]
 pub struct OwnershipVerified {
     pub nullifier_hash: [u8; 32],
@@ -553,12 +1580,54 @@ pub struct OwnershipVerified {
     pub timestamp: i64,
 }
 
+#[event]
+pub struct OwnershipRejected {
+    pub nullifier_hash: [u8; 32],
+    pub encrypted_result: Vec<u8>,
+    pub timestamp: i64,
+}
+
+/// A computation was given up on after its timeout and its escrowed fee
+/// refunded
+#[event]
+pub struct ComputationCancelled {
+    pub computation: Pubkey,
+    pub kind: ComputationKind,
+    pub requester: Pubkey,
+    pub refunded: u64,
+    pub cancelled_by: Pubkey,
+    pub timestamp: i64,
+}
+
+/// A resolved computation record was closed, returning its rent
+#[event]
+pub struct ComputationClosed {
+    pub computation: Pubkey,
+    pub requester: Pubkey,
+    pub rent_returned: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct BallotCast {
+    pub vote_id: [u8; 32],
+    pub index: u64,
+    pub timestamp: i64,
+}
+
 #[event]
 pub struct VoteCast {
     pub vote_id: [u8; 32],
     pub timestamp: i64,
 }
 
+#[event]
+pub struct VoteRejected {
+    pub vote_id: [u8; 32],
+    pub nullifier_hash: [u8; 32],
+    pub timestamp: i64,
+}
+
 #[event]
 pub struct VoteRevealed {
     pub vote_id: [u8; 32],
@@ -589,4 +1658,55 @@ pub enum PhantomError {
 
     #[msg("Invalid proof")]
     InvalidProof,
+
+    #[msg("Callback was not delivered by Arcium for a queued computation")]
+    InvalidCallback,
+
+    #[msg("Computation inputs changed after it was queued")]
+    StaleComputation,
+
+    #[msg("Nullifier account doesn't match the revealed nullifier")]
+    InvalidNullifierAccount,
+
+    #[msg("Computation is no longer queued")]
+    ComputationNotQueued,
+
+    #[msg("Computation hasn't reached its timeout")]
+    ComputationNotTimedOut,
+
+    #[msg("Computation is still queued")]
+    ComputationStillQueued,
+
+    #[msg("Encrypted vote is too large")]
+    InvalidBallot,
+
+    #[msg("Vote tally hasn't been initialized")]
+    TallyNotInitialized,
+
+    #[msg("No ballots waiting to be folded")]
+    NoBallotsQueued,
+
+    #[msg("Another ballot is being folded into the tally")]
+    FoldInFlight,
+
+    #[msg("Ballots are still waiting to be folded into the tally")]
+    BallotsPending,
+
+    #[msg("Arithmetic overflow")]
+    Overflow,
+
+    #[msg("Computation output isn't signed by the cluster for this computation")]
+    InvalidComputationOutput,
+
+    #[msg("MXE cluster is not set")]
+    ClusterNotSet,
+
+    #[msg("Vote account doesn't match the computation")]
+    InvalidVoteAccount,
+
+    #[msg("Computation isn't the fold in flight for this vote")]
+    FoldNotInFlight,
+
+    #[msg("Vote tally is already initialized")]
+    TallyAlreadyInitialized,
 }
@@ -0,0 +1,396 @@
+// Phantom Streams - Groth16 verifier
+//
+// Verifies BN254 Groth16 proofs (as produced by Sunspot from the Noir
+// circuit) using Solana's alt_bn128 syscalls.
+//
+// Encoding follows the syscalls (EIP-197): big-endian field elements,
+// G1 = x || y (64 bytes), G2 = x.c1 || x.c0 || y.c1 || y.c0 (128 bytes).
+
+use anchor_lang::prelude::*;
+use solana_sha256_hasher::hashv;
+use solana_bn254::prelude::{alt_bn128_addition, alt_bn128_multiplication, alt_bn128_pairing};
+
+use crate::PhantomError;
+
+/// Serialized proof length: A (G1) || B (G2) || C (G1)
+pub const PROOF_LEN: usize = 64 + 128 + 64;
+
+/// Upper bound on public inputs a verifying key may declare
+pub const MAX_PUBLIC_INPUTS: usize = 12;
+
+/// BN254 base field modulus q (big-endian), used to negate G1 points
+const BASE_FIELD_MODULUS: [u8; 32] = [
+    0x30, 0x64, 0x4e, 0x72, 0xe1, 0x31, 0xa0, 0x29, 0xb8, 0x50, 0x45, 0xb6, 0x81, 0x81, 0x58, 0x5d,
+    0x97, 0x81, 0x6a, 0x91, 0x68, 0x71, 0xca, 0x8d, 0x3c, 0x20, 0x8c, 0x16, 0xd8, 0x7c, 0xfd, 0x47,
+];
+
+/// BN254 scalar field modulus r (big-endian). Public inputs must be below
+/// it: x and x + r are the same field element, so an unreduced input would
+/// let one proof verify under several encodings (and nullifier seeds).
+pub const SCALAR_FIELD_MODULUS: [u8; 32] = [
+    0x30, 0x64, 0x4e, 0x72, 0xe1, 0x31, 0xa0, 0x29, 0xb8, 0x50, 0x45, 0xb6, 0x81, 0x81, 0x58, 0x5d,
+    0x28, 0x33, 0xe8, 0x48, 0x79, 0xb9, 0x70, 0x91, 0x43, 0xe1, 0xf5, 0x93, 0xf0, 0x00, 0x00, 0x01,
+];
+
+/// Groth16 verifying key in syscall encoding
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct Groth16VerifyingKey {
+    pub alpha_g1: [u8; 64],
+    pub beta_g2: [u8; 128],
+    pub gamma_g2: [u8; 128],
+    pub delta_g2: [u8; 128],
+    /// IC points: one for the constant term plus one per public input
+    pub ic: Vec<[u8; 64]>,
+}
+
+impl Groth16VerifyingKey {
+    pub const MAX_SIZE: usize = 64 + 128 * 3 + 4 + 64 * (MAX_PUBLIC_INPUTS + 1);
+
+    /// Number of public inputs this key expects
+    pub fn public_inputs_len(&self) -> usize {
+        self.ic.len().saturating_sub(1)
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        require!(
+            !self.ic.is_empty() && self.ic.len() <= MAX_PUBLIC_INPUTS + 1,
+            PhantomError::InvalidVerifyingKey
+        );
+        Ok(())
+    }
+}
+
+/// Verify `proof` against `vk` for the given public inputs.
+///
+/// Public inputs must be in the order declared by the circuit's `main`.
+pub fn verify(vk: &Groth16VerifyingKey, proof: &[u8], public_inputs: &[[u8; 32]]) -> Result<()> {
+    require!(proof.len() == PROOF_LEN, PhantomError::InvalidProof);
+    let vk_x = prepare_inputs(vk, public_inputs)?;
+
+    let (proof_a, rest) = proof.split_at(64);
+    let (proof_b, proof_c) = rest.split_at(128);
+
+    // e(-A, B) * e(alpha, beta) * e(vk_x, gamma) * e(C, delta) == 1
+    let neg_a = negate_g1(proof_a)?;
+    let pairing_input = [
+        neg_a.as_slice(),
+        proof_b,
+        &vk.alpha_g1,
+        &vk.beta_g2,
+        &vk_x,
+        &vk.gamma_g2,
+        proof_c,
+        &vk.delta_g2,
+    ]
+    .concat();
+
+    check_pairing(&pairing_input)
+}
+
+/// Verify several proofs against the same `vk` with a single pairing
+/// check.
+///
+/// Each proof's equation is raised to a challenge r_i and the results are
+/// multiplied together:
+/// prod e(-r_i A_i, B_i) * e(sum(r_i) alpha, beta) * e(sum(r_i vk_x_i), gamma)
+///     * e(sum(r_i C_i), delta) == 1
+/// The challenges hash every proof and input, so a prover can't choose
+/// invalid proofs whose errors cancel out. This takes one pairing per
+/// proof plus three, instead of four per proof.
+pub fn verify_batch(vk: &Groth16VerifyingKey, batch: &[(&[u8], &[[u8; 32]])]) -> Result<()> {
+    require!(!batch.is_empty(), PhantomError::InvalidProof);
+
+    let transcript: Vec<&[u8]> = batch
+        .iter()
+        .flat_map(|(proof, inputs)| std::iter::once(*proof).chain(inputs.iter().map(|i| i.as_slice())))
+        .collect();
+    let transcript = hashv(&transcript).to_bytes();
+
+    let mut pairing_input = Vec::with_capacity((batch.len() + 3) * 192);
+    let mut challenge_sum = 0u128;
+    let mut vk_x_sum = [0u8; 64];
+    let mut c_sum = [0u8; 64];
+    for (i, (proof, inputs)) in batch.iter().enumerate() {
+        require!(proof.len() == PROOF_LEN, PhantomError::InvalidProof);
+        let vk_x = prepare_inputs(vk, inputs)?;
+
+        let (proof_a, rest) = proof.split_at(64);
+        let (proof_b, proof_c) = rest.split_at(128);
+
+        // 120-bit odd challenge, so it's never zero and the sum can't overflow
+        let digest = hashv(&[&transcript, &(i as u32).to_le_bytes()]).to_bytes();
+        let mut challenge = [0u8; 32];
+        challenge[17..].copy_from_slice(&digest[..15]);
+        challenge[31] |= 1;
+        challenge_sum += u128::from_be_bytes(challenge[16..].try_into().unwrap());
+
+        pairing_input.extend_from_slice(&negate_g1(&g1_mul(proof_a, &challenge)?)?);
+        pairing_input.extend_from_slice(proof_b);
+        vk_x_sum = g1_add(&vk_x_sum, &g1_mul(&vk_x, &challenge)?)?;
+        c_sum = g1_add(&c_sum, &g1_mul(proof_c, &challenge)?)?;
+    }
+
+    let mut sum_scalar = [0u8; 32];
+    sum_scalar[16..].copy_from_slice(&challenge_sum.to_be_bytes());
+    pairing_input.extend_from_slice(&g1_mul(&vk.alpha_g1, &sum_scalar)?);
+    pairing_input.extend_from_slice(&vk.beta_g2);
+    pairing_input.extend_from_slice(&vk_x_sum);
+    pairing_input.extend_from_slice(&vk.gamma_g2);
+    pairing_input.extend_from_slice(&c_sum);
+    pairing_input.extend_from_slice(&vk.delta_g2);
+
+    check_pairing(&pairing_input)
+}
+
+/// vk_x = IC[0] + sum(input_i * IC[i + 1])
+fn prepare_inputs(vk: &Groth16VerifyingKey, public_inputs: &[[u8; 32]]) -> Result<[u8; 64]> {
+    require!(
+        public_inputs.len() == vk.public_inputs_len(),
+        PhantomError::InvalidVerifyingKey
+    );
+
+    let mut vk_x = vk.ic[0];
+    for (input, point) in public_inputs.iter().zip(&vk.ic[1..]) {
+        require!(
+            input.as_slice() < SCALAR_FIELD_MODULUS.as_slice(),
+            PhantomError::NonCanonicalFieldElement
+        );
+        vk_x = g1_add(&vk_x, &g1_mul(point, input)?)?;
+    }
+    Ok(vk_x)
+}
+
+fn g1_mul(point: &[u8], scalar: &[u8; 32]) -> Result<[u8; 64]> {
+    let mut input = [0u8; 96];
+    input[..64].copy_from_slice(point);
+    input[64..].copy_from_slice(scalar);
+    let product = alt_bn128_multiplication(&input).map_err(|_| error!(PhantomError::InvalidProof))?;
+    product.try_into().map_err(|_| error!(PhantomError::InvalidProof))
+}
+
+fn g1_add(left: &[u8], right: &[u8]) -> Result<[u8; 64]> {
+    let mut input = [0u8; 128];
+    input[..64].copy_from_slice(left);
+    input[64..].copy_from_slice(right);
+    let sum = alt_bn128_addition(&input).map_err(|_| error!(PhantomError::InvalidProof))?;
+    sum.try_into().map_err(|_| error!(PhantomError::InvalidProof))
+}
+
+fn check_pairing(input: &[u8]) -> Result<()> {
+    let result = alt_bn128_pairing(input).map_err(|_| error!(PhantomError::InvalidProof))?;
+    let is_one = result.len() == 32 && result[..31].iter().all(|b| *b == 0) && result[31] == 1;
+    require!(is_one, PhantomError::InvalidProof);
+    Ok(())
+}
+
+/// Negate a G1 point: (x, y) -> (x, q - y)
+fn negate_g1(point: &[u8]) -> Result<[u8; 64]> {
+    let mut negated = [0u8; 64];
+    negated[..32].copy_from_slice(&point[..32]);
+
+    let y = &point[32..64];
+    // Point at infinity is encoded as all zeroes and is its own negation
+    if y.iter().all(|b| *b == 0) {
+        return Ok(negated);
+    }
+    require!(
+        y < BASE_FIELD_MODULUS.as_slice(),
+        PhantomError::InvalidProof
+    );
+
+    let mut borrow = 0u16;
+    for i in (0..32).rev() {
+        let minuend = BASE_FIELD_MODULUS[i] as u16;
+        let subtrahend = y[i] as u16 + borrow;
+        if minuend >= subtrahend {
+            negated[32 + i] = (minuend - subtrahend) as u8;
+            borrow = 0;
+        } else {
+            negated[32 + i] = (minuend + 256 - subtrahend) as u8;
+            borrow = 1;
+        }
+    }
+
+    Ok(negated)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_bn254::{Bn254, Fq, Fr, G1Affine, G2Affine};
+    use ark_ff::{BigInteger, PrimeField};
+    use ark_groth16::Groth16;
+    use ark_relations::lc;
+    use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystemRef, SynthesisError};
+    use ark_snark::SNARK;
+    use ark_std::rand::{rngs::StdRng, SeedableRng};
+
+    /// Stand-in circuit with the same public input shape as `main`:
+    /// proves knowledge of `w` with `w * track_id = merkle_root` and
+    /// `w * w = nullifier_hash`.
+    #[derive(Clone)]
+    struct ToyCircuit {
+        w: Option<Fr>,
+        track_id: Option<Fr>,
+    }
+
+    impl ConstraintSynthesizer<Fr> for ToyCircuit {
+        fn generate_constraints(
+            self,
+            cs: ConstraintSystemRef<Fr>,
+        ) -> std::result::Result<(), SynthesisError> {
+            let w_val = self.w;
+            let t_val = self.track_id;
+            let root = cs.new_input_variable(|| {
+                Ok(w_val.ok_or(SynthesisError::AssignmentMissing)?
+                    * t_val.ok_or(SynthesisError::AssignmentMissing)?)
+            })?;
+            let track = cs.new_input_variable(|| t_val.ok_or(SynthesisError::AssignmentMissing))?;
+            let nullifier = cs.new_input_variable(|| {
+                let w = w_val.ok_or(SynthesisError::AssignmentMissing)?;
+                Ok(w * w)
+            })?;
+            let w = cs.new_witness_variable(|| w_val.ok_or(SynthesisError::AssignmentMissing))?;
+            cs.enforce_constraint(lc!() + w, lc!() + track, lc!() + root)?;
+            cs.enforce_constraint(lc!() + w, lc!() + w, lc!() + nullifier)?;
+            Ok(())
+        }
+    }
+
+    fn fq_be(f: &Fq) -> [u8; 32] {
+        f.into_bigint().to_bytes_be().try_into().unwrap()
+    }
+
+    fn fr_be(f: &Fr) -> [u8; 32] {
+        f.into_bigint().to_bytes_be().try_into().unwrap()
+    }
+
+    fn g1_be(p: &G1Affine) -> [u8; 64] {
+        let mut out = [0u8; 64];
+        out[..32].copy_from_slice(&fq_be(&p.x));
+        out[32..].copy_from_slice(&fq_be(&p.y));
+        out
+    }
+
+    fn g2_be(p: &G2Affine) -> [u8; 128] {
+        let mut out = [0u8; 128];
+        out[..32].copy_from_slice(&fq_be(&p.x.c1));
+        out[32..64].copy_from_slice(&fq_be(&p.x.c0));
+        out[64..96].copy_from_slice(&fq_be(&p.y.c1));
+        out[96..].copy_from_slice(&fq_be(&p.y.c0));
+        out
+    }
+
+    fn setup() -> (Groth16VerifyingKey, Vec<u8>, Vec<[u8; 32]>) {
+        let (vk, mut proofs) = setup_batch(&[1234]);
+        let (proof, inputs) = proofs.remove(0);
+        (vk, proof, inputs)
+    }
+
+    /// Serialized proof and its public inputs
+    type TestProof = (Vec<u8>, Vec<[u8; 32]>);
+
+    /// Verifying key and one proof per witness `w`
+    fn setup_batch(witnesses: &[u64]) -> (Groth16VerifyingKey, Vec<TestProof>) {
+        let mut rng = StdRng::seed_from_u64(7);
+        let (pk, vk) = Groth16::<Bn254>::circuit_specific_setup(
+            ToyCircuit {
+                w: None,
+                track_id: None,
+            },
+            &mut rng,
+        )
+        .unwrap();
+
+        let vk_bytes = Groth16VerifyingKey {
+            alpha_g1: g1_be(&vk.alpha_g1),
+            beta_g2: g2_be(&vk.beta_g2),
+            gamma_g2: g2_be(&vk.gamma_g2),
+            delta_g2: g2_be(&vk.delta_g2),
+            ic: vk.gamma_abc_g1.iter().map(g1_be).collect(),
+        };
+
+        let track_id = Fr::from(0xaabbccddu64);
+        let proofs = witnesses
+            .iter()
+            .map(|w| {
+                let w = Fr::from(*w);
+                let proof = Groth16::<Bn254>::prove(
+                    &pk,
+                    ToyCircuit {
+                        w: Some(w),
+                        track_id: Some(track_id),
+                    },
+                    &mut rng,
+                )
+                .unwrap();
+                let proof_bytes = [
+                    g1_be(&proof.a).to_vec(),
+                    g2_be(&proof.b).to_vec(),
+                    g1_be(&proof.c).to_vec(),
+                ]
+                .concat();
+                let inputs = vec![fr_be(&(w * track_id)), fr_be(&track_id), fr_be(&(w * w))];
+                (proof_bytes, inputs)
+            })
+            .collect();
+
+        (vk_bytes, proofs)
+    }
+
+    #[test]
+    fn accepts_valid_proof() {
+        let (vk, proof, inputs) = setup();
+        assert!(verify(&vk, &proof, &inputs).is_ok());
+    }
+
+    #[test]
+    fn rejects_wrong_public_input() {
+        let (vk, proof, mut inputs) = setup();
+        inputs[2][31] ^= 1;
+        assert!(verify(&vk, &proof, &inputs).is_err());
+    }
+
+    #[test]
+    fn rejects_malformed_proof() {
+        let (vk, mut proof, inputs) = setup();
+        assert!(verify(&vk, &proof[..PROOF_LEN - 1], &inputs).is_err());
+        proof[PROOF_LEN - 1] ^= 1;
+        assert!(verify(&vk, &proof, &inputs).is_err());
+    }
+
+    #[test]
+    fn rejects_non_canonical_input() {
+        let (vk, proof, mut inputs) = setup();
+        inputs[2] = SCALAR_FIELD_MODULUS;
+        let err = verify(&vk, &proof, &inputs).unwrap_err();
+        assert_eq!(err, PhantomError::NonCanonicalFieldElement.into());
+    }
+
+    #[test]
+    fn rejects_input_count_mismatch() {
+        let (vk, proof, inputs) = setup();
+        assert!(verify(&vk, &proof, &inputs[..2]).is_err());
+    }
+
+    #[test]
+    fn accepts_valid_batch() {
+        let (vk, proofs) = setup_batch(&[1, 2, 3]);
+        let batch: Vec<(&[u8], &[[u8; 32]])> = proofs
+            .iter()
+            .map(|(proof, inputs)| (proof.as_slice(), inputs.as_slice()))
+            .collect();
+        assert!(verify_batch(&vk, &batch).is_ok());
+    }
+
+    #[test]
+    fn rejects_batch_with_one_bad_proof() {
+        let (vk, mut proofs) = setup_batch(&[1, 2, 3]);
+        proofs[1].1[2][31] ^= 1;
+        let batch: Vec<(&[u8], &[[u8; 32]])> = proofs
+            .iter()
+            .map(|(proof, inputs)| (proof.as_slice(), inputs.as_slice()))
+            .collect();
+        assert!(verify_batch(&vk, &batch).is_err());
+    }
+}
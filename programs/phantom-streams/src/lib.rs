@@ -1,4 +1,18 @@
 use anchor_lang::prelude::*;
+use anchor_lang::system_program;
+use anchor_spl::associated_token::get_associated_token_address;
+use anchor_spl::token::{self, Token, TokenAccount};
+
+pub mod groth16;
+pub mod merkle_tree;
+pub mod migration;
+pub mod nullifier_archive;
+pub mod nullifier_set;
+pub mod public_inputs;
+
+use groth16::Groth16VerifyingKey;
+use nullifier_archive::ArchiveProof;
+use public_inputs::OwnershipInputs;
 
 declare_id!("2dtcKpRkN7UHADJoWeheHt3kN9T7JQntsGnCRDK9pi6X");
 
@@ -10,212 +24,3812 @@ pub mod phantom_streams {
     pub fn initialize(ctx: Context<Initialize>) -> Result<()> {
         let state = &mut ctx.accounts.state;
         state.authority = ctx.accounts.authority.key();
-        state.merkle_root = [0u8; 32];
+        state.pending_authority = None;
         state.verification_count = 0;
+        state.epoch_length = 0;
+        state.paused = 0;
+        state.pause_guardian = None;
         state.bump = ctx.bumps.state;
+        state.version = migration::PROTOCOL_STATE_VERSION;
+        state.reserved = [0u8; 64];
         
         msg!("Phantom Streams initialized");
         Ok(())
     }
 
-    /// Update the Merkle root (only authority can call)
+    /// Create a rights registry with its own tree and authority
+    /// (only protocol authority can call)
+    pub fn create_registry(
+        ctx: Context<CreateRegistry>,
+        registry_id: u64,
+        registry_authority: Pubkey,
+    ) -> Result<()> {
+        require_authority(
+            &ctx.accounts.state.authority,
+            &ctx.accounts.authority,
+            &ctx.accounts.multisig,
+            ctx.remaining_accounts,
+        )?;
+
+        let registry = &mut ctx.accounts.registry;
+        registry.registry_id = registry_id;
+        registry.authority = registry_authority;
+        registry.pending_authority = None;
+        registry.merkle_root = [0u8; 32];
+        registry.verification_count = 0;
+        registry.root_history = [RootEntry::default(); ROOT_HISTORY_SIZE];
+        registry.root_history_index = 0;
+        registry.root_update_delay = 0;
+        registry.guardian = None;
+        registry.pending_root = None;
+        registry.is_tree_managed = false;
+        registry.sharded_nullifiers = false;
+        registry.bump = ctx.bumps.registry;
+
+        emit!(RegistryCreated {
+            registry_id,
+            authority: registry_authority,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        msg!("Registry {} created", registry_id);
+        Ok(())
+    }
+
+    /// Update a registry's Merkle root (only registry authority can call)
+    /// The previous roots stay valid until they fall out of the history
+    /// window or pass their expiry
     pub fn update_merkle_root(
         ctx: Context<UpdateRoot>,
         new_root: [u8; 32],
+        expires_at: Option<i64>,
     ) -> Result<()> {
-        let state = &mut ctx.accounts.state;
-        
+        ctx.accounts.state.require_unpaused(pause_flags::UPDATE_MERKLE_ROOT)?;
+        require_authority(
+            &ctx.accounts.registry.authority,
+            &ctx.accounts.authority,
+            &ctx.accounts.multisig,
+            ctx.remaining_accounts,
+        )?;
+
+        let registry = &mut ctx.accounts.registry;
+
+        // Tree-managed registries derive their root from registrations
+        require!(!registry.is_tree_managed, PhantomError::RootManagedByTree);
+
+        // Timelocked registries must go through propose/finalize
         require!(
-            ctx.accounts.authority.key() == state.authority,
-            PhantomError::Unauthorized
+            registry.root_update_delay == 0,
+            PhantomError::RootTimelockActive
         );
-        
-        let old_root = state.merkle_root;
-        state.merkle_root = new_root;
+
+        let clock = Clock::get()?;
+        let old_root = registry.set_root(new_root, expires_at, &clock)?;
         
         emit!(MerkleRootUpdated {
+            registry_id: registry.registry_id,
             old_root,
             new_root,
-            timestamp: Clock::get()?.unix_timestamp,
+            slot: clock.slot,
+            expires_at,
+            timestamp: clock.unix_timestamp,
         });
         
         msg!("Merkle root updated");
         Ok(())
     }
 
-    /// Verify an ownership proof
-    /// In production, this would verify the actual ZK proof
-    /// For MVP/demo, we verify proof format and track nullifiers
-    pub fn verify_ownership(
-        ctx: Context<VerifyOwnership>,
-        proof_data: Vec<u8>,
+    /// Configure a registry's root update delay and guardian
+    /// (only protocol authority, so a registry authority can't lift its
+    /// own timelock)
+    pub fn set_root_timelock(
+        ctx: Context<SetRootTimelock>,
+        delay: i64,
+        guardian: Option<Pubkey>,
+    ) -> Result<()> {
+        require_authority(
+            &ctx.accounts.state.authority,
+            &ctx.accounts.authority,
+            &ctx.accounts.multisig,
+            ctx.remaining_accounts,
+        )?;
+        require!(delay >= 0, PhantomError::InvalidTimelock);
+
+        let registry = &mut ctx.accounts.registry;
+        registry.root_update_delay = delay;
+        registry.guardian = guardian;
+
+        emit!(RootTimelockUpdated {
+            registry_id: registry.registry_id,
+            delay,
+            guardian,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        msg!("Root timelock set to {}s", delay);
+        Ok(())
+    }
+
+    /// Set the nullifier epoch length in seconds (only protocol authority).
+    /// Proofs commit to the current epoch, so a holder can verify a track
+    /// once per epoch; 0 makes every nullifier single-use forever
+    pub fn set_epoch_length(ctx: Context<SetEpochLength>, epoch_length: i64) -> Result<()> {
+        require_authority(
+            &ctx.accounts.state.authority,
+            &ctx.accounts.authority,
+            &ctx.accounts.multisig,
+            ctx.remaining_accounts,
+        )?;
+        require!(epoch_length >= 0, PhantomError::InvalidEpochLength);
+
+        let state = &mut ctx.accounts.state;
+        state.epoch_length = epoch_length;
+
+        emit!(EpochLengthUpdated {
+            epoch_length,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        msg!("Epoch length set to {}s", epoch_length);
+        Ok(())
+    }
+
+    /// Set the instruction pause bitmask (see `pause_flags`). The protocol
+    /// authority can set any value; the pause guardian can only pause more
+    /// instructions, so a compromised guardian can't lift a pause
+    pub fn set_paused(ctx: Context<SetPaused>, paused: u32) -> Result<()> {
+        let state = &ctx.accounts.state;
+        let previous = state.paused;
+        let is_guardian = state.pause_guardian == Some(ctx.accounts.authority.key());
+        if is_guardian {
+            require!(paused & previous == previous, PhantomError::Unauthorized);
+        } else {
+            require_authority(
+                &state.authority,
+                &ctx.accounts.authority,
+                &ctx.accounts.multisig,
+                ctx.remaining_accounts,
+            )?;
+        }
+
+        ctx.accounts.state.paused = paused;
+
+        emit!(PauseChanged {
+            previous,
+            paused,
+            changed_by: ctx.accounts.authority.key(),
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        msg!("Pause flags set to {:#x}", paused);
+        Ok(())
+    }
+
+    /// Set or clear the key allowed to pause instructions (only protocol
+    /// authority)
+    pub fn set_pause_guardian(
+        ctx: Context<SetPauseGuardian>,
+        guardian: Option<Pubkey>,
+    ) -> Result<()> {
+        require_authority(
+            &ctx.accounts.state.authority,
+            &ctx.accounts.authority,
+            &ctx.accounts.multisig,
+            ctx.remaining_accounts,
+        )?;
+
+        ctx.accounts.state.pause_guardian = guardian;
+
+        emit!(PauseGuardianUpdated {
+            guardian,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        msg!("Pause guardian updated");
+        Ok(())
+    }
+
+    /// Propose a new Merkle root (only registry authority)
+    /// It can be finalized once the registry's delay has passed, unless
+    /// the guardian vetoes it first
+    pub fn propose_merkle_root(
+        ctx: Context<ProposeRoot>,
+        new_root: [u8; 32],
+        expires_at: Option<i64>,
+    ) -> Result<()> {
+        ctx.accounts.state.require_unpaused(pause_flags::PROPOSE_MERKLE_ROOT)?;
+        require_authority(
+            &ctx.accounts.registry.authority,
+            &ctx.accounts.authority,
+            &ctx.accounts.multisig,
+            ctx.remaining_accounts,
+        )?;
+
+        let registry = &mut ctx.accounts.registry;
+        require!(!registry.is_tree_managed, PhantomError::RootManagedByTree);
+        require!(registry.pending_root.is_none(), PhantomError::RootUpdatePending);
+
+        let now = Clock::get()?.unix_timestamp;
+        if let Some(expires_at) = expires_at {
+            require!(expires_at > now, PhantomError::InvalidRootExpiry);
+        }
+        let eligible_at = now
+            .checked_add(registry.root_update_delay)
+            .ok_or(PhantomError::Overflow)?;
+
+        registry.pending_root = Some(PendingRoot {
+            root: new_root,
+            expires_at,
+            proposed_at: now,
+            eligible_at,
+        });
+
+        emit!(MerkleRootProposed {
+            registry_id: registry.registry_id,
+            root: new_root,
+            expires_at,
+            eligible_at,
+            timestamp: now,
+        });
+
+        msg!("Merkle root proposed, finalizable at {}", eligible_at);
+        Ok(())
+    }
+
+    /// Veto the pending Merkle root (guardian or registry authority)
+    pub fn veto_merkle_root(ctx: Context<VetoRoot>) -> Result<()> {
+        let registry = &ctx.accounts.registry;
+        let is_guardian = registry.guardian.is_some_and(|guardian| {
+            require_authority(
+                &guardian,
+                &ctx.accounts.authority,
+                &ctx.accounts.multisig,
+                ctx.remaining_accounts,
+            )
+            .is_ok()
+        });
+        if !is_guardian {
+            require_authority(
+                &registry.authority,
+                &ctx.accounts.authority,
+                &ctx.accounts.multisig,
+                ctx.remaining_accounts,
+            )?;
+        }
+
+        let registry = &mut ctx.accounts.registry;
+        let pending = registry.pending_root.take().ok_or(PhantomError::NoPendingRoot)?;
+
+        emit!(MerkleRootVetoed {
+            registry_id: registry.registry_id,
+            root: pending.root,
+            vetoed_by: ctx.accounts.authority.key(),
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        msg!("Pending merkle root vetoed");
+        Ok(())
+    }
+
+    /// Apply the pending Merkle root once its delay has passed
+    /// (permissionless)
+    pub fn finalize_merkle_root(ctx: Context<FinalizeRoot>) -> Result<()> {
+        ctx.accounts.state.require_unpaused(pause_flags::FINALIZE_MERKLE_ROOT)?;
+        let registry = &mut ctx.accounts.registry;
+        let pending = registry.pending_root.ok_or(PhantomError::NoPendingRoot)?;
+
+        let clock = Clock::get()?;
+        require!(
+            clock.unix_timestamp >= pending.eligible_at,
+            PhantomError::TimelockNotElapsed
+        );
+
+        let old_root = registry.set_root(pending.root, pending.expires_at, &clock)?;
+        registry.pending_root = None;
+
+        emit!(MerkleRootUpdated {
+            registry_id: registry.registry_id,
+            old_root,
+            new_root: pending.root,
+            slot: clock.slot,
+            expires_at: pending.expires_at,
+            timestamp: clock.unix_timestamp,
+        });
+
+        msg!("Merkle root finalized");
+        Ok(())
+    }
+
+    /// Create the on-chain rights tree for a registry (only registry
+    /// authority). From then on the registry root only changes through
+    /// `register_rights`
+    pub fn initialize_rights_tree(ctx: Context<InitializeRightsTree>) -> Result<()> {
+        require_authority(
+            &ctx.accounts.registry.authority,
+            &ctx.accounts.authority,
+            &ctx.accounts.multisig,
+            ctx.remaining_accounts,
+        )?;
+
+        let registry = &mut ctx.accounts.registry;
+        require!(registry.pending_root.is_none(), PhantomError::RootUpdatePending);
+        registry.is_tree_managed = true;
+
+        let tree = &mut ctx.accounts.rights_tree;
+        tree.registry = registry.key();
+        tree.next_index = 0;
+        tree.zeros = merkle_tree::zero_hashes()?;
+        tree.filled_subtrees = tree.zeros;
+        tree.bump = ctx.bumps.rights_tree;
+
+        msg!("Rights tree initialized for registry {}", registry.registry_id);
+        Ok(())
+    }
+
+    /// Append a rights leaf commitment
+    /// H(wallet, rights_token_id, track_id, rights_tier) to the registry's
+    /// tree and publish the new root (only registry authority)
+    pub fn register_rights(ctx: Context<RegisterRights>, leaf: [u8; 32]) -> Result<()> {
+        ctx.accounts.state.require_unpaused(pause_flags::REGISTER_RIGHTS)?;
+        require_authority(
+            &ctx.accounts.registry.authority,
+            &ctx.accounts.authority,
+            &ctx.accounts.multisig,
+            ctx.remaining_accounts,
+        )?;
+
+        let tree = &mut ctx.accounts.rights_tree;
+        let leaf_index = tree.next_index;
+        let zeros = tree.zeros;
+        let new_root = merkle_tree::insert(&mut tree.filled_subtrees, &zeros, leaf_index, leaf)?;
+        tree.next_index = leaf_index.checked_add(1).ok_or(PhantomError::Overflow)?;
+
+        let clock = Clock::get()?;
+        let registry = &mut ctx.accounts.registry;
+        let old_root = registry.set_root(new_root, None, &clock)?;
+
+        emit!(RightsRegistered {
+            registry_id: registry.registry_id,
+            leaf,
+            leaf_index,
+            root: new_root,
+            timestamp: clock.unix_timestamp,
+        });
+        emit!(MerkleRootUpdated {
+            registry_id: registry.registry_id,
+            old_root,
+            new_root,
+            slot: clock.slot,
+            expires_at: None,
+            timestamp: clock.unix_timestamp,
+        });
+
+        msg!("Rights registered at leaf {}", leaf_index);
+        Ok(())
+    }
+
+    /// Create the access policy for a track (only registry authority)
+    /// Tracks without a policy can be verified by any tier, for free, but
+    /// can't issue access passes
+    pub fn create_track_policy(
+        ctx: Context<CreateTrackPolicy>,
         track_id: [u8; 32],
+        config: TrackPolicyConfig,
+    ) -> Result<()> {
+        require_authority(
+            &ctx.accounts.registry.authority,
+            &ctx.accounts.authority,
+            &ctx.accounts.multisig,
+            ctx.remaining_accounts,
+        )?;
+        config.validate()?;
+
+        let policy = &mut ctx.accounts.track_policy;
+        policy.registry = ctx.accounts.registry.key();
+        policy.track_id = track_id;
+        policy.config = config;
+        policy.is_paused = false;
+        policy.bump = ctx.bumps.track_policy;
+
+        emit!(TrackPolicyUpdated {
+            registry_id: ctx.accounts.registry.registry_id,
+            track_id,
+            config,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        msg!("Track policy created");
+        Ok(())
+    }
+
+    /// Change a track's access terms (only registry authority). Passes
+    /// already issued keep their expiry
+    pub fn update_track_policy(
+        ctx: Context<UpdateTrackPolicy>,
+        config: TrackPolicyConfig,
+    ) -> Result<()> {
+        require_authority(
+            &ctx.accounts.registry.authority,
+            &ctx.accounts.authority,
+            &ctx.accounts.multisig,
+            ctx.remaining_accounts,
+        )?;
+        config.validate()?;
+
+        let policy = &mut ctx.accounts.track_policy;
+        policy.config = config;
+
+        emit!(TrackPolicyUpdated {
+            registry_id: ctx.accounts.registry.registry_id,
+            track_id: policy.track_id,
+            config,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        msg!("Track policy updated");
+        Ok(())
+    }
+
+    /// Take a single track down, or bring it back (only registry authority)
+    pub fn set_track_paused(ctx: Context<UpdateTrackPolicy>, is_paused: bool) -> Result<()> {
+        require_authority(
+            &ctx.accounts.registry.authority,
+            &ctx.accounts.authority,
+            &ctx.accounts.multisig,
+            ctx.remaining_accounts,
+        )?;
+
+        let policy = &mut ctx.accounts.track_policy;
+        policy.is_paused = is_paused;
+
+        emit!(TrackPauseChanged {
+            registry_id: ctx.accounts.registry.registry_id,
+            track_id: policy.track_id,
+            is_paused,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        msg!("Track paused: {}", is_paused);
+        Ok(())
+    }
+
+    /// Revoke a session key's access pass before it expires, or clear away
+    /// an expired one, closing it and refunding the rent to whoever paid
+    /// for it (only registry authority). Servers treat a missing pass as
+    /// no access
+    pub fn revoke_access_pass(ctx: Context<RevokeAccessPass>) -> Result<()> {
+        require_authority(
+            &ctx.accounts.registry.authority,
+            &ctx.accounts.authority,
+            &ctx.accounts.multisig,
+            ctx.remaining_accounts,
+        )?;
+
+        // The pass is closed to its payer by the `close` constraint
+        let access_pass = &ctx.accounts.access_pass;
+        emit!(AccessPassRevoked {
+            registry_id: ctx.accounts.registry.registry_id,
+            track_id: access_pass.track_id,
+            session_key: access_pass.session_key,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        msg!("Access pass revoked");
+        Ok(())
+    }
+
+    /// Choose where a registry records spent nullifiers (only registry
+    /// authority). Can only change before the first verification, so a
+    /// nullifier can never be spent once in each store
+    pub fn set_nullifier_store(ctx: Context<SetNullifierStore>, sharded: bool) -> Result<()> {
+        require_authority(
+            &ctx.accounts.registry.authority,
+            &ctx.accounts.authority,
+            &ctx.accounts.multisig,
+            ctx.remaining_accounts,
+        )?;
+
+        let registry = &mut ctx.accounts.registry;
+        require!(registry.verification_count == 0, PhantomError::NullifierStoreLocked);
+        registry.sharded_nullifiers = sharded;
+
+        emit!(NullifierStoreUpdated {
+            registry_id: registry.registry_id,
+            sharded,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        msg!("Registry {} nullifier store set (sharded: {})", registry.registry_id, sharded);
+        Ok(())
+    }
+
+    /// Create a page of the nullifier shard for a hash prefix
+    /// (permissionless, the payer funds the header; entries are paid for as
+    /// they're added). Page 0 first, then each further page once the one
+    /// before it is full
+    pub fn initialize_nullifier_shard(
+        ctx: Context<InitializeNullifierShard>,
+        prefix: u8,
+        page: u16,
+    ) -> Result<()> {
+        match (page, &ctx.accounts.previous_page) {
+            (0, None) => {}
+            (_, Some(previous)) if page > 0 => {
+                require!(
+                    previous.count as usize == nullifier_set::MAX_ENTRIES,
+                    PhantomError::NullifierPageNotFull
+                );
+            }
+            _ => return err!(PhantomError::InvalidNullifierStore),
+        }
+
+        let shard = &mut ctx.accounts.nullifier_shard;
+        shard.registry = ctx.accounts.registry.key();
+        shard.prefix = prefix;
+        shard.page = page;
+        shard.count = 0;
+        shard.bump = ctx.bumps.nullifier_shard;
+
+        msg!("Nullifier shard {} page {} initialized", prefix, page);
+        Ok(())
+    }
+
+    /// Create the archive spent nullifier PDAs are folded into (only
+    /// registry authority). Only for registries with nullifier PDAs
+    pub fn initialize_nullifier_archive(ctx: Context<InitializeNullifierArchive>) -> Result<()> {
+        require_authority(
+            &ctx.accounts.registry.authority,
+            &ctx.accounts.authority,
+            &ctx.accounts.multisig,
+            ctx.remaining_accounts,
+        )?;
+        require!(
+            !ctx.accounts.registry.sharded_nullifiers,
+            PhantomError::InvalidNullifierStore
+        );
+
+        let archive = &mut ctx.accounts.nullifier_archive;
+        archive.registry = ctx.accounts.registry.key();
+        archive.zeros = merkle_tree::zero_hashes()?;
+        let zeros = archive.zeros;
+        archive.root = nullifier_archive::initialize(&mut archive.filled_subtrees, &zeros)?;
+        archive.next_index = 1;
+        archive.bump = ctx.bumps.nullifier_archive;
+
+        msg!("Nullifier archive initialized for registry {}", ctx.accounts.registry.registry_id);
+        Ok(())
+    }
+
+    /// Fold a spent nullifier into the registry's archive and close its
+    /// PDA, refunding the rent to the account that funded it (only
+    /// registry authority). Nullifiers migrated from v1 or v2 didn't record
+    /// who funded them; their rent goes to an account the authority picks.
+    ///
+    /// Archiving takes one nullifier rather than a batch: each insertion
+    /// carries its low leaf's Merkle path (`ArchiveProof::SIZE`, 712 bytes),
+    /// so two wouldn't fit in one transaction. A batch is archived as one
+    /// transaction per nullifier, each proof built against the root the
+    /// previous one leaves
+    pub fn archive_nullifier(
+        ctx: Context<ArchiveNullifier>,
         nullifier_hash: [u8; 32],
-        merkle_root_snapshot: [u8; 32],
+        proof: ArchiveProof,
     ) -> Result<()> {
-        let state = &mut ctx.accounts.state;
-        let nullifier = &mut ctx.accounts.nullifier;
-        
-        // 1. Verify nullifier hasn't been used
-        require!(!nullifier.is_used, PhantomError::NullifierAlreadyUsed);
-        
-        // 2. Verify merkle root matches current state
-        // (In production, might allow recent roots within a window)
+        require_authority(
+            &ctx.accounts.registry.authority,
+            &ctx.accounts.authority,
+            &ctx.accounts.multisig,
+            ctx.remaining_accounts,
+        )?;
+
+        // The rent goes back to whoever funded the nullifier
+        ctx.accounts
+            .nullifier
+            .check_rent_recipient(&ctx.accounts.payer.key())?;
+
+        let archive = &mut ctx.accounts.nullifier_archive;
+        let leaf_index = archive.next_index;
+        let root = archive.root;
+        let zeros = archive.zeros;
+        archive.root = nullifier_archive::insert(
+            &mut archive.filled_subtrees,
+            &zeros,
+            &root,
+            leaf_index,
+            &nullifier_hash,
+            &proof,
+        )?;
+        archive.next_index = leaf_index.checked_add(1).ok_or(PhantomError::Overflow)?;
+
+        // The nullifier PDA is closed to its payer by the `close` constraint
+        emit!(NullifierArchived {
+            registry_id: ctx.accounts.registry.registry_id,
+            nullifier_hash,
+            leaf_index,
+            root: archive.root,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        msg!("Nullifier archived at leaf {}", leaf_index);
+        Ok(())
+    }
+
+    /// Prove a nullifier is not in the registry's archive (permissionless).
+    /// Records the archive size the proof was checked at, which
+    /// `verify_ownership` requires to still be current. Calling again
+    /// refreshes a record made stale by later archiving
+    pub fn prove_not_archived(
+        ctx: Context<ProveNotArchived>,
+        nullifier_hash: [u8; 32],
+        proof: ArchiveProof,
+    ) -> Result<()> {
+        let archive = &ctx.accounts.nullifier_archive;
+        nullifier_archive::verify_non_membership(&archive.root, &nullifier_hash, &proof)?;
+
+        let exclusion = &mut ctx.accounts.archive_exclusion;
+        exclusion.archive_size = archive.next_index;
+        exclusion.bump = ctx.bumps.archive_exclusion;
+
+        msg!("Nullifier not archived as of archive size {}", archive.next_index);
+        Ok(())
+    }
+
+    /// Create the treasury verification fees are collected in (only
+    /// protocol authority). Verification stays free until a fee is set
+    pub fn initialize_treasury(ctx: Context<InitializeTreasury>) -> Result<()> {
+        require_authority(
+            &ctx.accounts.state.authority,
+            &ctx.accounts.authority,
+            &ctx.accounts.multisig,
+            ctx.remaining_accounts,
+        )?;
+
+        let treasury = &mut ctx.accounts.treasury;
+        treasury.fee_amount = 0;
+        treasury.fee_mint = None;
+        treasury.bump = ctx.bumps.treasury;
+
+        msg!("Treasury initialized");
+        Ok(())
+    }
+
+    /// Set the fee charged to the payer of every verification (only
+    /// protocol authority). `fee_mint` = None charges lamports; otherwise
+    /// the fee is paid in that SPL token into the treasury's associated
+    /// token account, which must already exist
+    pub fn set_verification_fee(
+        ctx: Context<SetVerificationFee>,
+        fee_amount: u64,
+        fee_mint: Option<Pubkey>,
+    ) -> Result<()> {
+        require_authority(
+            &ctx.accounts.state.authority,
+            &ctx.accounts.authority,
+            &ctx.accounts.multisig,
+            ctx.remaining_accounts,
+        )?;
+
+        let treasury_key = ctx.accounts.treasury.key();
+        if let Some(mint) = fee_mint {
+            let token_account = ctx.accounts.treasury_token_account.as_ref()
+                .ok_or(PhantomError::InvalidFeeAccount)?;
+            require_keys_eq!(
+                token_account.key(),
+                get_associated_token_address(&treasury_key, &mint),
+                PhantomError::InvalidFeeAccount
+            );
+        }
+
+        let treasury = &mut ctx.accounts.treasury;
+        treasury.fee_amount = fee_amount;
+        treasury.fee_mint = fee_mint;
+
+        emit!(VerificationFeeUpdated {
+            fee_amount,
+            fee_mint,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        msg!("Verification fee set to {}", fee_amount);
+        Ok(())
+    }
+
+    /// Withdraw collected fees (only protocol authority). Pass the
+    /// treasury's token account to withdraw SPL fees, otherwise lamports
+    /// above the rent reserve are withdrawn
+    pub fn withdraw_fees(ctx: Context<WithdrawFees>, amount: u64) -> Result<()> {
+        require_authority(
+            &ctx.accounts.state.authority,
+            &ctx.accounts.authority,
+            &ctx.accounts.multisig,
+            ctx.remaining_accounts,
+        )?;
+
+        let treasury = &ctx.accounts.treasury;
+        let destination = &ctx.accounts.destination;
+        let mint = match &ctx.accounts.treasury_token_account {
+            Some(token_account) => {
+                require_keys_eq!(
+                    token_account.key(),
+                    get_associated_token_address(&treasury.key(), &token_account.mint),
+                    PhantomError::InvalidFeeAccount
+                );
+                let token_program = ctx.accounts.token_program.as_ref()
+                    .ok_or(PhantomError::InvalidFeeAccount)?;
+
+                let seeds: &[&[u8]] = &[b"treasury", &[treasury.bump]];
+                token::transfer(
+                    CpiContext::new_with_signer(
+                        token_program.to_account_info(),
+                        token::Transfer {
+                            from: token_account.to_account_info(),
+                            to: destination.to_account_info(),
+                            authority: treasury.to_account_info(),
+                        },
+                        &[seeds],
+                    ),
+                    amount,
+                )?;
+                Some(token_account.mint)
+            }
+            None => {
+                let treasury_info = treasury.to_account_info();
+                let reserve = Rent::get()?.minimum_balance(treasury_info.data_len());
+                let available = treasury_info.lamports().saturating_sub(reserve);
+                require!(amount <= available, PhantomError::InsufficientTreasuryBalance);
+
+                **treasury_info.try_borrow_mut_lamports()? -= amount;
+                **destination.to_account_info().try_borrow_mut_lamports()? += amount;
+                None
+            }
+        };
+
+        emit!(FeesWithdrawn {
+            mint,
+            amount,
+            destination: destination.key(),
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        msg!("Withdrew {} in fees", amount);
+        Ok(())
+    }
+
+    /// Create the vault relayer fees are paid from (only protocol
+    /// authority). Fund it with plain lamport transfers
+    pub fn initialize_fee_vault(
+        ctx: Context<InitializeFeeVault>,
+        max_relayer_fee: u64,
+    ) -> Result<()> {
+        require_authority(
+            &ctx.accounts.state.authority,
+            &ctx.accounts.authority,
+            &ctx.accounts.multisig,
+            ctx.remaining_accounts,
+        )?;
+
+        let fee_vault = &mut ctx.accounts.fee_vault;
+        fee_vault.max_relayer_fee = max_relayer_fee;
+        fee_vault.bump = ctx.bumps.fee_vault;
+
+        emit!(RelayerFeeCapUpdated {
+            max_relayer_fee,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        msg!("Fee vault initialized");
+        Ok(())
+    }
+
+    /// Cap the fee a single relayed verification can claim (only protocol
+    /// authority). Proofs commit to their fee, so the cap bounds what a
+    /// holder and relayer can take from the vault together
+    pub fn set_max_relayer_fee(ctx: Context<SetMaxRelayerFee>, max_relayer_fee: u64) -> Result<()> {
+        require_authority(
+            &ctx.accounts.state.authority,
+            &ctx.accounts.authority,
+            &ctx.accounts.multisig,
+            ctx.remaining_accounts,
+        )?;
+
+        ctx.accounts.fee_vault.max_relayer_fee = max_relayer_fee;
+
+        emit!(RelayerFeeCapUpdated {
+            max_relayer_fee,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        msg!("Max relayer fee set to {}", max_relayer_fee);
+        Ok(())
+    }
+
+    /// Register a relayer that may claim fees for submitting proofs
+    /// (only protocol authority)
+    pub fn register_relayer(ctx: Context<RegisterRelayer>, relayer_key: Pubkey) -> Result<()> {
+        require_authority(
+            &ctx.accounts.state.authority,
+            &ctx.accounts.authority,
+            &ctx.accounts.multisig,
+            ctx.remaining_accounts,
+        )?;
+
+        let relayer = &mut ctx.accounts.relayer;
+        relayer.relayer = relayer_key;
+        relayer.is_active = true;
+        relayer.bump = ctx.bumps.relayer;
+
+        emit!(RelayerStatusChanged {
+            relayer: relayer_key,
+            is_active: true,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        msg!("Relayer {} registered", relayer_key);
+        Ok(())
+    }
+
+    /// Suspend or reinstate a relayer (only protocol authority)
+    pub fn set_relayer_active(ctx: Context<SetRelayerActive>, is_active: bool) -> Result<()> {
+        require_authority(
+            &ctx.accounts.state.authority,
+            &ctx.accounts.authority,
+            &ctx.accounts.multisig,
+            ctx.remaining_accounts,
+        )?;
+
+        let relayer = &mut ctx.accounts.relayer;
+        relayer.is_active = is_active;
+
+        emit!(RelayerStatusChanged {
+            relayer: relayer.relayer,
+            is_active,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        msg!("Relayer active: {}", is_active);
+        Ok(())
+    }
+
+    /// Register a Groth16 verifying key for a circuit version (only authority)
+    /// Keys are immutable once registered; rotate by registering a new version
+    pub fn register_verifying_key(
+        ctx: Context<RegisterVerifyingKey>,
+        circuit_id: u32,
+        version: u16,
+        vk: Groth16VerifyingKey,
+    ) -> Result<()> {
+        require_authority(
+            &ctx.accounts.state.authority,
+            &ctx.accounts.authority,
+            &ctx.accounts.multisig,
+            ctx.remaining_accounts,
+        )?;
+        vk.validate()?;
+
+        let verifying_key = &mut ctx.accounts.verifying_key;
+        verifying_key.circuit_id = circuit_id;
+        verifying_key.version = version;
+        verifying_key.is_active = true;
+        verifying_key.vk = vk;
+        verifying_key.bump = ctx.bumps.verifying_key;
+
+        emit!(VerifyingKeyRegistered {
+            circuit_id,
+            version,
+            public_inputs: verifying_key.vk.public_inputs_len() as u8,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        msg!("Verifying key registered for circuit {} v{}", circuit_id, version);
+        Ok(())
+    }
+
+    /// Enable or retire a registered verifying key (only authority)
+    pub fn set_verifying_key_active(
+        ctx: Context<SetVerifyingKeyActive>,
+        is_active: bool,
+    ) -> Result<()> {
+        require_authority(
+            &ctx.accounts.state.authority,
+            &ctx.accounts.authority,
+            &ctx.accounts.multisig,
+            ctx.remaining_accounts,
+        )?;
+
+        let verifying_key = &mut ctx.accounts.verifying_key;
+        verifying_key.is_active = is_active;
+
+        emit!(VerifyingKeyStatusChanged {
+            circuit_id: verifying_key.circuit_id,
+            version: verifying_key.version,
+            is_active,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        msg!("Verifying key active: {}", is_active);
+        Ok(())
+    }
+
+    /// Create an M-of-N signer set that can be used as an authority
+    pub fn create_multisig(
+        ctx: Context<CreateMultisig>,
+        multisig_id: u64,
+        signers: Vec<Pubkey>,
+        threshold: u8,
+    ) -> Result<()> {
+        Multisig::validate(&signers, threshold)?;
+
+        let multisig = &mut ctx.accounts.multisig;
+        multisig.multisig_id = multisig_id;
+        multisig.signers = signers;
+        multisig.threshold = threshold;
+        multisig.bump = ctx.bumps.multisig;
+
+        emit!(MultisigCreated {
+            multisig: multisig.key(),
+            signers: multisig.signers.clone(),
+            threshold,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        msg!("Multisig created: {}-of-{}", threshold, multisig.signers.len());
+        Ok(())
+    }
+
+    /// Propose a new protocol authority (only current authority)
+    /// Takes effect once the proposed authority accepts
+    pub fn propose_authority(
+        ctx: Context<ProposeAuthority>,
+        new_authority: Pubkey,
+    ) -> Result<()> {
+        require_authority(
+            &ctx.accounts.state.authority,
+            &ctx.accounts.authority,
+            &ctx.accounts.multisig,
+            ctx.remaining_accounts,
+        )?;
+
+        let state = &mut ctx.accounts.state;
+        state.pending_authority = Some(new_authority);
+
+        emit!(AuthorityProposed {
+            registry_id: None,
+            current_authority: state.authority,
+            pending_authority: new_authority,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        msg!("Protocol authority transfer proposed");
+        Ok(())
+    }
+
+    /// Accept a pending protocol authority transfer (only proposed authority)
+    pub fn accept_authority(ctx: Context<AcceptAuthority>) -> Result<()> {
+        let pending = ctx
+            .accounts
+            .state
+            .pending_authority
+            .ok_or(PhantomError::NoPendingAuthority)?;
+        require_authority(
+            &pending,
+            &ctx.accounts.authority,
+            &ctx.accounts.multisig,
+            ctx.remaining_accounts,
+        )?;
+
+        let state = &mut ctx.accounts.state;
+        let old_authority = state.authority;
+        state.authority = pending;
+        state.pending_authority = None;
+
+        emit!(AuthorityTransferred {
+            registry_id: None,
+            old_authority,
+            new_authority: pending,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        msg!("Protocol authority transferred");
+        Ok(())
+    }
+
+    /// Propose a new registry authority (only current registry authority)
+    pub fn propose_registry_authority(
+        ctx: Context<ProposeRegistryAuthority>,
+        new_authority: Pubkey,
+    ) -> Result<()> {
+        require_authority(
+            &ctx.accounts.registry.authority,
+            &ctx.accounts.authority,
+            &ctx.accounts.multisig,
+            ctx.remaining_accounts,
+        )?;
+
+        let registry = &mut ctx.accounts.registry;
+        registry.pending_authority = Some(new_authority);
+
+        emit!(AuthorityProposed {
+            registry_id: Some(registry.registry_id),
+            current_authority: registry.authority,
+            pending_authority: new_authority,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        msg!("Registry authority transfer proposed");
+        Ok(())
+    }
+
+    /// Accept a pending registry authority transfer (only proposed authority)
+    pub fn accept_registry_authority(ctx: Context<AcceptRegistryAuthority>) -> Result<()> {
+        let pending = ctx
+            .accounts
+            .registry
+            .pending_authority
+            .ok_or(PhantomError::NoPendingAuthority)?;
+        require_authority(
+            &pending,
+            &ctx.accounts.authority,
+            &ctx.accounts.multisig,
+            ctx.remaining_accounts,
+        )?;
+
+        let registry = &mut ctx.accounts.registry;
+        let old_authority = registry.authority;
+        registry.authority = pending;
+        registry.pending_authority = None;
+
+        emit!(AuthorityTransferred {
+            registry_id: Some(registry.registry_id),
+            old_authority,
+            new_authority: pending,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        msg!("Registry authority transferred");
+        Ok(())
+    }
+
+    /// Upgrade a v1 `ProtocolState`, or a v1 or v2 `NullifierAccount`, to
+    /// the current layout in place (permissionless, the payer funds the
+    /// extra rent).
+    /// The v1 state's Merkle root is emitted in `AccountMigrated` so the
+    /// authority can create a registry with it
+    pub fn migrate_state(ctx: Context<MigrateState>) -> Result<()> {
+        let account = &ctx.accounts.account;
+        let is_state = {
+            let data = account.try_borrow_data()?;
+            let discriminator = data.get(..8).ok_or(PhantomError::InvalidAccountVersion)?;
+            if discriminator == ProtocolState::DISCRIMINATOR {
+                true
+            } else if discriminator == NullifierAccount::DISCRIMINATOR {
+                false
+            } else {
+                return err!(PhantomError::InvalidAccountVersion);
+            }
+        };
+        // The layout being upgraded is told by the account's length
+        let (from_version, size, version) = if is_state {
+            // Only unversioned accounts have the v1 length
+            require!(
+                account.data_len() == 8 + ProtocolState::V1_SIZE,
+                PhantomError::InvalidAccountVersion
+            );
+            (1, ProtocolState::SIZE, migration::PROTOCOL_STATE_VERSION)
+        } else {
+            (
+                migration::nullifier_version(account.data_len())?,
+                NullifierAccount::SIZE,
+                migration::NULLIFIER_VERSION,
+            )
+        };
+
+        // 1. Top up rent for the larger account
+        let new_len = 8 + size;
+        let shortfall = Rent::get()?.minimum_balance(new_len).saturating_sub(account.lamports());
+        if shortfall > 0 {
+            system_program::transfer(
+                CpiContext::new(
+                    ctx.accounts.system_program.to_account_info(),
+                    system_program::Transfer {
+                        from: ctx.accounts.payer.to_account_info(),
+                        to: account.to_account_info(),
+                    },
+                ),
+                shortfall,
+            )?;
+        }
+
+        // 2. Grow the account and rewrite it in the current layout
+        account.resize(new_len)?;
+        let mut data = account.try_borrow_mut_data()?;
+        let legacy_merkle_root = if is_state {
+            Some(migration::upgrade_protocol_state(&mut data)?)
+        } else {
+            migration::upgrade_nullifier(&mut data, from_version)?;
+            None
+        };
+        drop(data);
+
+        emit!(AccountMigrated {
+            account: account.key(),
+            version,
+            legacy_merkle_root,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        msg!("Account migrated to v{}", version);
+        Ok(())
+    }
+
+    /// Verify an ownership proof
+    /// Checks the Groth16 proof against the stored verifying key and
+    /// records the nullifier so the proof can't be replayed. The nullifier
+    /// goes in its own PDA, or in its prefix shard when the registry uses
+    /// sharded nullifiers.
+    ///
+    /// The proof commits to the submitting wallet (`payer`) and the fee it
+    /// is owed, so a registered relayer can submit on the holder's behalf
+    /// and be reimbursed from the fee vault without the holder's wallet
+    /// appearing in the transaction.
+    ///
+    /// The track's policy, if it has one, can block verification, require
+    /// a minimum rights tier and charge the payer a fee. The protocol's
+    /// verification fee, if set, is charged to the payer as well. Pass
+    /// `access_pass` to also issue a pass for the track, owned by the
+    /// session key the proof names and expiring per the track's policy.
+    ///
+    /// Returns a `VerificationReceipt` as return data, so programs calling
+    /// this through CPI (build with the `cpi` feature) can gate their own
+    /// logic on the result in the same instruction:
+    ///
+    /// ```ignore
+    /// let receipt = phantom_streams::cpi::verify_ownership(cpi_ctx, /* args */)?.get();
+    /// require!(receipt.verifier == phantom_streams::ID, MyError::BadReceipt);
+    /// require!(receipt.track_id == expected_track, MyError::WrongTrack);
+    /// ```
+    #[allow(clippy::too_many_arguments)]
+    pub fn verify_ownership<'info>(
+        ctx: Context<'_, '_, '_, 'info, VerifyOwnership<'info>>,
+        proof_data: Vec<u8>,
+        track_id: [u8; 32],
+        nullifier_hash: [u8; 32],
+        merkle_root_snapshot: [u8; 32],
+        epoch: u64,
+        relayer_fee: u64,
+        session_key: Pubkey,
+        rights_tier: u8,
+        circuit_id: u32,
+        circuit_version: u16,
+    ) -> Result<VerificationReceipt> {
+        let state = &mut ctx.accounts.state;
+        let registry = &mut ctx.accounts.registry;
+        let verifying_key = &ctx.accounts.verifying_key;
+
+        // 0. Verification must not be paused, and the proof must target
+        // a circuit version that's still accepted
+        state.require_unpaused(pause_flags::VERIFY_OWNERSHIP)?;
+        require!(verifying_key.is_active, PhantomError::VerifyingKeyInactive);
+
+        // Encode the public inputs up front: the nullifier PDA is seeded
+        // with the raw bytes, so a non-canonical alias of a spent nullifier
+        // must be rejected before the replay check
+        let relayer_key = ctx.accounts.payer.key();
+        let public_inputs = OwnershipInputs {
+            merkle_root: merkle_root_snapshot,
+            track_id,
+            nullifier_hash,
+            epoch,
+            relayer: relayer_key,
+            relayer_fee,
+            session_key,
+            rights_tier,
+        }
+        .encode()?;
+        
+        // 1. Verify nullifier hasn't been used in the registry's store
+        match (&ctx.accounts.nullifier, &ctx.accounts.nullifier_shard) {
+            (Some(nullifier), None) if !registry.sharded_nullifiers => {
+                require!(!nullifier.is_used, PhantomError::NullifierAlreadyUsed);
+
+                // A missing PDA may have been archived, so once the archive
+                // holds anything the nullifier must be proven absent from it
+                let archive = load_optional::<NullifierArchive>(&ctx.accounts.nullifier_archive)?;
+                if let Some(archive) = archive.filter(|a| a.next_index > 1) {
+                    let exclusion = ctx.accounts.archive_exclusion
+                        .as_ref()
+                        .ok_or(PhantomError::ArchiveProofRequired)?;
+                    require!(
+                        exclusion.archive_size == archive.next_index,
+                        PhantomError::StaleArchiveProof
+                    );
+                }
+            }
+            (None, Some(shard)) if registry.sharded_nullifiers => {
+                // Inserts go to the prefix's last page, and the nullifier
+                // must be in none of the full pages before it, passed as
+                // remaining accounts
+                require!(
+                    (shard.count as usize) < nullifier_set::MAX_ENTRIES,
+                    PhantomError::NullifierShardFull
+                );
+                let mut pages = ctx.remaining_accounts.to_vec();
+                pages.push(shard.to_account_info());
+                require!(
+                    !nullifier_set::contains_in_pages(&registry.key(), &nullifier_hash, &pages)?,
+                    PhantomError::NullifierAlreadyUsed
+                );
+            }
+            _ => return err!(PhantomError::InvalidNullifierStore),
+        }
+        
+        // 2. Proof must be for the current epoch, so a nullifier from a
+        // past epoch can't be spent again
+        require!(
+            epoch == state.current_epoch(Clock::get()?.unix_timestamp),
+            PhantomError::InvalidEpoch
+        );
+
+        // 3. The proof commits to its submitter and fee (checked in step 6),
+        // so a copied proof can't be front-run for the fee. Fees are only
+        // paid to registered relayers, up to the vault's cap
+        if relayer_fee > 0 {
+            let relayer = ctx.accounts.relayer.as_ref()
+                .ok_or(PhantomError::RelayerNotRegistered)?;
+            require!(relayer.is_active, PhantomError::RelayerNotRegistered);
+            let fee_vault = ctx.accounts.fee_vault.as_ref()
+                .ok_or(PhantomError::InsufficientVaultBalance)?;
+            require!(relayer_fee <= fee_vault.max_relayer_fee, PhantomError::RelayerFeeTooHigh);
+        }
+
+        // 4. Enforce the track's policy
+        let track_policy = load_optional::<TrackPolicy>(&ctx.accounts.track_policy)?;
+        if let Some(policy) = &track_policy {
+            require!(policy.config.is_allowed, PhantomError::TrackNotAllowed);
+            require!(!policy.is_paused, PhantomError::TrackPaused);
+            require!(
+                rights_tier >= policy.config.required_tier,
+                PhantomError::InsufficientRightsTier
+            );
+        }
+
+        // 5. Verify merkle root is the registry's current root or a
+        // recent one still inside the history window
+        registry.check_root(&merkle_root_snapshot, Clock::get()?.unix_timestamp)?;
+        
+        // 6. Verify the Groth16 proof
+        groth16::verify(&verifying_key.vk, &proof_data, &public_inputs)?;
+        
+        // 7. Mark nullifier as used
+        if let Some(nullifier) = &mut ctx.accounts.nullifier {
+            nullifier.is_used = true;
+            nullifier.track_id = track_id;
+            nullifier.epoch = epoch;
+            nullifier.used_at = Clock::get()?.unix_timestamp;
+            nullifier.bump = ctx.bumps.nullifier.ok_or(PhantomError::InvalidNullifierStore)?;
+            nullifier.version = migration::NULLIFIER_VERSION;
+            nullifier.payer = ctx.accounts.payer.key();
+            nullifier.reserved = [0u8; 8];
+        }
+        if let Some(shard) = &mut ctx.accounts.nullifier_shard {
+            // Account was grown by one entry in the constraints
+            let count = shard.count as usize;
+            {
+                let info = shard.to_account_info();
+                let mut data = info.try_borrow_mut_data()?;
+                nullifier_set::insert(
+                    &mut data[NullifierShard::ENTRIES_OFFSET..],
+                    count,
+                    &nullifier_hash,
+                )?;
+            }
+            shard.count = shard.count.checked_add(1).ok_or(PhantomError::Overflow)?;
+        }
+        
+        // 8. Issue an access pass to the session key if requested (Anchor
+        // records a bump for every optional PDA that was passed)
+        if let (Some(access_pass), Some(bump)) = (&mut ctx.accounts.access_pass, ctx.bumps.access_pass) {
+            require!(session_key != Pubkey::default(), PhantomError::InvalidSessionKey);
+            let policy = track_policy.as_ref()
+                .ok_or(PhantomError::TrackPolicyRequired)?;
+            let now = Clock::get()?.unix_timestamp;
+
+            access_pass.registry = registry.key();
+            access_pass.track_id = track_id;
+            access_pass.nullifier_hash = nullifier_hash;
+            access_pass.session_key = session_key;
+            access_pass.issued_at = now;
+            access_pass.expires_at = now.checked_add(policy.config.access_duration)
+                .ok_or(PhantomError::Overflow)?;
+            access_pass.bump = bump;
+            access_pass.payer = ctx.accounts.payer.key();
+
+            emit!(AccessPassIssued {
+                registry_id: registry.registry_id,
+                track_id,
+                session_key,
+                expires_at: access_pass.expires_at,
+                timestamp: now,
+            });
+        }
+        
+        // 9. Charge the track's fee to the payer
+        if let Some(policy) = track_policy.as_ref().filter(|p| p.config.fee > 0) {
+            let recipient = ctx.accounts.track_fee_recipient.as_ref()
+                .ok_or(PhantomError::InvalidFeeRecipient)?;
+            require_keys_eq!(
+                recipient.key(),
+                policy.config.fee_recipient,
+                PhantomError::InvalidFeeRecipient
+            );
+            system_program::transfer(
+                CpiContext::new(
+                    ctx.accounts.system_program.to_account_info(),
+                    system_program::Transfer {
+                        from: ctx.accounts.payer.to_account_info(),
+                        to: recipient.to_account_info(),
+                    },
+                ),
+                policy.config.fee,
+            )?;
+        }
+        
+        // 10. Charge the protocol verification fee into the treasury
+        charge_verification_fee(
+            1,
+            &ctx.accounts.treasury,
+            &ctx.accounts.payer,
+            ctx.accounts.payer_token_account.as_ref().map(|a| a.to_account_info()),
+            ctx.accounts.treasury_token_account.as_ref().map(|a| a.to_account_info()),
+            ctx.accounts.token_program.as_ref().map(|p| p.to_account_info()),
+            &ctx.accounts.system_program,
+        )?;
+        
+        // 11. Reimburse the relayer from the fee vault
+        if let Some(fee_vault) = &ctx.accounts.fee_vault {
+            if relayer_fee > 0 {
+                let vault_info = fee_vault.to_account_info();
+                let reserve = Rent::get()?.minimum_balance(vault_info.data_len());
+                let available = vault_info.lamports().saturating_sub(reserve);
+                require!(relayer_fee <= available, PhantomError::InsufficientVaultBalance);
+
+                **vault_info.try_borrow_mut_lamports()? -= relayer_fee;
+                **ctx.accounts.payer.to_account_info().try_borrow_mut_lamports()? += relayer_fee;
+            }
+        }
+        
+        // 12. Increment registry and protocol-wide verification counts
+        registry.verification_count = registry.verification_count.checked_add(1)
+            .ok_or(PhantomError::Overflow)?;
+        state.verification_count = state.verification_count.checked_add(1)
+            .ok_or(PhantomError::Overflow)?;
+        if let Some(nullifier) = &mut ctx.accounts.nullifier {
+            nullifier.verification_id = registry.verification_count;
+        }
+        
+        // 13. Emit verification event and hand the receipt to the caller
+        emit!(OwnershipVerified {
+            registry_id: registry.registry_id,
+            track_id,
+            nullifier_hash,
+            epoch,
+            relayer: relayer_key,
+            relayer_fee,
+            circuit_id,
+            circuit_version,
+            verification_id: registry.verification_count,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        
+        msg!("Ownership verified for track");
+        Ok(VerificationReceipt {
+            verifier: crate::ID,
+            registry_id: registry.registry_id,
+            track_id,
+            nullifier_hash,
+            epoch,
+            verification_id: registry.verification_count,
+        })
+    }
+
+    /// Create the payer's proof buffer, which stages proofs for
+    /// `verify_ownership_batch` since a batch doesn't fit in one
+    /// transaction
+    pub fn open_proof_buffer(ctx: Context<OpenProofBuffer>) -> Result<()> {
+        let buffer = &mut ctx.accounts.proof_buffer;
+        buffer.entries = Vec::new();
+        buffer.bump = ctx.bumps.proof_buffer;
+
+        msg!("Proof buffer opened");
+        Ok(())
+    }
+
+    /// Append proofs to the payer's proof buffer
+    pub fn append_proof_buffer(
+        ctx: Context<AppendProofBuffer>,
+        entries: Vec<BatchEntry>,
+    ) -> Result<()> {
+        let buffer = &mut ctx.accounts.proof_buffer;
+        require!(
+            buffer.entries.len() + entries.len() <= MAX_BATCH_SIZE,
+            PhantomError::InvalidBatchSize
+        );
+        buffer.entries.extend(entries);
+
+        msg!("Proof buffer holds {} proofs", buffer.entries.len());
+        Ok(())
+    }
+
+    /// Close the payer's proof buffer without verifying it
+    pub fn close_proof_buffer(_ctx: Context<CloseProofBuffer>) -> Result<()> {
+        msg!("Proof buffer closed");
+        Ok(())
+    }
+
+    /// Verify every proof in the payer's proof buffer at once, spending
+    /// all of their nullifiers or none. The pairing checks are batched and
+    /// one aggregated event is emitted; the buffer is closed to the payer.
+    ///
+    /// Only for registries with nullifier PDAs. Batches don't reimburse
+    /// relayers or issue access passes, so each proof must name the payer
+    /// as relayer, with no fee and no session key.
+    ///
+    /// Remaining accounts, for each buffered entry in order: its nullifier
+    /// PDA, track policy PDA and archive exclusion PDA (which may not
+    /// exist), then the track's fee recipient (any account if the track
+    /// charges no fee)
+    pub fn verify_ownership_batch<'info>(
+        ctx: Context<'_, '_, '_, 'info, VerifyOwnershipBatch<'info>>,
+        merkle_root_snapshot: [u8; 32],
+        epoch: u64,
+        circuit_id: u32,
+        circuit_version: u16,
+    ) -> Result<()> {
+        let state = &mut ctx.accounts.state;
+        let registry = &mut ctx.accounts.registry;
+        let verifying_key = &ctx.accounts.verifying_key;
+        let entries = &ctx.accounts.proof_buffer.entries;
+        let now = Clock::get()?.unix_timestamp;
+
+        // 0. Same gates as `verify_ownership`, checked once for the batch
+        state.require_unpaused(pause_flags::VERIFY_OWNERSHIP)?;
+        require!(verifying_key.is_active, PhantomError::VerifyingKeyInactive);
+        require!(!registry.sharded_nullifiers, PhantomError::InvalidNullifierStore);
+        require!(
+            !entries.is_empty() && entries.len() <= MAX_BATCH_SIZE,
+            PhantomError::InvalidBatchSize
+        );
+        require!(
+            ctx.remaining_accounts.len() == entries.len() * BATCH_ACCOUNTS_PER_ENTRY,
+            PhantomError::InvalidBatchAccounts
+        );
+        require!(epoch == state.current_epoch(now), PhantomError::InvalidEpoch);
+        registry.check_root(&merkle_root_snapshot, now)?;
+
+        // 1. Check each entry's accounts and track policy, and build its
+        // public inputs
+        let registry_key = registry.key();
+        let archive = load_optional::<NullifierArchive>(&ctx.accounts.nullifier_archive)?
+            .filter(|a| a.next_index > 1);
+        let mut public_inputs = Vec::with_capacity(entries.len());
+        let mut policies = Vec::with_capacity(entries.len());
+        let mut nullifier_bumps = Vec::with_capacity(entries.len());
+        for (i, (entry, accounts)) in entries.iter().zip(ctx.remaining_accounts.chunks(BATCH_ACCOUNTS_PER_ENTRY)).enumerate() {
+            let (nullifier_info, policy_info, exclusion_info) = (&accounts[0], &accounts[1], &accounts[2]);
+            let inputs = OwnershipInputs {
+                merkle_root: merkle_root_snapshot,
+                track_id: entry.track_id,
+                nullifier_hash: entry.nullifier_hash,
+                epoch,
+                relayer: ctx.accounts.payer.key(),
+                relayer_fee: 0,
+                session_key: Pubkey::default(),
+                rights_tier: entry.rights_tier,
+            }
+            .encode()?;
+            require!(
+                entries[..i].iter().all(|e| e.nullifier_hash != entry.nullifier_hash),
+                PhantomError::NullifierAlreadyUsed
+            );
+
+            let (nullifier_key, nullifier_bump) = Pubkey::find_program_address(
+                &[b"nullifier", registry_key.as_ref(), &entry.nullifier_hash],
+                &crate::ID,
+            );
+            require_keys_eq!(nullifier_info.key(), nullifier_key, PhantomError::InvalidBatchAccounts);
+            require!(
+                nullifier_info.data_is_empty() && *nullifier_info.owner == system_program::ID,
+                PhantomError::NullifierAlreadyUsed
+            );
+            nullifier_bumps.push(nullifier_bump);
+
+            if let Some(archive) = &archive {
+                let (exclusion_key, _) = Pubkey::find_program_address(
+                    &[b"archive_exclusion", registry_key.as_ref(), &entry.nullifier_hash],
+                    &crate::ID,
+                );
+                require_keys_eq!(exclusion_info.key(), exclusion_key, PhantomError::InvalidBatchAccounts);
+                let exclusion = load_optional::<ArchiveExclusion>(exclusion_info)?
+                    .ok_or(PhantomError::ArchiveProofRequired)?;
+                require!(
+                    exclusion.archive_size == archive.next_index,
+                    PhantomError::StaleArchiveProof
+                );
+            }
+
+            let (policy_key, _) = Pubkey::find_program_address(
+                &[b"track_policy", registry_key.as_ref(), &entry.track_id],
+                &crate::ID,
+            );
+            require_keys_eq!(policy_info.key(), policy_key, PhantomError::InvalidBatchAccounts);
+            let policy = load_optional::<TrackPolicy>(policy_info)?;
+            if let Some(policy) = &policy {
+                require!(policy.config.is_allowed, PhantomError::TrackNotAllowed);
+                require!(!policy.is_paused, PhantomError::TrackPaused);
+                require!(
+                    entry.rights_tier >= policy.config.required_tier,
+                    PhantomError::InsufficientRightsTier
+                );
+            }
+            policies.push(policy);
+
+            public_inputs.push(inputs);
+        }
+
+        // 2. Verify every proof with one pairing check
+        let batch: Vec<(&[u8], &[[u8; 32]])> = entries
+            .iter()
+            .zip(&public_inputs)
+            .map(|(entry, inputs)| (entry.proof.as_slice(), inputs.as_slice()))
+            .collect();
+        groth16::verify_batch(&verifying_key.vk, &batch)?;
+
+        // 3. Spend the nullifiers, consume archive exclusions and charge
+        // track fees
+        let payer = ctx.accounts.payer.to_account_info();
+        let system_program = ctx.accounts.system_program.to_account_info();
+        let first_verification_id = registry.verification_count.checked_add(1)
+            .ok_or(PhantomError::Overflow)?;
+        for (i, (entry, accounts)) in entries.iter().zip(ctx.remaining_accounts.chunks(BATCH_ACCOUNTS_PER_ENTRY)).enumerate() {
+            let (nullifier_info, exclusion_info, fee_recipient) = (&accounts[0], &accounts[2], &accounts[3]);
+
+            create_pda(
+                nullifier_info,
+                &payer,
+                &system_program,
+                8 + NullifierAccount::SIZE,
+                &[b"nullifier", registry_key.as_ref(), &entry.nullifier_hash, &[nullifier_bumps[i]]],
+            )?;
+            let nullifier = NullifierAccount {
+                is_used: true,
+                track_id: entry.track_id,
+                epoch,
+                used_at: now,
+                bump: nullifier_bumps[i],
+                version: migration::NULLIFIER_VERSION,
+                verification_id: first_verification_id + i as u64,
+                reserved: [0u8; 8],
+                payer: payer.key(),
+            };
+            let mut data = nullifier_info.try_borrow_mut_data()?;
+            let mut writer: &mut [u8] = &mut data;
+            nullifier.try_serialize(&mut writer)?;
+            drop(data);
+
+            if archive.is_some() {
+                close_account(exclusion_info, &payer)?;
+            }
+
+            if let Some(policy) = policies[i].as_ref().filter(|p| p.config.fee > 0) {
+                require_keys_eq!(
+                    fee_recipient.key(),
+                    policy.config.fee_recipient,
+                    PhantomError::InvalidFeeRecipient
+                );
+                system_program::transfer(
+                    CpiContext::new(
+                        system_program.clone(),
+                        system_program::Transfer {
+                            from: payer.clone(),
+                            to: fee_recipient.clone(),
+                        },
+                    ),
+                    policy.config.fee,
+                )?;
+            }
+        }
+
+        // 4. Charge one protocol verification fee per proof
+        charge_verification_fee(
+            entries.len() as u64,
+            &ctx.accounts.treasury,
+            &ctx.accounts.payer,
+            ctx.accounts.payer_token_account.as_ref().map(|a| a.to_account_info()),
+            ctx.accounts.treasury_token_account.as_ref().map(|a| a.to_account_info()),
+            ctx.accounts.token_program.as_ref().map(|p| p.to_account_info()),
+            &ctx.accounts.system_program,
+        )?;
+
+        // 5. Increment verification counts and emit one event for the batch
+        let count = entries.len() as u64;
+        registry.verification_count = registry.verification_count.checked_add(count)
+            .ok_or(PhantomError::Overflow)?;
+        state.verification_count = state.verification_count.checked_add(count)
+            .ok_or(PhantomError::Overflow)?;
+
+        emit!(OwnershipBatchVerified {
+            registry_id: registry.registry_id,
+            track_ids: entries.iter().map(|e| e.track_id).collect(),
+            nullifier_hashes: entries.iter().map(|e| e.nullifier_hash).collect(),
+            epoch,
+            relayer: ctx.accounts.payer.key(),
+            circuit_id,
+            circuit_version,
+            first_verification_id,
+            timestamp: now,
+        });
+
+        msg!("Ownership verified for {} tracks", count);
+        Ok(())
+    }
+
+    /// Look up a nullifier's verification status (view function)
+    /// Every account is a PDA of the registry and nullifier hash and may
+    /// not exist, so this succeeds for nullifiers that were never spent
+    pub fn get_verification_status<'info>(
+        ctx: Context<'_, '_, '_, 'info, GetVerificationStatus<'info>>,
+        nullifier_hash: [u8; 32],
+    ) -> Result<VerificationStatus> {
+        let registry = &ctx.accounts.registry;
+        let mut status = VerificationStatus {
+            status: NullifierState::Unused,
+            registry: registry.key(),
+            track_id: None,
+            used_at: None,
+            verification_id: None,
+        };
+
+        if registry.sharded_nullifiers {
+            // Shards only hold the hash, so there's nothing more to report.
+            // Pages after the first are passed as remaining accounts
+            let mut pages = vec![ctx.accounts.nullifier_shard.to_account_info()];
+            pages.extend_from_slice(ctx.remaining_accounts);
+            if nullifier_set::contains_in_pages(&registry.key(), &nullifier_hash, &pages)? {
+                status.status = NullifierState::Used;
+            }
+        } else if let Some(nullifier) = load_optional::<NullifierAccount>(&ctx.accounts.nullifier)? {
+            if nullifier.is_used {
+                status.status = NullifierState::Used;
+                status.track_id = Some(nullifier.track_id);
+                status.used_at = Some(nullifier.used_at);
+                // Nullifiers spent before the id was recorded hold zero
+                status.verification_id = Some(nullifier.verification_id).filter(|id| *id > 0);
+            }
+        } else {
+            // Not in the live set, but it may have been archived unless a
+            // current exclusion says otherwise
+            let archive = load_optional::<NullifierArchive>(&ctx.accounts.nullifier_archive)?;
+            if let Some(archive) = archive.filter(|a| a.next_index > 1) {
+                let excluded = load_optional::<ArchiveExclusion>(&ctx.accounts.archive_exclusion)?
+                    .is_some_and(|e| e.archive_size == archive.next_index);
+                if !excluded {
+                    status.status = NullifierState::PossiblyArchived;
+                }
+            }
+        }
+
+        Ok(status)
+    }
+
+    /// Check if a nullifier has been used (view function)
+    /// Pass the nullifier PDA or, for sharded registries, the first page of
+    /// its prefix shard, with the later pages as remaining accounts. Fails
+    /// if the nullifier PDA doesn't exist; `get_verification_status`
+    /// doesn't
+    pub fn check_nullifier<'info>(
+        ctx: Context<'_, '_, '_, 'info, CheckNullifier<'info>>,
+        nullifier_hash: [u8; 32],
+    ) -> Result<bool> {
+        match (&ctx.accounts.nullifier, &ctx.accounts.nullifier_shard) {
+            (Some(nullifier), None) if !ctx.accounts.registry.sharded_nullifiers => {
+                Ok(nullifier.is_used)
+            }
+            (None, Some(shard)) if ctx.accounts.registry.sharded_nullifiers => {
+                let mut pages = vec![shard.to_account_info()];
+                pages.extend_from_slice(ctx.remaining_accounts);
+                nullifier_set::contains_in_pages(
+                    &ctx.accounts.registry.key(),
+                    &nullifier_hash,
+                    &pages,
+                )
+            }
+            _ => err!(PhantomError::InvalidNullifierStore),
+        }
+    }
+}
+
+// ========== ACCOUNTS ==========
+
+#[derive(Accounts)]
+pub struct Initialize<'info> {
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + ProtocolState::SIZE,
+        seeds = [b"state"],
+        bump
+    )]
+    pub state: Box<Account<'info, ProtocolState>>,
+    
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(registry_id: u64)]
+pub struct CreateRegistry<'info> {
+    #[account(
+        seeds = [b"state"],
+        bump = state.bump
+    )]
+    pub state: Box<Account<'info, ProtocolState>>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + Registry::SIZE,
+        seeds = [b"registry", registry_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub registry: Box<Account<'info, Registry>>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    /// Required when the authority is a multisig; co-signers go in
+    /// remaining accounts
+    pub multisig: Option<Box<Account<'info, Multisig>>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateRoot<'info> {
+    #[account(
+        seeds = [b"state"],
+        bump = state.bump
+    )]
+    pub state: Box<Account<'info, ProtocolState>>,
+
+    #[account(
+        mut,
+        seeds = [b"registry", registry.registry_id.to_le_bytes().as_ref()],
+        bump = registry.bump
+    )]
+    pub registry: Box<Account<'info, Registry>>,
+    
+    pub authority: Signer<'info>,
+
+    /// Required when the authority is a multisig; co-signers go in
+    /// remaining accounts
+    pub multisig: Option<Box<Account<'info, Multisig>>>,
+}
+
+#[derive(Accounts)]
+pub struct SetRootTimelock<'info> {
+    #[account(
+        seeds = [b"state"],
+        bump = state.bump
+    )]
+    pub state: Box<Account<'info, ProtocolState>>,
+
+    #[account(
+        mut,
+        seeds = [b"registry", registry.registry_id.to_le_bytes().as_ref()],
+        bump = registry.bump
+    )]
+    pub registry: Box<Account<'info, Registry>>,
+
+    pub authority: Signer<'info>,
+
+    /// Required when the authority is a multisig
+    pub multisig: Option<Box<Account<'info, Multisig>>>,
+}
+
+#[derive(Accounts)]
+pub struct SetEpochLength<'info> {
+    #[account(
+        mut,
+        seeds = [b"state"],
+        bump = state.bump
+    )]
+    pub state: Box<Account<'info, ProtocolState>>,
+
+    pub authority: Signer<'info>,
+
+    /// Required when the authority is a multisig
+    pub multisig: Option<Box<Account<'info, Multisig>>>,
+}
+
+#[derive(Accounts)]
+pub struct SetPaused<'info> {
+    #[account(
+        mut,
+        seeds = [b"state"],
+        bump = state.bump
+    )]
+    pub state: Box<Account<'info, ProtocolState>>,
+
+    /// Protocol authority or pause guardian
+    pub authority: Signer<'info>,
+
+    /// Required when the authority is a multisig
+    pub multisig: Option<Box<Account<'info, Multisig>>>,
+}
+
+#[derive(Accounts)]
+pub struct SetPauseGuardian<'info> {
+    #[account(
+        mut,
+        seeds = [b"state"],
+        bump = state.bump
+    )]
+    pub state: Box<Account<'info, ProtocolState>>,
+
+    pub authority: Signer<'info>,
+
+    /// Required when the authority is a multisig
+    pub multisig: Option<Box<Account<'info, Multisig>>>,
+}
+
+#[derive(Accounts)]
+pub struct ProposeRoot<'info> {
+    #[account(
+        seeds = [b"state"],
+        bump = state.bump
+    )]
+    pub state: Box<Account<'info, ProtocolState>>,
+
+    #[account(
+        mut,
+        seeds = [b"registry", registry.registry_id.to_le_bytes().as_ref()],
+        bump = registry.bump
+    )]
+    pub registry: Box<Account<'info, Registry>>,
+
+    pub authority: Signer<'info>,
+
+    /// Required when the authority is a multisig
+    pub multisig: Option<Box<Account<'info, Multisig>>>,
+}
+
+#[derive(Accounts)]
+pub struct VetoRoot<'info> {
+    #[account(
+        mut,
+        seeds = [b"registry", registry.registry_id.to_le_bytes().as_ref()],
+        bump = registry.bump
+    )]
+    pub registry: Box<Account<'info, Registry>>,
+
+    /// Guardian or registry authority
+    pub authority: Signer<'info>,
+
+    /// Required when the guardian or authority is a multisig
+    pub multisig: Option<Box<Account<'info, Multisig>>>,
+}
+
+#[derive(Accounts)]
+pub struct FinalizeRoot<'info> {
+    #[account(
+        seeds = [b"state"],
+        bump = state.bump
+    )]
+    pub state: Box<Account<'info, ProtocolState>>,
+
+    #[account(
+        mut,
+        seeds = [b"registry", registry.registry_id.to_le_bytes().as_ref()],
+        bump = registry.bump
+    )]
+    pub registry: Box<Account<'info, Registry>>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeRightsTree<'info> {
+    #[account(
+        mut,
+        seeds = [b"registry", registry.registry_id.to_le_bytes().as_ref()],
+        bump = registry.bump
+    )]
+    pub registry: Box<Account<'info, Registry>>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + RightsTree::SIZE,
+        seeds = [b"rights_tree", registry.key().as_ref()],
+        bump
+    )]
+    pub rights_tree: Box<Account<'info, RightsTree>>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    /// Required when the authority is a multisig
+    pub multisig: Option<Box<Account<'info, Multisig>>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RegisterRights<'info> {
+    #[account(
+        seeds = [b"state"],
+        bump = state.bump
+    )]
+    pub state: Box<Account<'info, ProtocolState>>,
+
+    #[account(
+        mut,
+        seeds = [b"registry", registry.registry_id.to_le_bytes().as_ref()],
+        bump = registry.bump
+    )]
+    pub registry: Box<Account<'info, Registry>>,
+
+    #[account(
+        mut,
+        seeds = [b"rights_tree", registry.key().as_ref()],
+        bump = rights_tree.bump
+    )]
+    pub rights_tree: Box<Account<'info, RightsTree>>,
+
+    pub authority: Signer<'info>,
+
+    /// Required when the authority is a multisig
+    pub multisig: Option<Box<Account<'info, Multisig>>>,
+}
+
+#[derive(Accounts)]
+#[instruction(track_id: [u8; 32])]
+pub struct CreateTrackPolicy<'info> {
+    #[account(
+        seeds = [b"registry", registry.registry_id.to_le_bytes().as_ref()],
+        bump = registry.bump
+    )]
+    pub registry: Box<Account<'info, Registry>>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + TrackPolicy::SIZE,
+        seeds = [b"track_policy", registry.key().as_ref(), track_id.as_ref()],
+        bump
+    )]
+    pub track_policy: Box<Account<'info, TrackPolicy>>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    /// Required when the authority is a multisig
+    pub multisig: Option<Box<Account<'info, Multisig>>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateTrackPolicy<'info> {
+    #[account(
+        seeds = [b"registry", registry.registry_id.to_le_bytes().as_ref()],
+        bump = registry.bump
+    )]
+    pub registry: Box<Account<'info, Registry>>,
+
+    #[account(
+        mut,
+        seeds = [b"track_policy", registry.key().as_ref(), track_policy.track_id.as_ref()],
+        bump = track_policy.bump
+    )]
+    pub track_policy: Box<Account<'info, TrackPolicy>>,
+
+    pub authority: Signer<'info>,
+
+    /// Required when the authority is a multisig
+    pub multisig: Option<Box<Account<'info, Multisig>>>,
+}
+
+#[derive(Accounts)]
+pub struct RevokeAccessPass<'info> {
+    #[account(
+        seeds = [b"registry", registry.registry_id.to_le_bytes().as_ref()],
+        bump = registry.bump
+    )]
+    pub registry: Box<Account<'info, Registry>>,
+
+    #[account(
+        mut,
+        close = payer,
+        has_one = payer @ PhantomError::InvalidRentRecipient,
+        seeds = [
+            b"access_pass",
+            registry.key().as_ref(),
+            access_pass.track_id.as_ref(),
+            access_pass.nullifier_hash.as_ref()
+        ],
+        bump = access_pass.bump
+    )]
+    pub access_pass: Box<Account<'info, AccessPass>>,
+
+    pub authority: Signer<'info>,
+
+    /// Required when the authority is a multisig
+    pub multisig: Option<Box<Account<'info, Multisig>>>,
+
+    /// CHECK: the pass's payer (checked by `has_one`), which receives its
+    /// rent
+    #[account(mut)]
+    pub payer: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetNullifierStore<'info> {
+    #[account(
+        mut,
+        seeds = [b"registry", registry.registry_id.to_le_bytes().as_ref()],
+        bump = registry.bump
+    )]
+    pub registry: Box<Account<'info, Registry>>,
+
+    pub authority: Signer<'info>,
+
+    /// Required when the authority is a multisig
+    pub multisig: Option<Box<Account<'info, Multisig>>>,
+}
+
+#[derive(Accounts)]
+#[instruction(prefix: u8, page: u16)]
+pub struct InitializeNullifierShard<'info> {
+    #[account(
+        seeds = [b"registry", registry.registry_id.to_le_bytes().as_ref()],
+        bump = registry.bump
+    )]
+    pub registry: Box<Account<'info, Registry>>,
+
+    #[account(
+        init,
+        payer = payer,
+        space = NullifierShard::space(0),
+        seeds = [b"nullifier_shard", registry.key().as_ref(), &[prefix], page.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub nullifier_shard: Box<Account<'info, NullifierShard>>,
+
+    /// The page before this one, which must be full; none for page 0
+    #[account(
+        seeds = [
+            b"nullifier_shard",
+            registry.key().as_ref(),
+            &[prefix],
+            page.saturating_sub(1).to_le_bytes().as_ref()
+        ],
+        bump = previous_page.bump
+    )]
+    pub previous_page: Option<Box<Account<'info, NullifierShard>>>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeNullifierArchive<'info> {
+    #[account(
+        seeds = [b"registry", registry.registry_id.to_le_bytes().as_ref()],
+        bump = registry.bump
+    )]
+    pub registry: Box<Account<'info, Registry>>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + NullifierArchive::SIZE,
+        seeds = [b"nullifier_archive", registry.key().as_ref()],
+        bump
+    )]
+    pub nullifier_archive: Box<Account<'info, NullifierArchive>>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    /// Required when the authority is a multisig
+    pub multisig: Option<Box<Account<'info, Multisig>>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(nullifier_hash: [u8; 32])]
+pub struct ArchiveNullifier<'info> {
+    #[account(
+        seeds = [b"registry", registry.registry_id.to_le_bytes().as_ref()],
+        bump = registry.bump
+    )]
+    pub registry: Box<Account<'info, Registry>>,
+
+    #[account(
+        mut,
+        seeds = [b"nullifier_archive", registry.key().as_ref()],
+        bump = nullifier_archive.bump
+    )]
+    pub nullifier_archive: Box<Account<'info, NullifierArchive>>,
+
+    #[account(
+        mut,
+        close = payer,
+        seeds = [b"nullifier", registry.key().as_ref(), nullifier_hash.as_ref()],
+        bump = nullifier.bump
+    )]
+    pub nullifier: Account<'info, NullifierAccount>,
+
+    pub authority: Signer<'info>,
+
+    /// Required when the authority is a multisig
+    pub multisig: Option<Box<Account<'info, Multisig>>>,
+
+    /// CHECK: the nullifier's recorded payer, or the authority's pick if
+    /// it has none (checked in the handler), which receives its rent
+    #[account(mut)]
+    pub payer: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(nullifier_hash: [u8; 32])]
+pub struct ProveNotArchived<'info> {
+    #[account(
+        seeds = [b"registry", registry.registry_id.to_le_bytes().as_ref()],
+        bump = registry.bump
+    )]
+    pub registry: Box<Account<'info, Registry>>,
+
+    #[account(
+        seeds = [b"nullifier_archive", registry.key().as_ref()],
+        bump = nullifier_archive.bump
+    )]
+    pub nullifier_archive: Box<Account<'info, NullifierArchive>>,
+
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + ArchiveExclusion::SIZE,
+        seeds = [b"archive_exclusion", registry.key().as_ref(), nullifier_hash.as_ref()],
+        bump
+    )]
+    pub archive_exclusion: Box<Account<'info, ArchiveExclusion>>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeTreasury<'info> {
+    #[account(
+        seeds = [b"state"],
+        bump = state.bump
+    )]
+    pub state: Box<Account<'info, ProtocolState>>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + Treasury::SIZE,
+        seeds = [b"treasury"],
+        bump
+    )]
+    pub treasury: Box<Account<'info, Treasury>>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    /// Required when the authority is a multisig
+    pub multisig: Option<Box<Account<'info, Multisig>>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetVerificationFee<'info> {
+    #[account(
+        seeds = [b"state"],
+        bump = state.bump
+    )]
+    pub state: Box<Account<'info, ProtocolState>>,
+
+    #[account(
+        mut,
+        seeds = [b"treasury"],
+        bump = treasury.bump
+    )]
+    pub treasury: Box<Account<'info, Treasury>>,
+
+    /// Treasury's associated token account, required for SPL fees
+    pub treasury_token_account: Option<Box<Account<'info, TokenAccount>>>,
+
+    pub authority: Signer<'info>,
+
+    /// Required when the authority is a multisig
+    pub multisig: Option<Box<Account<'info, Multisig>>>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawFees<'info> {
+    #[account(
+        seeds = [b"state"],
+        bump = state.bump
+    )]
+    pub state: Box<Account<'info, ProtocolState>>,
+
+    #[account(
+        mut,
+        seeds = [b"treasury"],
+        bump = treasury.bump
+    )]
+    pub treasury: Box<Account<'info, Treasury>>,
+
+    /// Treasury's associated token account, when withdrawing SPL fees
+    #[account(mut)]
+    pub treasury_token_account: Option<Box<Account<'info, TokenAccount>>>,
+
+    /// CHECK: receives the fees; a token account for SPL withdrawals
+    #[account(mut)]
+    pub destination: UncheckedAccount<'info>,
+
+    pub token_program: Option<Program<'info, Token>>,
+
+    pub authority: Signer<'info>,
+
+    /// Required when the authority is a multisig
+    pub multisig: Option<Box<Account<'info, Multisig>>>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeFeeVault<'info> {
+    #[account(
+        seeds = [b"state"],
+        bump = state.bump
+    )]
+    pub state: Box<Account<'info, ProtocolState>>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + FeeVault::SIZE,
+        seeds = [b"fee_vault"],
+        bump
+    )]
+    pub fee_vault: Box<Account<'info, FeeVault>>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    /// Required when the authority is a multisig
+    pub multisig: Option<Box<Account<'info, Multisig>>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetMaxRelayerFee<'info> {
+    #[account(
+        seeds = [b"state"],
+        bump = state.bump
+    )]
+    pub state: Box<Account<'info, ProtocolState>>,
+
+    #[account(
+        mut,
+        seeds = [b"fee_vault"],
+        bump = fee_vault.bump
+    )]
+    pub fee_vault: Box<Account<'info, FeeVault>>,
+
+    pub authority: Signer<'info>,
+
+    /// Required when the authority is a multisig
+    pub multisig: Option<Box<Account<'info, Multisig>>>,
+}
+
+#[derive(Accounts)]
+#[instruction(relayer_key: Pubkey)]
+pub struct RegisterRelayer<'info> {
+    #[account(
+        seeds = [b"state"],
+        bump = state.bump
+    )]
+    pub state: Box<Account<'info, ProtocolState>>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + Relayer::SIZE,
+        seeds = [b"relayer", relayer_key.as_ref()],
+        bump
+    )]
+    pub relayer: Box<Account<'info, Relayer>>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    /// Required when the authority is a multisig
+    pub multisig: Option<Box<Account<'info, Multisig>>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetRelayerActive<'info> {
+    #[account(
+        seeds = [b"state"],
+        bump = state.bump
+    )]
+    pub state: Box<Account<'info, ProtocolState>>,
+
+    #[account(
+        mut,
+        seeds = [b"relayer", relayer.relayer.as_ref()],
+        bump = relayer.bump
+    )]
+    pub relayer: Box<Account<'info, Relayer>>,
+
+    pub authority: Signer<'info>,
+
+    /// Required when the authority is a multisig
+    pub multisig: Option<Box<Account<'info, Multisig>>>,
+}
+
+#[derive(Accounts)]
+#[instruction(circuit_id: u32, version: u16)]
+pub struct RegisterVerifyingKey<'info> {
+    #[account(
+        seeds = [b"state"],
+        bump = state.bump
+    )]
+    pub state: Box<Account<'info, ProtocolState>>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + VerifyingKey::SIZE,
+        seeds = [b"verifying_key", circuit_id.to_le_bytes().as_ref(), version.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub verifying_key: Box<Account<'info, VerifyingKey>>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    /// Required when the authority is a multisig; co-signers go in
+    /// remaining accounts
+    pub multisig: Option<Box<Account<'info, Multisig>>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetVerifyingKeyActive<'info> {
+    #[account(
+        seeds = [b"state"],
+        bump = state.bump
+    )]
+    pub state: Box<Account<'info, ProtocolState>>,
+
+    #[account(
+        mut,
+        seeds = [
+            b"verifying_key",
+            verifying_key.circuit_id.to_le_bytes().as_ref(),
+            verifying_key.version.to_le_bytes().as_ref()
+        ],
+        bump = verifying_key.bump
+    )]
+    pub verifying_key: Box<Account<'info, VerifyingKey>>,
+
+    pub authority: Signer<'info>,
+
+    /// Required when the authority is a multisig; co-signers go in
+    /// remaining accounts
+    pub multisig: Option<Box<Account<'info, Multisig>>>,
+}
+
+#[derive(Accounts)]
+#[instruction(multisig_id: u64)]
+pub struct CreateMultisig<'info> {
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + Multisig::SIZE,
+        seeds = [b"multisig", multisig_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub multisig: Box<Account<'info, Multisig>>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ProposeAuthority<'info> {
+    #[account(
+        mut,
+        seeds = [b"state"],
+        bump = state.bump
+    )]
+    pub state: Box<Account<'info, ProtocolState>>,
+
+    pub authority: Signer<'info>,
+
+    /// Required when the authority is a multisig
+    pub multisig: Option<Box<Account<'info, Multisig>>>,
+}
+
+#[derive(Accounts)]
+pub struct AcceptAuthority<'info> {
+    #[account(
+        mut,
+        seeds = [b"state"],
+        bump = state.bump
+    )]
+    pub state: Box<Account<'info, ProtocolState>>,
+
+    /// Proposed authority (or a member of the proposed multisig)
+    pub authority: Signer<'info>,
+
+    /// Required when the proposed authority is a multisig
+    pub multisig: Option<Box<Account<'info, Multisig>>>,
+}
+
+#[derive(Accounts)]
+pub struct ProposeRegistryAuthority<'info> {
+    #[account(
+        mut,
+        seeds = [b"registry", registry.registry_id.to_le_bytes().as_ref()],
+        bump = registry.bump
+    )]
+    pub registry: Box<Account<'info, Registry>>,
+
+    pub authority: Signer<'info>,
+
+    /// Required when the authority is a multisig
+    pub multisig: Option<Box<Account<'info, Multisig>>>,
+}
+
+#[derive(Accounts)]
+pub struct AcceptRegistryAuthority<'info> {
+    #[account(
+        mut,
+        seeds = [b"registry", registry.registry_id.to_le_bytes().as_ref()],
+        bump = registry.bump
+    )]
+    pub registry: Box<Account<'info, Registry>>,
+
+    /// Proposed authority (or a member of the proposed multisig)
+    pub authority: Signer<'info>,
+
+    /// Required when the proposed authority is a multisig
+    pub multisig: Option<Box<Account<'info, Multisig>>>,
+}
+
+#[derive(Accounts)]
+pub struct MigrateState<'info> {
+    /// CHECK: a v1 `ProtocolState` or a v1 or v2 `NullifierAccount`,
+    /// identified by its discriminator and length
+    #[account(mut, owner = crate::ID)]
+    pub account: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(
+    proof_data: Vec<u8>,
+    track_id: [u8; 32],
+    nullifier_hash: [u8; 32],
+    merkle_root_snapshot: [u8; 32],
+    epoch: u64,
+    relayer_fee: u64,
+    session_key: Pubkey,
+    rights_tier: u8,
+    circuit_id: u32,
+    circuit_version: u16
+)]
+pub struct VerifyOwnership<'info> {
+    #[account(
+        mut,
+        seeds = [b"state"],
+        bump = state.bump
+    )]
+    pub state: Box<Account<'info, ProtocolState>>,
+
+    #[account(
+        mut,
+        seeds = [b"registry", registry.registry_id.to_le_bytes().as_ref()],
+        bump = registry.bump
+    )]
+    pub registry: Box<Account<'info, Registry>>,
+
+    #[account(
+        seeds = [b"verifying_key", circuit_id.to_le_bytes().as_ref(), circuit_version.to_le_bytes().as_ref()],
+        bump = verifying_key.bump
+    )]
+    pub verifying_key: Box<Account<'info, VerifyingKey>>,
+    
+    /// Nullifier PDA, for registries without sharded nullifiers
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + NullifierAccount::SIZE,
+        seeds = [b"nullifier", registry.key().as_ref(), nullifier_hash.as_ref()],
+        bump
+    )]
+    pub nullifier: Option<Account<'info, NullifierAccount>>,
+
+    /// Last page of the prefix shard, for registries with sharded
+    /// nullifiers; grown by one entry to hold this nullifier. The pages
+    /// before it are passed as remaining accounts
+    #[account(
+        mut,
+        seeds = [
+            b"nullifier_shard",
+            registry.key().as_ref(),
+            &[nullifier_set::shard_prefix(&nullifier_hash)],
+            nullifier_shard.page.to_le_bytes().as_ref()
+        ],
+        bump = nullifier_shard.bump,
+        realloc = NullifierShard::space(nullifier_shard.count as usize + 1),
+        realloc::payer = payer,
+        realloc::zero = false
+    )]
+    pub nullifier_shard: Option<Box<Account<'info, NullifierShard>>>,
+
+    /// CHECK: the registry's `NullifierArchive` PDA, which may not exist.
+    /// Always required so archived nullifiers can't be replayed by
+    /// leaving it out
+    #[account(
+        seeds = [b"nullifier_archive", registry.key().as_ref()],
+        bump
+    )]
+    pub nullifier_archive: UncheckedAccount<'info>,
+
+    /// Proof the nullifier isn't archived, required once the archive is
+    /// non-empty; closed to the payer
+    #[account(
+        mut,
+        close = payer,
+        seeds = [b"archive_exclusion", registry.key().as_ref(), nullifier_hash.as_ref()],
+        bump = archive_exclusion.bump
+    )]
+    pub archive_exclusion: Option<Box<Account<'info, ArchiveExclusion>>>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    /// Required when claiming a relayer fee
+    #[account(
+        seeds = [b"relayer", payer.key().as_ref()],
+        bump = relayer.bump
+    )]
+    pub relayer: Option<Box<Account<'info, Relayer>>>,
+
+    /// Required when claiming a relayer fee
+    #[account(
+        mut,
+        seeds = [b"fee_vault"],
+        bump = fee_vault.bump
+    )]
+    pub fee_vault: Option<Box<Account<'info, FeeVault>>>,
+
+    /// CHECK: the track's `TrackPolicy` PDA, which may not exist. Always
+    /// required so a caller can't skip the policy by leaving it out
+    #[account(
+        seeds = [b"track_policy", registry.key().as_ref(), track_id.as_ref()],
+        bump
+    )]
+    pub track_policy: UncheckedAccount<'info>,
+
+    /// CHECK: must match the policy's fee recipient; required when the
+    /// track charges a fee
+    #[account(mut)]
+    pub track_fee_recipient: Option<UncheckedAccount<'info>>,
+
+    /// Optional pass for the session key, created alongside the nullifier
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + AccessPass::SIZE,
+        seeds = [b"access_pass", registry.key().as_ref(), track_id.as_ref(), nullifier_hash.as_ref()],
+        bump
+    )]
+    pub access_pass: Option<Box<Account<'info, AccessPass>>>,
+
+    /// CHECK: the `Treasury` PDA, which may not exist. Always required so
+    /// the verification fee can't be skipped
+    #[account(
+        mut,
+        seeds = [b"treasury"],
+        bump
+    )]
+    pub treasury: UncheckedAccount<'info>,
+
+    /// Payer's token account, required when the fee is in an SPL token
+    #[account(mut)]
+    pub payer_token_account: Option<Box<Account<'info, TokenAccount>>>,
+
+    /// Treasury's associated token account for the fee mint
+    #[account(mut)]
+    pub treasury_token_account: Option<Box<Account<'info, TokenAccount>>>,
+
+    pub token_program: Option<Program<'info, Token>>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct OpenProofBuffer<'info> {
+    #[account(
+        init,
+        payer = payer,
+        space = ProofBuffer::space(0),
+        seeds = [b"proof_buffer", payer.key().as_ref()],
+        bump
+    )]
+    pub proof_buffer: Box<Account<'info, ProofBuffer>>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(entries: Vec<BatchEntry>)]
+pub struct AppendProofBuffer<'info> {
+    #[account(
+        mut,
+        seeds = [b"proof_buffer", payer.key().as_ref()],
+        bump = proof_buffer.bump,
+        realloc = ProofBuffer::space(proof_buffer.entries.len() + entries.len()),
+        realloc::payer = payer,
+        realloc::zero = false
+    )]
+    pub proof_buffer: Box<Account<'info, ProofBuffer>>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CloseProofBuffer<'info> {
+    #[account(
+        mut,
+        close = payer,
+        seeds = [b"proof_buffer", payer.key().as_ref()],
+        bump = proof_buffer.bump
+    )]
+    pub proof_buffer: Box<Account<'info, ProofBuffer>>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(
+    merkle_root_snapshot: [u8; 32],
+    epoch: u64,
+    circuit_id: u32,
+    circuit_version: u16
+)]
+pub struct VerifyOwnershipBatch<'info> {
+    #[account(
+        mut,
+        seeds = [b"state"],
+        bump = state.bump
+    )]
+    pub state: Box<Account<'info, ProtocolState>>,
+
+    #[account(
+        mut,
+        seeds = [b"registry", registry.registry_id.to_le_bytes().as_ref()],
+        bump = registry.bump
+    )]
+    pub registry: Box<Account<'info, Registry>>,
+
+    #[account(
+        seeds = [b"verifying_key", circuit_id.to_le_bytes().as_ref(), circuit_version.to_le_bytes().as_ref()],
+        bump = verifying_key.bump
+    )]
+    pub verifying_key: Box<Account<'info, VerifyingKey>>,
+
+    /// Proofs to verify; closed to the payer
+    #[account(
+        mut,
+        close = payer,
+        seeds = [b"proof_buffer", payer.key().as_ref()],
+        bump = proof_buffer.bump
+    )]
+    pub proof_buffer: Box<Account<'info, ProofBuffer>>,
+
+    /// CHECK: the registry's `NullifierArchive` PDA, which may not exist.
+    /// Always required so archived nullifiers can't be replayed by
+    /// leaving it out
+    #[account(
+        seeds = [b"nullifier_archive", registry.key().as_ref()],
+        bump
+    )]
+    pub nullifier_archive: UncheckedAccount<'info>,
+
+    /// CHECK: the `Treasury` PDA, which may not exist. Always required so
+    /// the verification fee can't be skipped
+    #[account(
+        mut,
+        seeds = [b"treasury"],
+        bump
+    )]
+    pub treasury: UncheckedAccount<'info>,
+
+    /// Submitter, named as relayer in every proof
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    /// Payer's token account, required when the fee is in an SPL token
+    #[account(mut)]
+    pub payer_token_account: Option<Box<Account<'info, TokenAccount>>>,
+
+    /// Treasury's associated token account for the fee mint
+    #[account(mut)]
+    pub treasury_token_account: Option<Box<Account<'info, TokenAccount>>>,
+
+    pub token_program: Option<Program<'info, Token>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(nullifier_hash: [u8; 32])]
+pub struct GetVerificationStatus<'info> {
+    #[account(
+        seeds = [b"registry", registry.registry_id.to_le_bytes().as_ref()],
+        bump = registry.bump
+    )]
+    pub registry: Box<Account<'info, Registry>>,
+
+    /// CHECK: the `NullifierAccount` PDA, which only exists once spent
+    #[account(
+        seeds = [b"nullifier", registry.key().as_ref(), nullifier_hash.as_ref()],
+        bump
+    )]
+    pub nullifier: UncheckedAccount<'info>,
+
+    /// CHECK: first page of the nullifier's `NullifierShard`, which may
+    /// not exist; later pages are passed as remaining accounts
+    #[account(
+        seeds = [
+            b"nullifier_shard",
+            registry.key().as_ref(),
+            &[nullifier_set::shard_prefix(&nullifier_hash)],
+            0u16.to_le_bytes().as_ref()
+        ],
+        bump
+    )]
+    pub nullifier_shard: UncheckedAccount<'info>,
+
+    /// CHECK: the registry's `NullifierArchive` PDA, which may not exist
+    #[account(
+        seeds = [b"nullifier_archive", registry.key().as_ref()],
+        bump
+    )]
+    pub nullifier_archive: UncheckedAccount<'info>,
+
+    /// CHECK: the nullifier's `ArchiveExclusion` PDA, which may not exist
+    #[account(
+        seeds = [b"archive_exclusion", registry.key().as_ref(), nullifier_hash.as_ref()],
+        bump
+    )]
+    pub archive_exclusion: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(nullifier_hash: [u8; 32])]
+pub struct CheckNullifier<'info> {
+    #[account(
+        seeds = [b"registry", registry.registry_id.to_le_bytes().as_ref()],
+        bump = registry.bump
+    )]
+    pub registry: Box<Account<'info, Registry>>,
+
+    #[account(
+        seeds = [b"nullifier", registry.key().as_ref(), nullifier_hash.as_ref()],
+        bump = nullifier.bump
+    )]
+    pub nullifier: Option<Account<'info, NullifierAccount>>,
+
+    /// First page of the prefix shard; later pages are passed as remaining
+    /// accounts
+    #[account(
+        seeds = [
+            b"nullifier_shard",
+            registry.key().as_ref(),
+            &[nullifier_set::shard_prefix(&nullifier_hash)],
+            0u16.to_le_bytes().as_ref()
+        ],
+        bump = nullifier_shard.bump
+    )]
+    pub nullifier_shard: Option<Box<Account<'info, NullifierShard>>>,
+}
+
+// ========== STATE ==========
+
+#[account]
+pub struct ProtocolState {
+    /// Authority that manages verifying keys and registries
+    /// (a wallet or a `Multisig` PDA)
+    pub authority: Pubkey,
+    /// Authority proposed via `propose_authority`, awaiting acceptance
+    pub pending_authority: Option<Pubkey>,
+    /// Total successful verifications across all registries
+    pub verification_count: u64,
+    /// Seconds per nullifier epoch (0 = a single epoch forever)
+    pub epoch_length: i64,
+    /// Paused instructions, as `pause_flags` bits
+    pub paused: u32,
+    /// Key that can pause (but not unpause) instructions
+    pub pause_guardian: Option<Pubkey>,
+    /// PDA bump
+    pub bump: u8,
+    /// Layout version (see `migration`)
+    pub version: u8,
+    /// Space for future fields
+    pub reserved: [u8; 64],
+}
+
+impl ProtocolState {
+    /// Size of the unversioned v1 layout
+    pub const V1_SIZE: usize = migration::ProtocolStateV1::SIZE;
+
+    pub const SIZE: usize = 32 + (1 + 32) + 8 + 8 + 4 + (1 + 32) + 1 + 1 + 64;
+
+    /// Fail if any of `flags` is paused
+    pub fn require_unpaused(&self, flags: u32) -> Result<()> {
+        require!(self.paused & flags == 0, PhantomError::Paused);
+        Ok(())
+    }
+
+    /// Epoch a proof submitted at `now` must commit to
+    pub fn current_epoch(&self, now: i64) -> u64 {
+        if self.epoch_length == 0 {
+            return 0;
+        }
+        (now / self.epoch_length).max(0) as u64
+    }
+}
+
+/// Instructions that can be paused through `ProtocolState::paused`
+pub mod pause_flags {
+    pub const VERIFY_OWNERSHIP: u32 = 1 << 0;
+    pub const UPDATE_MERKLE_ROOT: u32 = 1 << 1;
+    pub const PROPOSE_MERKLE_ROOT: u32 = 1 << 2;
+    pub const FINALIZE_MERKLE_ROOT: u32 = 1 << 3;
+    pub const REGISTER_RIGHTS: u32 = 1 << 4;
+}
+
+#[account]
+pub struct Registry {
+    /// Registry identifier (PDA seed)
+    pub registry_id: u64,
+    /// Authority that can update this registry's merkle root
+    /// (a wallet or a `Multisig` PDA)
+    pub authority: Pubkey,
+    /// Authority proposed via `propose_registry_authority`
+    pub pending_authority: Option<Pubkey>,
+    /// Current merkle root of this rights registry
+    pub merkle_root: [u8; 32],
+    /// Successful verifications against this registry
+    pub verification_count: u64,
+    /// Ring buffer of recent roots (current root included)
+    pub root_history: [RootEntry; ROOT_HISTORY_SIZE],
+    /// Next ring buffer slot to write
+    pub root_history_index: u8,
+    /// Seconds a proposed root must wait before it can be finalized
+    /// (0 = immediate updates via `update_merkle_root`)
+    pub root_update_delay: i64,
+    /// Key that may veto a pending root during the delay
+    pub guardian: Option<Pubkey>,
+    /// Root awaiting finalization
+    pub pending_root: Option<PendingRoot>,
+    /// Root is derived from the on-chain rights tree
+    pub is_tree_managed: bool,
+    /// Spent nullifiers are kept in prefix shards instead of one PDA each
+    pub sharded_nullifiers: bool,
+    /// PDA bump
+    pub bump: u8,
+}
+
+impl Registry {
+    pub const SIZE: usize = 8
+        + 32
+        + (1 + 32)
+        + 32
+        + 8
+        + RootEntry::SIZE * ROOT_HISTORY_SIZE
+        + 1
+        + 8
+        + (1 + 32)
+        + (1 + PendingRoot::SIZE)
+        + 1
+        + 1
+        + 1;
+
+    /// Make `root` current and record it in the history window,
+    /// returning the previous root
+    pub fn set_root(
+        &mut self,
+        root: [u8; 32],
+        expires_at: Option<i64>,
+        clock: &Clock,
+    ) -> Result<[u8; 32]> {
+        if let Some(expires_at) = expires_at {
+            require!(expires_at > clock.unix_timestamp, PhantomError::InvalidRootExpiry);
+        }
+
+        let old_root = self.merkle_root;
+        self.merkle_root = root;
+        self.push_root(RootEntry {
+            root,
+            slot: clock.slot,
+            set_at: clock.unix_timestamp,
+            expires_at,
+        });
+        Ok(old_root)
+    }
+
+    /// Record a new root, overwriting the oldest entry once the window is full
+    pub fn push_root(&mut self, entry: RootEntry) {
+        let index = self.root_history_index as usize % ROOT_HISTORY_SIZE;
+        self.root_history[index] = entry;
+        self.root_history_index = ((index + 1) % ROOT_HISTORY_SIZE) as u8;
+    }
+
+    /// Accept `root` if it's in the history window and hasn't expired
+    pub fn check_root(&self, root: &[u8; 32], now: i64) -> Result<()> {
+        // An all-zero root means no registry has been published yet
+        require!(*root != [0u8; 32], PhantomError::InvalidMerkleRoot);
+
+        let entry = self
+            .root_history
+            .iter()
+            .find(|entry| entry.root == *root)
+            .ok_or(PhantomError::InvalidMerkleRoot)?;
+
+        if let Some(expires_at) = entry.expires_at {
+            require!(now < expires_at, PhantomError::MerkleRootExpired);
+        }
+        Ok(())
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy)]
+pub struct PendingRoot {
+    /// Proposed merkle root
+    pub root: [u8; 32],
+    /// Expiry the root will get once finalized
+    pub expires_at: Option<i64>,
+    /// Timestamp the root was proposed at
+    pub proposed_at: i64,
+    /// Earliest timestamp the root can be finalized at
+    pub eligible_at: i64,
+}
+
+impl PendingRoot {
+    pub const SIZE: usize = 32 + (1 + 8) + 8 + 8;
+}
+
+#[account]
+pub struct RightsTree {
+    /// Registry whose root this tree maintains
+    pub registry: Pubkey,
+    /// Index the next leaf will be inserted at
+    pub next_index: u64,
+    /// Rightmost filled subtree root at each level
+    pub filled_subtrees: [[u8; 32]; merkle_tree::TREE_DEPTH],
+    /// Empty subtree roots at each level
+    pub zeros: [[u8; 32]; merkle_tree::TREE_DEPTH],
+    /// PDA bump
+    pub bump: u8,
+}
+
+impl RightsTree {
+    pub const SIZE: usize = 32 + 8 + 32 * merkle_tree::TREE_DEPTH * 2 + 1;
+}
+
+/// Most proofs `verify_ownership_batch` takes at once
+pub const MAX_BATCH_SIZE: usize = 10;
+
+/// Remaining accounts `verify_ownership_batch` takes per proof
+pub const BATCH_ACCOUNTS_PER_ENTRY: usize = 4;
+
+/// Proofs staged for `verify_ownership_batch`
+#[account]
+pub struct ProofBuffer {
+    pub entries: Vec<BatchEntry>,
+    /// PDA bump
+    pub bump: u8,
+}
+
+impl ProofBuffer {
+    pub const fn space(entries: usize) -> usize {
+        8 + 4 + entries * BatchEntry::SIZE + 1
+    }
+}
+
+/// One proof in a batch; the rest of its public inputs are shared
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct BatchEntry {
+    pub proof: [u8; groth16::PROOF_LEN],
+    pub track_id: [u8; 32],
+    pub nullifier_hash: [u8; 32],
+    pub rights_tier: u8,
+}
+
+impl BatchEntry {
+    pub const SIZE: usize = groth16::PROOF_LEN + 32 + 32 + 1;
+}
+
+/// Number of recent roots accepted by `verify_ownership`
+pub const ROOT_HISTORY_SIZE: usize = 16;
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default)]
+pub struct RootEntry {
+    /// Merkle root
+    pub root: [u8; 32],
+    /// Slot the root was set in
+    pub slot: u64,
+    /// Timestamp the root was set at
+    pub set_at: i64,
+    /// Proofs against this root are rejected from this timestamp on
+    pub expires_at: Option<i64>,
+}
+
+impl RootEntry {
+    pub const SIZE: usize = 32 + 8 + 8 + (1 + 8);
+}
+
+#[account]
+pub struct NullifierAccount {
+    /// Whether this nullifier has been used
+    pub is_used: bool,
+    /// Track ID this nullifier was used for
+    pub track_id: [u8; 32],
+    /// Epoch the nullifier was spent in
+    pub epoch: u64,
+    /// Timestamp when used
+    pub used_at: i64,
+    /// PDA bump
+    pub bump: u8,
+    /// Layout version (see `migration`)
+    pub version: u8,
+    /// Registry verification count after the verification that spent
+    /// it (carved out of the reserved space; zero if not recorded)
+    pub verification_id: u64,
+    /// Space for future fields
+    pub reserved: [u8; 8],
+    /// Account that funded the PDA and gets its rent back when it's
+    /// archived (added in v3; default for nullifiers migrated from v1 or
+    /// v2, which didn't record it)
+    pub payer: Pubkey,
+}
+
+impl NullifierAccount {
+    /// Size of the unversioned v1 layout
+    pub const V1_SIZE: usize = migration::NullifierAccountV1::SIZE;
+
+    /// Size of the v2 layout, before `payer`
+    pub const V2_SIZE: usize = migration::NullifierAccountV2::SIZE;
+
+    pub const SIZE: usize = Self::V2_SIZE + 32;
+
+    /// Fail unless `recipient` may receive the rent when the nullifier is
+    /// archived: its recorded payer, or any account the authority picks
+    /// for a migrated nullifier that has none
+    pub fn check_rent_recipient(&self, recipient: &Pubkey) -> Result<()> {
+        if self.payer != Pubkey::default() {
+            require_keys_eq!(*recipient, self.payer, PhantomError::InvalidRentRecipient);
+        }
+        Ok(())
+    }
+}
+
+/// Header of a nullifier shard page; the sorted nullifier hashes follow it
+/// directly in the account data (see `nullifier_set`)
+#[account]
+pub struct NullifierShard {
+    /// Registry the nullifiers belong to
+    pub registry: Pubkey,
+    /// Low byte shared by every nullifier in this shard
+    pub prefix: u8,
+    /// Index of this page among the prefix's pages
+    pub page: u16,
+    /// Number of stored nullifiers
+    pub count: u32,
+    /// PDA bump
+    pub bump: u8,
+}
+
+impl NullifierShard {
+    pub const SIZE: usize = 32 + 1 + 2 + 4 + 1;
+
+    /// Offset of the first entry in the account data
+    pub const ENTRIES_OFFSET: usize = 8 + Self::SIZE;
+
+    /// Account size holding `entries` nullifiers
+    pub const fn space(entries: usize) -> usize {
+        Self::ENTRIES_OFFSET + entries * nullifier_set::ENTRY_LEN
+    }
+}
+
+/// Indexed Merkle accumulator of a registry's closed nullifier PDAs (see
+/// `nullifier_archive`)
+#[account]
+pub struct NullifierArchive {
+    /// Registry whose closed nullifier PDAs this archive holds
+    pub registry: Pubkey,
+    /// Root of the indexed tree of archived nullifiers
+    pub root: [u8; 32],
+    /// Index the next leaf will be inserted at (leaf 0 is the sentinel)
+    pub next_index: u64,
+    /// Left node at each level of the last insert's path
+    pub filled_subtrees: [[u8; 32]; merkle_tree::TREE_DEPTH],
+    /// Empty subtree roots at each level
+    pub zeros: [[u8; 32]; merkle_tree::TREE_DEPTH],
+    /// PDA bump
+    pub bump: u8,
+}
+
+impl NullifierArchive {
+    pub const SIZE: usize = 32 + 32 + 8 + 32 * merkle_tree::TREE_DEPTH * 2 + 1;
+}
+
+/// Record that a nullifier was proven absent from the archive
+#[account]
+pub struct ArchiveExclusion {
+    /// Archive `next_index` the proof was checked against
+    pub archive_size: u64,
+    /// PDA bump
+    pub bump: u8,
+}
+
+impl ArchiveExclusion {
+    pub const SIZE: usize = 8 + 1;
+}
+
+/// Per-track access rules set by the registry authority
+#[account]
+pub struct TrackPolicy {
+    /// Registry the track belongs to
+    pub registry: Pubkey,
+    /// Track this policy applies to
+    pub track_id: [u8; 32],
+    /// Access terms
+    pub config: TrackPolicyConfig,
+    /// Verification is suspended for this track
+    pub is_paused: bool,
+    /// PDA bump
+    pub bump: u8,
+}
+
+impl TrackPolicy {
+    pub const SIZE: usize = 32 + 32 + TrackPolicyConfig::SIZE + 1 + 1;
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy)]
+pub struct TrackPolicyConfig {
+    /// Whether the track can be verified at all
+    pub is_allowed: bool,
+    /// Seconds an access pass stays valid after verification
+    pub access_duration: i64,
+    /// Lowest rights tier that may verify
+    pub required_tier: u8,
+    /// Lamports charged to the payer per verification (0 = free)
+    pub fee: u64,
+    /// Account the fee is paid to
+    pub fee_recipient: Pubkey,
+}
+
+impl TrackPolicyConfig {
+    pub const SIZE: usize = 1 + 8 + 1 + 8 + 32;
+
+    pub fn validate(&self) -> Result<()> {
+        require!(self.access_duration > 0, PhantomError::InvalidAccessDuration);
+        Ok(())
+    }
+}
+
+/// Proof that a track's ownership was verified, held by a session key so
+/// content servers can check one account instead of the proof
+#[account]
+pub struct AccessPass {
+    /// Registry ownership was proven against
+    pub registry: Pubkey,
+    /// Track the pass grants access to
+    pub track_id: [u8; 32],
+    /// Nullifier spent to issue the pass
+    pub nullifier_hash: [u8; 32],
+    /// Ephemeral key named in the proof that the pass belongs to
+    pub session_key: Pubkey,
+    /// Timestamp the pass was issued at
+    pub issued_at: i64,
+    /// Pass is invalid from this timestamp on
+    pub expires_at: i64,
+    /// PDA bump
+    pub bump: u8,
+    /// Account that funded the PDA and gets its rent back when the pass
+    /// is revoked
+    pub payer: Pubkey,
+}
+
+impl AccessPass {
+    pub const SIZE: usize = 32 + 32 + 32 + 32 + 8 + 8 + 1 + 32;
+}
+
+/// Protocol treasury; holds lamport fees directly and owns the associated
+/// token accounts SPL fees are paid into
+#[account]
+pub struct Treasury {
+    /// Fee charged per verification (0 = free)
+    pub fee_amount: u64,
+    /// Mint the fee is paid in (None = lamports)
+    pub fee_mint: Option<Pubkey>,
+    /// PDA bump
+    pub bump: u8,
+}
+
+impl Treasury {
+    pub const SIZE: usize = 8 + (1 + 32) + 1;
+}
+
+/// Relayer allowed to claim fees for submitting proofs
+#[account]
+pub struct Relayer {
+    /// Relayer wallet (PDA seed)
+    pub relayer: Pubkey,
+    /// Whether the relayer may currently claim fees
+    pub is_active: bool,
+    /// PDA bump
+    pub bump: u8,
+}
+
+impl Relayer {
+    pub const SIZE: usize = 32 + 1 + 1;
+}
+
+/// Protocol-owned lamports that relayer fees are paid from
+#[account]
+pub struct FeeVault {
+    /// Largest fee a single verification can claim
+    pub max_relayer_fee: u64,
+    /// PDA bump
+    pub bump: u8,
+}
+
+impl FeeVault {
+    pub const SIZE: usize = 8 + 1;
+}
+
+#[account]
+pub struct VerifyingKey {
+    /// Circuit this key belongs to
+    pub circuit_id: u32,
+    /// Circuit version (bumped on depth/hash changes)
+    pub version: u16,
+    /// Whether proofs against this key are still accepted
+    pub is_active: bool,
+    /// Groth16 verifying key
+    pub vk: Groth16VerifyingKey,
+    /// PDA bump
+    pub bump: u8,
+}
+
+impl VerifyingKey {
+    pub const SIZE: usize = 4 + 2 + 1 + Groth16VerifyingKey::MAX_SIZE + 1;
+}
+
+/// Maximum members of a multisig authority
+pub const MAX_MULTISIG_SIGNERS: usize = 10;
+
+#[account]
+pub struct Multisig {
+    /// Multisig identifier (PDA seed)
+    pub multisig_id: u64,
+    /// Members allowed to co-sign
+    pub signers: Vec<Pubkey>,
+    /// Number of member signatures required
+    pub threshold: u8,
+    /// PDA bump
+    pub bump: u8,
+}
+
+impl Multisig {
+    pub const SIZE: usize = 8 + (4 + 32 * MAX_MULTISIG_SIGNERS) + 1 + 1;
+
+    pub fn validate(signers: &[Pubkey], threshold: u8) -> Result<()> {
         require!(
-            state.merkle_root == merkle_root_snapshot,
-            PhantomError::InvalidMerkleRoot
+            !signers.is_empty() && signers.len() <= MAX_MULTISIG_SIGNERS,
+            PhantomError::InvalidMultisig
         );
-        
-        // 3. Verify proof data is present
-        // TODO: Integrate with Sunspot or similar ZK verifier
-        // For MVP, we check proof is non-empty
-        require!(proof_data.len() > 0, PhantomError::InvalidProof);
-        
-        // 4. Mark nullifier as used
-        nullifier.is_used = true;
-        nullifier.track_id = track_id;
-        nullifier.used_at = Clock::get()?.unix_timestamp;
-        nullifier.bump = ctx.bumps.nullifier;
-        
-        // 5. Increment verification count
-        state.verification_count = state.verification_count.checked_add(1)
-            .ok_or(PhantomError::Overflow)?;
-        
-        // 6. Emit verification event
-        emit!(OwnershipVerified {
-            track_id,
-            nullifier_hash,
-            verification_id: state.verification_count,
-            timestamp: Clock::get()?.unix_timestamp,
-        });
-        
-        msg!("Ownership verified for track");
+        require!(
+            threshold > 0 && threshold as usize <= signers.len(),
+            PhantomError::InvalidMultisig
+        );
+        for (i, signer) in signers.iter().enumerate() {
+            require!(!signers[..i].contains(signer), PhantomError::InvalidMultisig);
+        }
         Ok(())
     }
 
-    /// Check if a nullifier has been used (view function)
-    pub fn check_nullifier(ctx: Context<CheckNullifier>) -> Result<bool> {
-        Ok(ctx.accounts.nullifier.is_used)
+    /// Count distinct members among the signing accounts
+    pub fn approvals(&self, signing_keys: &[Pubkey]) -> usize {
+        self.signers
+            .iter()
+            .filter(|member| signing_keys.contains(member))
+            .count()
     }
 }
 
-// ========== ACCOUNTS ==========
+/// Require `expected` to have authorized this instruction: either it
+/// signed directly, or it's a multisig and enough members signed
+/// (`authority` plus any signing co-signers in remaining accounts)
+fn require_authority(
+    expected: &Pubkey,
+    authority: &Signer,
+    multisig: &Option<Box<Account<Multisig>>>,
+    co_signers: &[AccountInfo],
+) -> Result<()> {
+    if authority.key() == *expected {
+        return Ok(());
+    }
 
-#[derive(Accounts)]
-pub struct Initialize<'info> {
-    #[account(
-        init,
-        payer = authority,
-        space = 8 + ProtocolState::SIZE,
-        seeds = [b"state"],
-        bump
-    )]
-    pub state: Account<'info, ProtocolState>,
-    
-    #[account(mut)]
-    pub authority: Signer<'info>,
-    
-    pub system_program: Program<'info, System>,
-}
+    let multisig = multisig.as_ref().ok_or(PhantomError::Unauthorized)?;
+    require_keys_eq!(multisig.key(), *expected, PhantomError::Unauthorized);
 
-#[derive(Accounts)]
-pub struct UpdateRoot<'info> {
-    #[account(
-        mut,
-        seeds = [b"state"],
-        bump = state.bump
-    )]
-    pub state: Account<'info, ProtocolState>,
-    
-    pub authority: Signer<'info>,
+    let signing_keys: Vec<Pubkey> = std::iter::once(authority.key())
+        .chain(co_signers.iter().filter(|a| a.is_signer).map(|a| a.key()))
+        .collect();
+    require!(
+        multisig.approvals(&signing_keys) >= multisig.threshold as usize,
+        PhantomError::MultisigThresholdNotMet
+    );
+    Ok(())
 }
 
-#[derive(Accounts)]
-#[instruction(proof_data: Vec<u8>, track_id: [u8; 32], nullifier_hash: [u8; 32])]
-pub struct VerifyOwnership<'info> {
-    #[account(
-        mut,
-        seeds = [b"state"],
-        bump = state.bump
-    )]
-    pub state: Account<'info, ProtocolState>,
-    
-    #[account(
-        init,
-        payer = payer,
-        space = 8 + NullifierAccount::SIZE,
-        seeds = [b"nullifier", nullifier_hash.as_ref()],
-        bump
-    )]
-    pub nullifier: Account<'info, NullifierAccount>,
-    
-    #[account(mut)]
-    pub payer: Signer<'info>,
-    
-    pub system_program: Program<'info, System>,
+/// Result of a successful `verify_ownership`, returned via
+/// `set_return_data`
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct VerificationReceipt {
+    /// Program that verified the proof. Return data is overwritten by any
+    /// later CPI, so callers should check this is the phantom-streams ID
+    pub verifier: Pubkey,
+    /// Registry the proof was checked against
+    pub registry_id: u64,
+    /// Track ownership was proven for
+    pub track_id: [u8; 32],
+    /// Nullifier spent by this verification
+    pub nullifier_hash: [u8; 32],
+    /// Epoch the nullifier was spent in
+    pub epoch: u64,
+    /// Registry verification count after this verification
+    pub verification_id: u64,
 }
 
-#[derive(Accounts)]
-#[instruction(nullifier_hash: [u8; 32])]
-pub struct CheckNullifier<'info> {
-    #[account(
-        seeds = [b"nullifier", nullifier_hash.as_ref()],
-        bump = nullifier.bump
-    )]
-    pub nullifier: Account<'info, NullifierAccount>,
+/// Deserialize an account that may not have been created yet; None if
+/// `info` is empty
+fn load_optional<T: AccountDeserialize + Owner>(info: &AccountInfo) -> Result<Option<T>> {
+    if info.data_is_empty() {
+        return Ok(None);
+    }
+    require_keys_eq!(*info.owner, T::owner(), ErrorCode::AccountOwnedByWrongProgram);
+    let data = info.try_borrow_data()?;
+    Ok(Some(T::try_deserialize(&mut &data[..])?))
 }
 
-// ========== STATE ==========
+/// Whether a nullifier has been spent, as far as the live set shows
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NullifierState {
+    Unused,
+    Used,
+    /// Not in the live set, but the archive is non-empty and there's no
+    /// current `ArchiveExclusion` for it
+    PossiblyArchived,
+}
 
-#[account]
-pub struct ProtocolState {
-    /// Authority that can update merkle root
-    pub authority: Pubkey,
-    /// Current merkle root of rights registry
-    pub merkle_root: [u8; 32],
-    /// Total successful verifications
-    pub verification_count: u64,
-    /// PDA bump
-    pub bump: u8,
+/// Result of `get_verification_status`
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct VerificationStatus {
+    pub status: NullifierState,
+    /// Registry the nullifier was looked up in
+    pub registry: Pubkey,
+    /// Track the nullifier was spent on; only nullifier PDAs record the
+    /// details below, sharded registries keep just the hash
+    pub track_id: Option<[u8; 32]>,
+    /// When the nullifier was spent
+    pub used_at: Option<i64>,
+    /// Registry verification count after the verification that spent it
+    pub verification_id: Option<u64>,
 }
 
-impl ProtocolState {
-    pub const SIZE: usize = 32 + 32 + 8 + 1;
+/// Charge `count` protocol verification fees from the payer into the
+/// treasury, in lamports or the treasury's fee mint
+fn charge_verification_fee<'info>(
+    count: u64,
+    treasury_info: &AccountInfo<'info>,
+    payer: &AccountInfo<'info>,
+    payer_token_account: Option<AccountInfo<'info>>,
+    treasury_token_account: Option<AccountInfo<'info>>,
+    token_program: Option<AccountInfo<'info>>,
+    system_program: &AccountInfo<'info>,
+) -> Result<()> {
+    let Some(treasury) = load_optional::<Treasury>(treasury_info)? else {
+        return Ok(());
+    };
+    let amount = treasury.fee_amount.checked_mul(count).ok_or(PhantomError::Overflow)?;
+    if amount == 0 {
+        return Ok(());
+    }
+
+    match treasury.fee_mint {
+        None => system_program::transfer(
+            CpiContext::new(
+                system_program.clone(),
+                system_program::Transfer {
+                    from: payer.clone(),
+                    to: treasury_info.clone(),
+                },
+            ),
+            amount,
+        ),
+        Some(mint) => {
+            let (Some(from), Some(to), Some(token_program)) =
+                (payer_token_account, treasury_token_account, token_program)
+            else {
+                return err!(PhantomError::InvalidFeeAccount);
+            };
+            require_keys_eq!(
+                to.key(),
+                get_associated_token_address(treasury_info.key, &mint),
+                PhantomError::InvalidFeeAccount
+            );
+            token::transfer(
+                CpiContext::new(
+                    token_program,
+                    token::Transfer {
+                        from,
+                        to,
+                        authority: payer.clone(),
+                    },
+                ),
+                amount,
+            )
+        }
+    }
 }
 
-#[account]
-pub struct NullifierAccount {
-    /// Whether this nullifier has been used
-    pub is_used: bool,
-    /// Track ID this nullifier was used for
-    pub track_id: [u8; 32],
-    /// Timestamp when used
-    pub used_at: i64,
-    /// PDA bump
-    pub bump: u8,
+/// Create a program-owned PDA the way Anchor's `init` does, including
+/// when the address has already been sent lamports
+fn create_pda<'info>(
+    account: &AccountInfo<'info>,
+    payer: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    space: usize,
+    seeds: &[&[u8]],
+) -> Result<()> {
+    let rent = Rent::get()?.minimum_balance(space);
+    let signer_seeds = &[seeds];
+
+    if account.lamports() == 0 {
+        return system_program::create_account(
+            CpiContext::new_with_signer(
+                system_program.clone(),
+                system_program::CreateAccount {
+                    from: payer.clone(),
+                    to: account.clone(),
+                },
+                signer_seeds,
+            ),
+            rent,
+            space as u64,
+            &crate::ID,
+        );
+    }
+
+    let shortfall = rent.saturating_sub(account.lamports());
+    if shortfall > 0 {
+        system_program::transfer(
+            CpiContext::new(
+                system_program.clone(),
+                system_program::Transfer {
+                    from: payer.clone(),
+                    to: account.clone(),
+                },
+            ),
+            shortfall,
+        )?;
+    }
+    system_program::allocate(
+        CpiContext::new_with_signer(
+            system_program.clone(),
+            system_program::Allocate {
+                account_to_allocate: account.clone(),
+            },
+            signer_seeds,
+        ),
+        space as u64,
+    )?;
+    system_program::assign(
+        CpiContext::new_with_signer(
+            system_program.clone(),
+            system_program::Assign {
+                account_to_assign: account.clone(),
+            },
+            signer_seeds,
+        ),
+        &crate::ID,
+    )
 }
 
-impl NullifierAccount {
-    pub const SIZE: usize = 1 + 32 + 8 + 1;
+/// Close a program-owned account, sending its lamports to `destination`
+fn close_account(account: &AccountInfo, destination: &AccountInfo) -> Result<()> {
+    **destination.try_borrow_mut_lamports()? += account.lamports();
+    **account.try_borrow_mut_lamports()? = 0;
+    account.assign(&system_program::ID);
+    account.resize(0)?;
+    Ok(())
 }
 
 // ========== EVENTS ==========
 
+#[event]
+pub struct RegistryCreated {
+    pub registry_id: u64,
+    pub authority: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct MultisigCreated {
+    pub multisig: Pubkey,
+    pub signers: Vec<Pubkey>,
+    pub threshold: u8,
+    pub timestamp: i64,
+}
+
+/// `registry_id` is `None` for the protocol authority
+#[event]
+pub struct AuthorityProposed {
+    pub registry_id: Option<u64>,
+    pub current_authority: Pubkey,
+    pub pending_authority: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct AuthorityTransferred {
+    pub registry_id: Option<u64>,
+    pub old_authority: Pubkey,
+    pub new_authority: Pubkey,
+    pub timestamp: i64,
+}
+
 #[event]
 pub struct MerkleRootUpdated {
+    pub registry_id: u64,
     pub old_root: [u8; 32],
     pub new_root: [u8; 32],
+    pub slot: u64,
+    pub expires_at: Option<i64>,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct RootTimelockUpdated {
+    pub registry_id: u64,
+    pub delay: i64,
+    pub guardian: Option<Pubkey>,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct EpochLengthUpdated {
+    pub epoch_length: i64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct AccountMigrated {
+    pub account: Pubkey,
+    pub version: u8,
+    /// Root held by a v1 `ProtocolState`, which now belongs in a registry
+    pub legacy_merkle_root: Option<[u8; 32]>,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct PauseChanged {
+    pub previous: u32,
+    pub paused: u32,
+    pub changed_by: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct PauseGuardianUpdated {
+    pub guardian: Option<Pubkey>,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct MerkleRootProposed {
+    pub registry_id: u64,
+    pub root: [u8; 32],
+    pub expires_at: Option<i64>,
+    pub eligible_at: i64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct MerkleRootVetoed {
+    pub registry_id: u64,
+    pub root: [u8; 32],
+    pub vetoed_by: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct RightsRegistered {
+    pub registry_id: u64,
+    pub leaf: [u8; 32],
+    pub leaf_index: u64,
+    pub root: [u8; 32],
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct VerificationFeeUpdated {
+    pub fee_amount: u64,
+    pub fee_mint: Option<Pubkey>,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct FeesWithdrawn {
+    pub mint: Option<Pubkey>,
+    pub amount: u64,
+    pub destination: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct RelayerStatusChanged {
+    pub relayer: Pubkey,
+    pub is_active: bool,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct RelayerFeeCapUpdated {
+    pub max_relayer_fee: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct TrackPolicyUpdated {
+    pub registry_id: u64,
+    pub track_id: [u8; 32],
+    pub config: TrackPolicyConfig,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct TrackPauseChanged {
+    pub registry_id: u64,
+    pub track_id: [u8; 32],
+    pub is_paused: bool,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct AccessPassIssued {
+    pub registry_id: u64,
+    pub track_id: [u8; 32],
+    pub session_key: Pubkey,
+    pub expires_at: i64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct AccessPassRevoked {
+    pub registry_id: u64,
+    pub track_id: [u8; 32],
+    pub session_key: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct VerifyingKeyRegistered {
+    pub circuit_id: u32,
+    pub version: u16,
+    pub public_inputs: u8,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct VerifyingKeyStatusChanged {
+    pub circuit_id: u32,
+    pub version: u16,
+    pub is_active: bool,
     pub timestamp: i64,
 }
 
 #[event]
 pub struct OwnershipVerified {
+    pub registry_id: u64,
     pub track_id: [u8; 32],
     pub nullifier_hash: [u8; 32],
+    pub epoch: u64,
+    pub relayer: Pubkey,
+    pub relayer_fee: u64,
+    pub circuit_id: u32,
+    pub circuit_version: u16,
     pub verification_id: u64,
     pub timestamp: i64,
 }
 
+#[event]
+pub struct NullifierArchived {
+    pub registry_id: u64,
+    pub nullifier_hash: [u8; 32],
+    pub leaf_index: u64,
+    pub root: [u8; 32],
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct OwnershipBatchVerified {
+    pub registry_id: u64,
+    pub track_ids: Vec<[u8; 32]>,
+    pub nullifier_hashes: Vec<[u8; 32]>,
+    pub epoch: u64,
+    pub relayer: Pubkey,
+    pub circuit_id: u32,
+    pub circuit_version: u16,
+    /// Verification id of the first proof; the rest follow in order
+    pub first_verification_id: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct NullifierStoreUpdated {
+    pub registry_id: u64,
+    pub sharded: bool,
+    pub timestamp: i64,
+}
+
 // ========== ERRORS ==========
 
 #[error_code]
@@ -234,4 +3848,133 @@ pub enum PhantomError {
     
     #[msg("Arithmetic overflow")]
     Overflow,
+
+    #[msg("Verifying key is malformed or doesn't match the circuit")]
+    InvalidVerifyingKey,
+
+    #[msg("Public input is not a canonical BN254 field element")]
+    NonCanonicalFieldElement,
+
+    #[msg("Verifying key has been retired")]
+    VerifyingKeyInactive,
+
+    #[msg("Merkle root has expired")]
+    MerkleRootExpired,
+
+    #[msg("Root expiry must be in the future")]
+    InvalidRootExpiry,
+
+    #[msg("No authority transfer is pending")]
+    NoPendingAuthority,
+
+    #[msg("Multisig signers or threshold are invalid")]
+    InvalidMultisig,
+
+    #[msg("Not enough multisig members signed")]
+    MultisigThresholdNotMet,
+
+    #[msg("Registry is timelocked - use propose_merkle_root")]
+    RootTimelockActive,
+
+    #[msg("A root update is already pending")]
+    RootUpdatePending,
+
+    #[msg("No root update is pending")]
+    NoPendingRoot,
+
+    #[msg("Root update delay has not elapsed")]
+    TimelockNotElapsed,
+
+    #[msg("Timelock delay must not be negative")]
+    InvalidTimelock,
+
+    #[msg("Registry root is managed by its rights tree")]
+    RootManagedByTree,
+
+    #[msg("Leaf is not a valid field element")]
+    InvalidLeaf,
+
+    #[msg("Rights tree is full")]
+    TreeFull,
+
+    #[msg("Nullifier account doesn't match the registry's nullifier store")]
+    InvalidNullifierStore,
+
+    #[msg("Nullifier store can't change after verifications")]
+    NullifierStoreLocked,
+
+    #[msg("Proof is not for the current epoch")]
+    InvalidEpoch,
+
+    #[msg("Epoch length must not be negative")]
+    InvalidEpochLength,
+
+    #[msg("Relayer is not registered or is inactive")]
+    RelayerNotRegistered,
+
+    #[msg("Relayer fee exceeds the protocol cap")]
+    RelayerFeeTooHigh,
+
+    #[msg("Fee vault can't cover the relayer fee")]
+    InsufficientVaultBalance,
+
+    #[msg("Access duration must be positive")]
+    InvalidAccessDuration,
+
+    #[msg("Track has no policy to issue an access pass from")]
+    TrackPolicyRequired,
+
+    #[msg("Access pass needs a session key")]
+    InvalidSessionKey,
+
+    #[msg("Track is not open for verification")]
+    TrackNotAllowed,
+
+    #[msg("Track is paused")]
+    TrackPaused,
+
+    #[msg("Rights tier is below the track's requirement")]
+    InsufficientRightsTier,
+
+    #[msg("Fee recipient doesn't match the track policy")]
+    InvalidFeeRecipient,
+
+    #[msg("Fee token accounts are missing or don't match the fee mint")]
+    InvalidFeeAccount,
+
+    #[msg("Treasury can't cover the withdrawal")]
+    InsufficientTreasuryBalance,
+
+    #[msg("Instruction is paused")]
+    Paused,
+
+    #[msg("Account is not a migratable v1 layout")]
+    InvalidAccountVersion,
+
+    #[msg("Invalid nullifier archive proof")]
+    InvalidArchiveProof,
+
+    #[msg("Nullifier must be proven absent from the archive")]
+    ArchiveProofRequired,
+
+    #[msg("Archive proof is out of date")]
+    StaleArchiveProof,
+
+    #[msg("Batch must hold between 1 and MAX_BATCH_SIZE proofs")]
+    InvalidBatchSize,
+
+    #[msg("Remaining accounts don't match the batch")]
+    InvalidBatchAccounts,
+
+    #[msg("Nullifier shard page is full; initialize the next page")]
+    NullifierShardFull,
+
+    #[msg("Rent must be refunded to the account that funded the nullifier")]
+    InvalidRentRecipient,
+
+    #[msg("Nullifier shard pages are missing; pass every page up to the last")]
+    NullifierPageMissing,
+
+    #[msg("The previous nullifier shard page isn't full yet")]
+    NullifierPageNotFull,
 }
@@ -0,0 +1,152 @@
+// Phantom Streams - Incremental Merkle tree
+//
+// Append-only Merkle tree over Poseidon (BN254, circom parameters), the
+// same hash the Noir circuit uses for leaves and internal nodes. Only the
+// rightmost filled subtree at each level is kept, so inserts cost
+// TREE_DEPTH hashes and O(TREE_DEPTH) storage.
+//
+// On-chain hashing goes through the `sol_poseidon` syscall; off-chain
+// builds (tests, clients) use light-poseidon, which the syscall wraps.
+
+use anchor_lang::prelude::*;
+
+use crate::PhantomError;
+
+/// Tree depth - must match TREE_DEPTH in circuits/src/main.nr
+pub const TREE_DEPTH: usize = 20;
+
+/// Number of leaves the tree can hold
+pub const MAX_LEAVES: u64 = 1 << TREE_DEPTH;
+
+/// Poseidon hash of two big-endian field elements
+pub fn hash_pair(left: &[u8; 32], right: &[u8; 32]) -> Result<[u8; 32]> {
+    poseidon(&[left.as_slice(), right.as_slice()])
+}
+
+#[cfg(target_os = "solana")]
+fn poseidon(inputs: &[&[u8]]) -> Result<[u8; 32]> {
+    // Parameters 0 = BN254 x^5 (circom), endianness 0 = big-endian
+    let mut result = [0u8; 32];
+    let status = unsafe {
+        solana_define_syscall::definitions::sol_poseidon(
+            0,
+            0,
+            inputs as *const _ as *const u8,
+            inputs.len() as u64,
+            result.as_mut_ptr(),
+        )
+    };
+    require!(status == 0, PhantomError::InvalidLeaf);
+    Ok(result)
+}
+
+#[cfg(not(target_os = "solana"))]
+fn poseidon(inputs: &[&[u8]]) -> Result<[u8; 32]> {
+    use light_poseidon::{Poseidon, PoseidonBytesHasher};
+
+    let mut hasher = Poseidon::<ark_bn254::Fr>::new_circom(inputs.len())
+        .map_err(|_| error!(PhantomError::InvalidLeaf))?;
+    hasher
+        .hash_bytes_be(inputs)
+        .map_err(|_| error!(PhantomError::InvalidLeaf))
+}
+
+/// Roots of empty subtrees: zeros[0] is the empty leaf,
+/// zeros[i + 1] = H(zeros[i], zeros[i])
+pub fn zero_hashes() -> Result<[[u8; 32]; TREE_DEPTH]> {
+    let mut zeros = [[0u8; 32]; TREE_DEPTH];
+    for i in 1..TREE_DEPTH {
+        zeros[i] = hash_pair(&zeros[i - 1], &zeros[i - 1])?;
+    }
+    Ok(zeros)
+}
+
+/// Insert `leaf` at `index`, updating the filled-subtree cache, and
+/// return the new root
+pub fn insert(
+    filled_subtrees: &mut [[u8; 32]; TREE_DEPTH],
+    zeros: &[[u8; 32]; TREE_DEPTH],
+    index: u64,
+    leaf: [u8; 32],
+) -> Result<[u8; 32]> {
+    require!(index < MAX_LEAVES, PhantomError::TreeFull);
+
+    let mut current = leaf;
+    let mut current_index = index;
+    for level in 0..TREE_DEPTH {
+        let (left, right) = if current_index & 1 == 0 {
+            filled_subtrees[level] = current;
+            (current, zeros[level])
+        } else {
+            (filled_subtrees[level], current)
+        };
+        current = hash_pair(&left, &right)?;
+        current_index /= 2;
+    }
+
+    Ok(current)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn leaf(n: u8) -> [u8; 32] {
+        let mut leaf = [0u8; 32];
+        leaf[31] = n;
+        leaf
+    }
+
+    /// Root of a tree whose first leaves are `leaves`, computed level by level
+    fn naive_root(leaves: &[[u8; 32]]) -> [u8; 32] {
+        let zeros = zero_hashes().unwrap();
+        let mut level_nodes = leaves.to_vec();
+        for zero in zeros.iter() {
+            if level_nodes.len() % 2 == 1 {
+                level_nodes.push(*zero);
+            }
+            level_nodes = level_nodes
+                .chunks(2)
+                .map(|pair| hash_pair(&pair[0], &pair[1]).unwrap())
+                .collect();
+        }
+        level_nodes[0]
+    }
+
+    #[test]
+    fn matches_circom_poseidon() {
+        // poseidon([0, 0]) from circomlibjs
+        let expected = [
+            0x20, 0x98, 0xf5, 0xfb, 0x9e, 0x23, 0x9e, 0xab, 0x3c, 0xea, 0xc3, 0xf2, 0x7b, 0x81,
+            0xe4, 0x81, 0xdc, 0x31, 0x24, 0xd5, 0x5f, 0xfe, 0xd5, 0x23, 0xa8, 0x39, 0xee, 0x84,
+            0x46, 0xb6, 0x48, 0x64,
+        ];
+        assert_eq!(hash_pair(&[0u8; 32], &[0u8; 32]).unwrap(), expected);
+    }
+
+    #[test]
+    fn incremental_root_matches_full_tree() {
+        let zeros = zero_hashes().unwrap();
+        let mut filled = [[0u8; 32]; TREE_DEPTH];
+        let leaves: Vec<[u8; 32]> = (1..=5).map(leaf).collect();
+
+        for (i, l) in leaves.iter().enumerate() {
+            let root = insert(&mut filled, &zeros, i as u64, *l).unwrap();
+            assert_eq!(root, naive_root(&leaves[..=i]));
+        }
+    }
+
+    #[test]
+    fn rejects_non_canonical_leaf() {
+        let zeros = zero_hashes().unwrap();
+        let mut filled = [[0u8; 32]; TREE_DEPTH];
+        assert!(insert(&mut filled, &zeros, 0, [0xff; 32]).is_err());
+    }
+
+    #[test]
+    fn rejects_insert_past_capacity() {
+        let zeros = zero_hashes().unwrap();
+        let mut filled = [[0u8; 32]; TREE_DEPTH];
+        assert!(insert(&mut filled, &zeros, MAX_LEAVES, leaf(1)).is_err());
+    }
+}
@@ -0,0 +1,345 @@
+// Phantom Streams - Account versioning
+//
+// `ProtocolState` and `NullifierAccount` were first deployed without a
+// version byte (v1). From v2 on, both carry a version byte and reserved
+// space, so later fields that fit can be carved out of the reserved bytes
+// without changing the account size again.
+//
+// v2 isn't v1 plus a suffix: the single Merkle root moved into registries
+// and nullifiers gained an epoch. Upgrading deserializes the v1 layout and
+// re-serializes it as the current one rather than patching bytes in place.
+// Fields v1 didn't have start out at their defaults: no pending authority,
+// a single epoch, nothing paused. v1 nullifier PDAs were seeded without a
+// registry; upgrading makes them readable but leaves them at that address.
+//
+// Nullifiers are on v3: recording the payer took 32 bytes, more than the
+// reserved space had left, so v3 is v2 with the payer appended and the
+// account grows again. v1 and v2 nullifiers both upgrade straight to v3,
+// with no payer recorded; `archive_nullifier` lets the authority pick who
+// gets their rent back.
+
+use anchor_lang::prelude::*;
+
+use crate::{NullifierAccount, PhantomError, ProtocolState};
+
+/// Current `ProtocolState` layout version
+pub const PROTOCOL_STATE_VERSION: u8 = 2;
+
+/// Current `NullifierAccount` layout version
+pub const NULLIFIER_VERSION: u8 = 3;
+
+/// `ProtocolState` as deployed before versioning
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct ProtocolStateV1 {
+    pub authority: Pubkey,
+    pub merkle_root: [u8; 32],
+    pub verification_count: u64,
+    pub bump: u8,
+}
+
+impl ProtocolStateV1 {
+    pub const SIZE: usize = 32 + 32 + 8 + 1;
+}
+
+/// `NullifierAccount` as deployed before versioning
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct NullifierAccountV1 {
+    pub is_used: bool,
+    pub track_id: [u8; 32],
+    pub used_at: i64,
+    pub bump: u8,
+}
+
+impl NullifierAccountV1 {
+    pub const SIZE: usize = 1 + 32 + 8 + 1;
+}
+
+/// `NullifierAccount` v2, before it recorded its payer
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct NullifierAccountV2 {
+    pub is_used: bool,
+    pub track_id: [u8; 32],
+    pub epoch: u64,
+    pub used_at: i64,
+    pub bump: u8,
+    pub version: u8,
+    pub verification_id: u64,
+    pub reserved: [u8; 8],
+}
+
+impl NullifierAccountV2 {
+    pub const SIZE: usize = 1 + 32 + 8 + 8 + 1 + 1 + 8 + 8;
+}
+
+/// Rewrite account data holding a v1 `ProtocolState` as the current
+/// layout. `data` must already be sized for it.
+///
+/// Returns the v1 Merkle root, which has no place in the new layout; the
+/// authority carries it over by creating a registry with it.
+pub fn upgrade_protocol_state(data: &mut [u8]) -> Result<[u8; 32]> {
+    let v1: ProtocolStateV1 = read_previous(data)?;
+    let state = ProtocolState {
+        authority: v1.authority,
+        pending_authority: None,
+        verification_count: v1.verification_count,
+        epoch_length: 0,
+        paused: 0,
+        pause_guardian: None,
+        bump: v1.bump,
+        version: PROTOCOL_STATE_VERSION,
+        reserved: [0u8; 64],
+    };
+    write(data, ProtocolState::SIZE, &state)?;
+    Ok(v1.merkle_root)
+}
+
+/// Layout version of `NullifierAccount` data `data_len` bytes long that
+/// `upgrade_nullifier` can upgrade
+pub fn nullifier_version(data_len: usize) -> Result<u8> {
+    match data_len.checked_sub(8) {
+        Some(NullifierAccountV1::SIZE) => Ok(1),
+        Some(NullifierAccountV2::SIZE) => Ok(2),
+        _ => err!(PhantomError::InvalidAccountVersion),
+    }
+}
+
+/// Rewrite account data holding a v1 or v2 `NullifierAccount` as the
+/// current layout. `data` must already be sized for it. v1 nullifiers
+/// predate epochs, so they're recorded as spent in epoch 0. Neither
+/// version recorded the payer, which is left at its default.
+pub fn upgrade_nullifier(data: &mut [u8], from_version: u8) -> Result<()> {
+    let nullifier = match from_version {
+        1 => {
+            let v1: NullifierAccountV1 = read_previous(data)?;
+            NullifierAccount {
+                is_used: v1.is_used,
+                track_id: v1.track_id,
+                epoch: 0,
+                used_at: v1.used_at,
+                bump: v1.bump,
+                version: NULLIFIER_VERSION,
+                verification_id: 0,
+                reserved: [0u8; 8],
+                payer: Pubkey::default(),
+            }
+        }
+        2 => {
+            let v2: NullifierAccountV2 = read_previous(data)?;
+            require!(v2.version == 2, PhantomError::InvalidAccountVersion);
+            NullifierAccount {
+                is_used: v2.is_used,
+                track_id: v2.track_id,
+                epoch: v2.epoch,
+                used_at: v2.used_at,
+                bump: v2.bump,
+                version: NULLIFIER_VERSION,
+                verification_id: v2.verification_id,
+                reserved: v2.reserved,
+                payer: Pubkey::default(),
+            }
+        }
+        _ => return err!(PhantomError::InvalidAccountVersion),
+    };
+    write(data, NullifierAccount::SIZE, &nullifier)
+}
+
+fn read_previous<T: AnchorDeserialize>(data: &[u8]) -> Result<T> {
+    let mut fields = data.get(8..).ok_or(PhantomError::InvalidAccountVersion)?;
+    T::deserialize(&mut fields).map_err(|_| error!(PhantomError::InvalidAccountVersion))
+}
+
+fn write<T: AccountSerialize>(data: &mut [u8], size: usize, account: &T) -> Result<()> {
+    require!(data.len() >= 8 + size, PhantomError::InvalidAccountVersion);
+
+    data.fill(0);
+    let mut writer: &mut [u8] = data;
+    account.try_serialize(&mut writer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An account exactly as an earlier program allocated it:
+    /// discriminator + that version's fields, with no spare bytes
+    fn old_account<T: AnchorSerialize>(discriminator: &[u8], fields: &T, len: usize) -> Vec<u8> {
+        let mut data = discriminator.to_vec();
+        fields.serialize(&mut data).unwrap();
+        assert_eq!(data.len(), 8 + len);
+        data
+    }
+
+    /// What `migrate_state` does: grow the account, then rewrite it
+    fn grow(mut data: Vec<u8>, new_len: usize) -> Vec<u8> {
+        data.resize(new_len, 0);
+        data
+    }
+
+    fn v1_nullifier() -> NullifierAccountV1 {
+        NullifierAccountV1 {
+            is_used: true,
+            track_id: [7u8; 32],
+            used_at: 1_700_000_000,
+            bump: 255,
+        }
+    }
+
+    fn v2_nullifier() -> NullifierAccountV2 {
+        NullifierAccountV2 {
+            is_used: true,
+            track_id: [7u8; 32],
+            epoch: 12,
+            used_at: 1_700_000_000,
+            bump: 255,
+            version: 2,
+            verification_id: 99,
+            reserved: [0u8; 8],
+        }
+    }
+
+    #[test]
+    fn v1_sizes_match_the_deployed_layouts() {
+        assert_eq!(ProtocolState::V1_SIZE, 73);
+        assert_eq!(NullifierAccount::V1_SIZE, 42);
+        assert_eq!(NullifierAccount::V2_SIZE, 67);
+    }
+
+    #[test]
+    fn nullifier_version_is_told_by_length() {
+        assert_eq!(nullifier_version(8 + NullifierAccount::V1_SIZE).unwrap(), 1);
+        assert_eq!(nullifier_version(8 + NullifierAccount::V2_SIZE).unwrap(), 2);
+        // Already current
+        assert!(nullifier_version(8 + NullifierAccount::SIZE).is_err());
+        assert!(nullifier_version(4).is_err());
+    }
+
+    #[test]
+    fn v1_protocol_state_deserializes_after_upgrade() {
+        let authority = Pubkey::new_unique();
+        let v1 = ProtocolStateV1 {
+            authority,
+            merkle_root: [9u8; 32],
+            verification_count: 42,
+            bump: 254,
+        };
+        let data = old_account(ProtocolState::DISCRIMINATOR, &v1, ProtocolState::V1_SIZE);
+        let mut data = grow(data, 8 + ProtocolState::SIZE);
+
+        let legacy_root = upgrade_protocol_state(&mut data).unwrap();
+        let state = ProtocolState::try_deserialize(&mut data.as_slice()).unwrap();
+
+        assert_eq!(legacy_root, [9u8; 32]);
+        assert_eq!(state.authority, authority);
+        assert_eq!(state.pending_authority, None);
+        assert_eq!(state.verification_count, 42);
+        assert_eq!(state.epoch_length, 0);
+        assert_eq!(state.paused, 0);
+        assert_eq!(state.pause_guardian, None);
+        assert_eq!(state.bump, 254);
+        assert_eq!(state.version, PROTOCOL_STATE_VERSION);
+        assert!(state.reserved.iter().all(|b| *b == 0));
+    }
+
+    #[test]
+    fn v1_nullifier_deserializes_after_upgrade() {
+        let data = old_account(
+            NullifierAccount::DISCRIMINATOR,
+            &v1_nullifier(),
+            NullifierAccount::V1_SIZE,
+        );
+        let mut data = grow(data, 8 + NullifierAccount::SIZE);
+
+        upgrade_nullifier(&mut data, 1).unwrap();
+        let nullifier = NullifierAccount::try_deserialize(&mut data.as_slice()).unwrap();
+
+        assert!(nullifier.is_used);
+        assert_eq!(nullifier.track_id, [7u8; 32]);
+        assert_eq!(nullifier.epoch, 0);
+        assert_eq!(nullifier.used_at, 1_700_000_000);
+        assert_eq!(nullifier.bump, 255);
+        assert_eq!(nullifier.version, NULLIFIER_VERSION);
+        assert_eq!(nullifier.payer, Pubkey::default());
+    }
+
+    #[test]
+    fn v2_nullifier_deserializes_after_upgrade() {
+        let data = old_account(
+            NullifierAccount::DISCRIMINATOR,
+            &v2_nullifier(),
+            NullifierAccount::V2_SIZE,
+        );
+        assert!(NullifierAccount::try_deserialize(&mut data.as_slice()).is_err());
+        let mut data = grow(data, 8 + NullifierAccount::SIZE);
+
+        upgrade_nullifier(&mut data, 2).unwrap();
+        let nullifier = NullifierAccount::try_deserialize(&mut data.as_slice()).unwrap();
+
+        assert!(nullifier.is_used);
+        assert_eq!(nullifier.track_id, [7u8; 32]);
+        assert_eq!(nullifier.epoch, 12);
+        assert_eq!(nullifier.used_at, 1_700_000_000);
+        assert_eq!(nullifier.bump, 255);
+        assert_eq!(nullifier.verification_id, 99);
+        assert_eq!(nullifier.version, NULLIFIER_VERSION);
+        assert_eq!(nullifier.payer, Pubkey::default());
+    }
+
+    #[test]
+    fn rejects_v2_upgrade_of_another_version() {
+        let mut v2 = v2_nullifier();
+        v2.version = 3;
+        let data = old_account(NullifierAccount::DISCRIMINATOR, &v2, NullifierAccount::V2_SIZE);
+        let mut data = grow(data, 8 + NullifierAccount::SIZE);
+        assert!(upgrade_nullifier(&mut data, 2).is_err());
+    }
+
+    #[test]
+    fn migrated_nullifier_rent_goes_to_the_authoritys_pick() {
+        let data = old_account(
+            NullifierAccount::DISCRIMINATOR,
+            &v1_nullifier(),
+            NullifierAccount::V1_SIZE,
+        );
+        let mut data = grow(data, 8 + NullifierAccount::SIZE);
+        upgrade_nullifier(&mut data, 1).unwrap();
+        let mut nullifier = NullifierAccount::try_deserialize(&mut data.as_slice()).unwrap();
+
+        // No payer recorded: any recipient
+        assert!(nullifier.check_rent_recipient(&Pubkey::new_unique()).is_ok());
+
+        // Recorded payer: only it
+        let payer = Pubkey::new_unique();
+        nullifier.payer = payer;
+        assert!(nullifier.check_rent_recipient(&payer).is_ok());
+        assert!(nullifier.check_rent_recipient(&Pubkey::new_unique()).is_err());
+    }
+
+    #[test]
+    fn v1_layout_does_not_deserialize_as_current() {
+        let data = old_account(
+            NullifierAccount::DISCRIMINATOR,
+            &v1_nullifier(),
+            NullifierAccount::V1_SIZE,
+        );
+        assert!(NullifierAccount::try_deserialize(&mut data.as_slice()).is_err());
+
+        let v1 = ProtocolStateV1 {
+            authority: Pubkey::new_unique(),
+            merkle_root: [0u8; 32],
+            verification_count: 0,
+            bump: 255,
+        };
+        let data = old_account(ProtocolState::DISCRIMINATOR, &v1, ProtocolState::V1_SIZE);
+        assert!(ProtocolState::try_deserialize(&mut data.as_slice()).is_err());
+    }
+
+    #[test]
+    fn rejects_upgrade_without_room() {
+        let mut data = old_account(
+            NullifierAccount::DISCRIMINATOR,
+            &v1_nullifier(),
+            NullifierAccount::V1_SIZE,
+        );
+        assert!(upgrade_nullifier(&mut data, 1).is_err());
+    }
+}
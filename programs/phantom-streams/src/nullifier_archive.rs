@@ -0,0 +1,293 @@
+// Phantom Streams - Nullifier archive
+//
+// Closing spent `NullifierAccount` PDAs reclaims their rent, but the replay
+// check still has to know those nullifiers were spent. The archive folds
+// them into an indexed Merkle tree: each leaf holds an archived nullifier
+// and the next larger one, so the leaves form a sorted linked list and a
+// single leaf with value < n < next_value proves n was never archived.
+//
+// Leaves are appended with the incremental tree in `merkle_tree`. Archiving
+// n also rewrites its low leaf (the leaf whose range n falls into), so the
+// caller supplies that leaf's Merkle path and the filled-subtree cache is
+// patched wherever the rewritten path crosses it.
+
+use anchor_lang::prelude::*;
+
+use crate::merkle_tree::{self, TREE_DEPTH};
+use crate::PhantomError;
+
+/// Leaf of the archive tree
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ArchiveLeaf {
+    /// Archived nullifier (zero for the sentinel leaf at index 0)
+    pub value: [u8; 32],
+    /// Next larger archived nullifier, or zero if this is the largest
+    pub next_value: [u8; 32],
+}
+
+impl ArchiveLeaf {
+    pub fn hash(&self) -> Result<[u8; 32]> {
+        merkle_tree::hash_pair(&self.value, &self.next_value)
+    }
+
+    /// Whether `nullifier` falls strictly inside the gap this leaf covers
+    pub fn covers(&self, nullifier: &[u8; 32]) -> bool {
+        self.value < *nullifier && (self.next_value == [0u8; 32] || *nullifier < self.next_value)
+    }
+}
+
+/// Low leaf of a nullifier (the leaf whose gap it falls into) with its
+/// Merkle path
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct ArchiveProof {
+    pub low_leaf: ArchiveLeaf,
+    pub low_index: u64,
+    pub siblings: [[u8; 32]; TREE_DEPTH],
+}
+
+impl ArchiveProof {
+    /// Serialized size: 712 bytes at depth 20, so a transaction has room
+    /// for only one
+    pub const SIZE: usize = 64 + 8 + 32 * TREE_DEPTH;
+}
+
+/// Nodes on the path from `leaf` at `index` to the root, leaf first, and
+/// the root itself
+fn path_nodes(
+    leaf: [u8; 32],
+    index: u64,
+    siblings: &[[u8; 32]; TREE_DEPTH],
+) -> Result<([[u8; 32]; TREE_DEPTH], [u8; 32])> {
+    require!(
+        index < merkle_tree::MAX_LEAVES,
+        PhantomError::InvalidArchiveProof
+    );
+
+    let mut nodes = [[0u8; 32]; TREE_DEPTH];
+    let mut current = leaf;
+    for (level, sibling) in siblings.iter().enumerate() {
+        nodes[level] = current;
+        current = if (index >> level) & 1 == 0 {
+            merkle_tree::hash_pair(&current, sibling)?
+        } else {
+            merkle_tree::hash_pair(sibling, &current)?
+        };
+    }
+    Ok((nodes, current))
+}
+
+/// Root of an empty archive: only the sentinel leaf, covering every
+/// nullifier. Returns the root and leaves the sentinel in `filled_subtrees`
+pub fn initialize(
+    filled_subtrees: &mut [[u8; 32]; TREE_DEPTH],
+    zeros: &[[u8; 32]; TREE_DEPTH],
+) -> Result<[u8; 32]> {
+    *filled_subtrees = *zeros;
+    merkle_tree::insert(filled_subtrees, zeros, 0, ArchiveLeaf::default().hash()?)
+}
+
+/// Check `nullifier` is not in the archive with the given root
+pub fn verify_non_membership(
+    root: &[u8; 32],
+    nullifier: &[u8; 32],
+    proof: &ArchiveProof,
+) -> Result<()> {
+    require!(
+        proof.low_leaf.covers(nullifier),
+        PhantomError::InvalidArchiveProof
+    );
+    let (_, computed_root) = path_nodes(proof.low_leaf.hash()?, proof.low_index, &proof.siblings)?;
+    require!(computed_root == *root, PhantomError::InvalidArchiveProof);
+    Ok(())
+}
+
+/// Archive `nullifier` as leaf `next_index` and return the new root
+pub fn insert(
+    filled_subtrees: &mut [[u8; 32]; TREE_DEPTH],
+    zeros: &[[u8; 32]; TREE_DEPTH],
+    root: &[u8; 32],
+    next_index: u64,
+    nullifier: &[u8; 32],
+    proof: &ArchiveProof,
+) -> Result<[u8; 32]> {
+    let low_index = proof.low_index;
+    require!(low_index < next_index, PhantomError::InvalidArchiveProof);
+    verify_non_membership(root, nullifier, proof)?;
+
+    // 1. Point the low leaf at the new nullifier
+    let updated = ArchiveLeaf {
+        value: proof.low_leaf.value,
+        next_value: *nullifier,
+    };
+    let (nodes, _) = path_nodes(updated.hash()?, low_index, &proof.siblings)?;
+
+    // 2. The cache holds the left node at each level of the last insert's
+    // path; refresh any the rewritten path runs through
+    let last_index = next_index - 1;
+    for (level, node) in nodes.iter().enumerate() {
+        if low_index >> level == (last_index >> level) & !1 {
+            filled_subtrees[level] = *node;
+        }
+    }
+
+    // 3. Append the nullifier, taking over the low leaf's old successor
+    let leaf = ArchiveLeaf {
+        value: *nullifier,
+        next_value: proof.low_leaf.next_value,
+    };
+    merkle_tree::insert(filled_subtrees, zeros, next_index, leaf.hash()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn nullifier(n: u8) -> [u8; 32] {
+        let mut nullifier = [0u8; 32];
+        nullifier[31] = n;
+        nullifier
+    }
+
+    /// Archive maintained naively, with every leaf kept in memory
+    struct NaiveArchive {
+        leaves: Vec<ArchiveLeaf>,
+    }
+
+    impl NaiveArchive {
+        fn new() -> Self {
+            Self {
+                leaves: vec![ArchiveLeaf::default()],
+            }
+        }
+
+        fn low_index(&self, n: &[u8; 32]) -> usize {
+            self.leaves.iter().position(|leaf| leaf.covers(n)).unwrap()
+        }
+
+        /// Root and sibling path of `index`, computed level by level
+        fn path(&self, index: usize) -> ([u8; 32], [[u8; 32]; TREE_DEPTH]) {
+            let zeros = merkle_tree::zero_hashes().unwrap();
+            let mut siblings = [[0u8; 32]; TREE_DEPTH];
+            let mut level_nodes: Vec<[u8; 32]> = self
+                .leaves
+                .iter()
+                .map(|leaf| leaf.hash().unwrap())
+                .collect();
+            let mut position = index;
+            for (level, zero) in zeros.iter().enumerate() {
+                if level_nodes.len() % 2 == 1 {
+                    level_nodes.push(*zero);
+                }
+                siblings[level] = level_nodes[position ^ 1];
+                level_nodes = level_nodes
+                    .chunks(2)
+                    .map(|pair| merkle_tree::hash_pair(&pair[0], &pair[1]).unwrap())
+                    .collect();
+                position /= 2;
+            }
+            (level_nodes[0], siblings)
+        }
+
+        /// Root and non-membership proof of `n`
+        fn proof(&self, n: &[u8; 32]) -> ([u8; 32], ArchiveProof) {
+            let low_index = self.low_index(n);
+            let (root, siblings) = self.path(low_index);
+            let proof = ArchiveProof {
+                low_leaf: self.leaves[low_index],
+                low_index: low_index as u64,
+                siblings,
+            };
+            (root, proof)
+        }
+
+        fn insert(&mut self, n: &[u8; 32]) {
+            let low = self.low_index(n);
+            let next_value = self.leaves[low].next_value;
+            self.leaves[low].next_value = *n;
+            self.leaves.push(ArchiveLeaf {
+                value: *n,
+                next_value,
+            });
+        }
+    }
+
+    #[test]
+    fn one_proof_per_transaction() {
+        // `archive_nullifier` takes one nullifier because a second
+        // nullifier and proof wouldn't fit in a transaction's 1232 bytes
+        const PACKET_DATA_SIZE: usize = 1232;
+        let (_, proof) = NaiveArchive::new().proof(&nullifier(1));
+        let proof_len = proof.try_to_vec().unwrap().len();
+        assert_eq!(proof_len, ArchiveProof::SIZE);
+        assert!(2 * (32 + proof_len) > PACKET_DATA_SIZE);
+    }
+
+    #[test]
+    fn incremental_root_matches_full_tree() {
+        let zeros = merkle_tree::zero_hashes().unwrap();
+        let mut filled = [[0u8; 32]; TREE_DEPTH];
+        let mut root = initialize(&mut filled, &zeros).unwrap();
+        let mut naive = NaiveArchive::new();
+        assert_eq!(root, naive.path(0).0);
+
+        for n in [50u8, 20, 80, 10, 30, 90, 60] {
+            let n = nullifier(n);
+            let (_, proof) = naive.proof(&n);
+            let next_index = naive.leaves.len() as u64;
+
+            root = insert(&mut filled, &zeros, &root, next_index, &n, &proof).unwrap();
+            naive.insert(&n);
+            assert_eq!(root, naive.path(0).0);
+        }
+    }
+
+    #[test]
+    fn proves_non_membership() {
+        let mut naive = NaiveArchive::new();
+        for n in [20u8, 40] {
+            naive.insert(&nullifier(n));
+        }
+
+        let absent = nullifier(30);
+        let (root, proof) = naive.proof(&absent);
+        verify_non_membership(&root, &absent, &proof).unwrap();
+    }
+
+    #[test]
+    fn rejects_non_membership_of_archived_nullifier() {
+        let mut naive = NaiveArchive::new();
+        for n in [20u8, 40] {
+            naive.insert(&nullifier(n));
+        }
+
+        // No leaf's gap contains an archived value, so try each neighbour
+        let archived = nullifier(40);
+        for index in 0..naive.leaves.len() {
+            let (root, siblings) = naive.path(index);
+            let proof = ArchiveProof {
+                low_leaf: naive.leaves[index],
+                low_index: index as u64,
+                siblings,
+            };
+            assert!(verify_non_membership(&root, &archived, &proof).is_err());
+        }
+    }
+
+    #[test]
+    fn rejects_forged_low_leaf() {
+        let mut naive = NaiveArchive::new();
+        naive.insert(&nullifier(40));
+        let (root, siblings) = naive.path(1);
+
+        // Claims a gap around 40, hiding it from the check
+        let proof = ArchiveProof {
+            low_leaf: ArchiveLeaf {
+                value: nullifier(20),
+                next_value: nullifier(60),
+            },
+            low_index: 1,
+            siblings,
+        };
+        assert!(verify_non_membership(&root, &nullifier(40), &proof).is_err());
+    }
+}
@@ -0,0 +1,274 @@
+// Phantom Streams - Sharded nullifier set
+//
+// Rent-efficient alternative to one `NullifierAccount` PDA per nullifier.
+// Nullifiers are partitioned into shards by their low byte; each shard
+// keeps its nullifiers as a sorted array of 32-byte hashes stored after the
+// account header, so a spent nullifier costs 32 bytes of rent instead of a
+// whole account. Nullifiers are big-endian field elements below r, whose
+// top byte is at most 0x30, so only the low byte spreads them over all 256
+// shards.
+//
+// Lookups binary-search the raw account bytes, so the entries never have to
+// be deserialized. Inserts shift every later entry up one slot, so a shard
+// page is capped at `MAX_ENTRIES` to bound that copy.
+//
+// A prefix's nullifiers are spread over pages, PDAs of the prefix and a page
+// index. Only the last page takes inserts; once it's full, anyone can
+// allocate the next one, so a prefix has no ceiling. Every page before the
+// last is full, which is how a lookup knows it has seen them all: it walks
+// the pages in order and stops at the first one that isn't full or doesn't
+// exist yet.
+
+use anchor_lang::prelude::*;
+
+use crate::{NullifierShard, PhantomError};
+
+/// Bytes per stored nullifier
+pub const ENTRY_LEN: usize = 32;
+
+/// Most nullifiers a single shard page holds (128 KiB of entries)
+pub const MAX_ENTRIES: usize = 4096;
+
+/// Shard a nullifier belongs to
+pub fn shard_prefix(nullifier: &[u8; 32]) -> u8 {
+    nullifier[31]
+}
+
+/// Address of page `page` of a registry's shard for `prefix`
+pub fn page_address(registry: &Pubkey, prefix: u8, page: u16) -> Pubkey {
+    Pubkey::find_program_address(
+        &[b"nullifier_shard", registry.as_ref(), &[prefix], &page.to_le_bytes()],
+        &crate::ID,
+    )
+    .0
+}
+
+/// Whether `nullifier` is stored in its prefix's shard, given `pages`: the
+/// prefix's pages in order from page 0, through the first one that isn't
+/// full or doesn't exist yet. Leaving a page out, or passing any past that
+/// one, is rejected
+pub fn contains_in_pages(
+    registry: &Pubkey,
+    nullifier: &[u8; 32],
+    pages: &[AccountInfo],
+) -> Result<bool> {
+    let prefix = shard_prefix(nullifier);
+    for (index, info) in pages.iter().enumerate() {
+        let page = u16::try_from(index).map_err(|_| PhantomError::InvalidNullifierStore)?;
+        require_keys_eq!(
+            info.key(),
+            page_address(registry, prefix, page),
+            PhantomError::InvalidNullifierStore
+        );
+        let last = index + 1 == pages.len();
+
+        let Some(shard) = crate::load_optional::<NullifierShard>(info)? else {
+            require!(last, PhantomError::InvalidNullifierStore);
+            return Ok(false);
+        };
+        let count = shard.count as usize;
+        let data = info.try_borrow_data()?;
+        if contains(&data[NullifierShard::ENTRIES_OFFSET..], count, nullifier) {
+            return Ok(true);
+        }
+        if count < MAX_ENTRIES {
+            require!(last, PhantomError::InvalidNullifierStore);
+            return Ok(false);
+        }
+    }
+    err!(PhantomError::NullifierPageMissing)
+}
+
+/// Position of `nullifier` among the first `count` entries, or the position
+/// it would be inserted at to keep them sorted
+fn search(entries: &[u8], count: usize, nullifier: &[u8; 32]) -> std::result::Result<usize, usize> {
+    let (mut low, mut high) = (0, count);
+    while low < high {
+        let mid = low + (high - low) / 2;
+        let entry = &entries[mid * ENTRY_LEN..(mid + 1) * ENTRY_LEN];
+        match entry.cmp(nullifier.as_slice()) {
+            std::cmp::Ordering::Less => low = mid + 1,
+            std::cmp::Ordering::Greater => high = mid,
+            std::cmp::Ordering::Equal => return Ok(mid),
+        }
+    }
+    Err(low)
+}
+
+/// Whether `nullifier` is among the first `count` entries
+pub fn contains(entries: &[u8], count: usize, nullifier: &[u8; 32]) -> bool {
+    search(entries, count, nullifier).is_ok()
+}
+
+/// Insert `nullifier` into the first `count` sorted entries.
+///
+/// `entries` must have room for `count + 1` entries.
+pub fn insert(entries: &mut [u8], count: usize, nullifier: &[u8; 32]) -> Result<()> {
+    require!(count < MAX_ENTRIES, PhantomError::NullifierShardFull);
+    require!(
+        entries.len() >= (count + 1) * ENTRY_LEN,
+        PhantomError::InvalidNullifierStore
+    );
+
+    let index = match search(entries, count, nullifier) {
+        Ok(_) => return err!(PhantomError::NullifierAlreadyUsed),
+        Err(index) => index,
+    };
+
+    // Shift the tail up one slot and write the new entry in the gap
+    let start = index * ENTRY_LEN;
+    entries.copy_within(start..count * ENTRY_LEN, start + ENTRY_LEN);
+    entries[start..start + ENTRY_LEN].copy_from_slice(nullifier);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn nullifier(n: u8) -> [u8; 32] {
+        let mut nullifier = [0u8; 32];
+        nullifier[31] = n;
+        nullifier
+    }
+
+    #[test]
+    fn keeps_entries_sorted() {
+        let mut entries = vec![0u8; 4 * ENTRY_LEN];
+        for (count, n) in [3u8, 1, 4, 2].iter().enumerate() {
+            insert(&mut entries, count, &nullifier(*n)).unwrap();
+        }
+
+        for (i, chunk) in entries.chunks(ENTRY_LEN).enumerate() {
+            assert_eq!(chunk, nullifier(i as u8 + 1).as_slice());
+        }
+        assert!(contains(&entries, 4, &nullifier(2)));
+        assert!(!contains(&entries, 4, &nullifier(5)));
+    }
+
+    #[test]
+    fn rejects_duplicate_nullifier() {
+        let mut entries = vec![0u8; 2 * ENTRY_LEN];
+        insert(&mut entries, 0, &nullifier(7)).unwrap();
+        assert!(insert(&mut entries, 1, &nullifier(7)).is_err());
+    }
+
+    #[test]
+    fn ignores_space_past_count() {
+        // Zeroed spare capacity must not read as a stored all-zero nullifier
+        let entries = vec![0u8; 2 * ENTRY_LEN];
+        assert!(!contains(&entries, 0, &[0u8; 32]));
+    }
+
+    #[test]
+    fn rejects_insert_into_full_shard() {
+        let mut entries = vec![0u8; (MAX_ENTRIES + 1) * ENTRY_LEN];
+        let err = insert(&mut entries, MAX_ENTRIES, &nullifier(1)).unwrap_err();
+        assert_eq!(err, PhantomError::NullifierShardFull.into());
+    }
+
+    #[test]
+    fn shards_on_low_byte() {
+        // Canonical field elements all start at or below 0x30
+        let mut nullifier = [0x30u8; 32];
+        nullifier[31] = 0xff;
+        assert_eq!(shard_prefix(&nullifier), 0xff);
+    }
+
+    #[test]
+    fn rejects_insert_without_room() {
+        let mut entries = vec![0u8; ENTRY_LEN];
+        insert(&mut entries, 0, &nullifier(1)).unwrap();
+        assert!(insert(&mut entries, 1, &nullifier(2)).is_err());
+    }
+
+    /// Nullifier `n` of prefix 1
+    fn in_prefix(n: u8) -> [u8; 32] {
+        let mut nullifier = [0u8; 32];
+        nullifier[0] = n;
+        nullifier[31] = 1;
+        nullifier
+    }
+
+    /// A page of `registry`'s shard for prefix 1 holding `stored`, filled
+    /// up to `count` entries with nullifiers that sort before them
+    fn page(registry: &Pubkey, page: u16, count: usize, stored: &[u8]) -> (Pubkey, Vec<u8>) {
+        let header = NullifierShard {
+            registry: *registry,
+            prefix: 1,
+            page,
+            count: count as u32,
+            bump: 0,
+        };
+        let mut data = Vec::with_capacity(NullifierShard::space(count));
+        header.try_serialize(&mut data).unwrap();
+
+        let mut entries: Vec<[u8; 32]> = stored.iter().map(|n| in_prefix(*n)).collect();
+        for filler in 0..count - stored.len() {
+            let mut nullifier = in_prefix(0);
+            nullifier[1..5].copy_from_slice(&(filler as u32).to_be_bytes());
+            entries.push(nullifier);
+        }
+        entries.sort();
+        data.extend(entries.iter().flatten());
+        (page_address(registry, 1, page), data)
+    }
+
+    /// Look `n` up across `pages`, each an address and its data (empty for
+    /// a page that doesn't exist)
+    fn lookup(registry: &Pubkey, pages: &mut [(Pubkey, Vec<u8>)], n: u8) -> Result<bool> {
+        let owner = crate::ID;
+        let mut lamports = vec![1u64; pages.len()];
+        let infos: Vec<AccountInfo> = pages
+            .iter_mut()
+            .zip(lamports.iter_mut())
+            .map(|((key, data), lamports)| {
+                AccountInfo::new(key, false, false, lamports, data, &owner, false, 0)
+            })
+            .collect();
+        contains_in_pages(registry, &in_prefix(n), &infos)
+    }
+
+    #[test]
+    fn finds_nullifier_in_any_page() {
+        let registry = Pubkey::new_unique();
+        let mut pages = [
+            page(&registry, 0, MAX_ENTRIES, &[1]),
+            page(&registry, 1, 1, &[2]),
+        ];
+        assert!(lookup(&registry, &mut pages, 1).unwrap());
+        assert!(lookup(&registry, &mut pages, 2).unwrap());
+        assert!(!lookup(&registry, &mut pages, 3).unwrap());
+    }
+
+    #[test]
+    fn requires_every_page_up_to_the_last() {
+        let registry = Pubkey::new_unique();
+        let full = || page(&registry, 0, MAX_ENTRIES, &[]);
+
+        // Page 0 is full, so page 1 has to be looked at too
+        let mut pages = [full()];
+        assert_eq!(
+            lookup(&registry, &mut pages, 2).unwrap_err(),
+            PhantomError::NullifierPageMissing.into()
+        );
+
+        // A page 1 that doesn't exist yet ends the walk
+        let mut pages = [full(), (page_address(&registry, 1, 1), vec![])];
+        assert!(!lookup(&registry, &mut pages, 2).unwrap());
+
+        // Pages out of order
+        let mut pages = [page(&registry, 1, 1, &[2]), full()];
+        assert_eq!(
+            lookup(&registry, &mut pages, 2).unwrap_err(),
+            PhantomError::InvalidNullifierStore.into()
+        );
+
+        // Pages past the last one
+        let mut pages = [page(&registry, 0, 1, &[3]), page(&registry, 1, 1, &[2])];
+        assert_eq!(
+            lookup(&registry, &mut pages, 2).unwrap_err(),
+            PhantomError::InvalidNullifierStore.into()
+        );
+    }
+}
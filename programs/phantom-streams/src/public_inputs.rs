@@ -0,0 +1,134 @@
+// Phantom Streams - Public input encoding
+//
+// Groth16 public inputs are BN254 scalar field elements, but instruction
+// arguments arrive as raw 32-byte big-endian values. 32 bytes can hold
+// numbers up to about five times the field modulus r, so x and x + r are
+// different arguments (and nullifier PDA seeds) for the same field element
+// and would verify against the same proof.
+//
+// groth16::verify rejects unreduced inputs; encoding checks them up front,
+// before the raw bytes are used as nullifier seeds or in the replay check.
+
+use anchor_lang::prelude::*;
+
+use crate::groth16::SCALAR_FIELD_MODULUS;
+use crate::PhantomError;
+
+/// Number of public inputs of the ownership circuit
+pub const OWNERSHIP_INPUTS: usize = 10;
+
+/// `bytes` as a field element, rejecting values that aren't reduced mod r
+pub fn field_element(bytes: &[u8; 32]) -> Result<[u8; 32]> {
+    require!(
+        bytes.as_slice() < SCALAR_FIELD_MODULUS.as_slice(),
+        PhantomError::NonCanonicalFieldElement
+    );
+    Ok(*bytes)
+}
+
+/// Big-endian field element encoding of a u64 public input
+pub fn u64_field(value: u64) -> [u8; 32] {
+    let mut field = [0u8; 32];
+    field[24..].copy_from_slice(&value.to_be_bytes());
+    field
+}
+
+/// Pubkey as two big-endian field elements (high and low 16 bytes), since
+/// 32 arbitrary bytes can exceed the BN254 scalar field
+pub fn pubkey_fields(key: &Pubkey) -> [[u8; 32]; 2] {
+    let bytes = key.to_bytes();
+    let mut fields = [[0u8; 32]; 2];
+    fields[0][16..].copy_from_slice(&bytes[..16]);
+    fields[1][16..].copy_from_slice(&bytes[16..]);
+    fields
+}
+
+/// Public inputs of the ownership circuit
+pub struct OwnershipInputs {
+    pub merkle_root: [u8; 32],
+    pub track_id: [u8; 32],
+    pub nullifier_hash: [u8; 32],
+    pub epoch: u64,
+    pub relayer: Pubkey,
+    pub relayer_fee: u64,
+    pub session_key: Pubkey,
+    pub rights_tier: u8,
+}
+
+impl OwnershipInputs {
+    /// Encode in the order of `main` in circuits/src/main.nr
+    pub fn encode(&self) -> Result<[[u8; 32]; OWNERSHIP_INPUTS]> {
+        let [relayer_hi, relayer_lo] = pubkey_fields(&self.relayer);
+        let [session_hi, session_lo] = pubkey_fields(&self.session_key);
+        Ok([
+            field_element(&self.merkle_root)?,
+            field_element(&self.track_id)?,
+            field_element(&self.nullifier_hash)?,
+            u64_field(self.epoch),
+            relayer_hi,
+            relayer_lo,
+            u64_field(self.relayer_fee),
+            session_hi,
+            session_lo,
+            u64_field(self.rights_tier as u64),
+        ])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn modulus_minus(n: u8) -> [u8; 32] {
+        let mut value = SCALAR_FIELD_MODULUS;
+        value[31] -= n;
+        value
+    }
+
+    fn inputs() -> OwnershipInputs {
+        OwnershipInputs {
+            merkle_root: [1u8; 32],
+            track_id: [2u8; 32],
+            nullifier_hash: [3u8; 32],
+            epoch: 4,
+            relayer: Pubkey::new_from_array([0xff; 32]),
+            relayer_fee: 5,
+            session_key: Pubkey::default(),
+            rights_tier: 6,
+        }
+    }
+
+    #[test]
+    fn accepts_largest_field_element() {
+        assert_eq!(field_element(&modulus_minus(1)).unwrap(), modulus_minus(1));
+        assert!(field_element(&[0u8; 32]).is_ok());
+    }
+
+    #[test]
+    fn rejects_unreduced_values() {
+        assert!(field_element(&SCALAR_FIELD_MODULUS).is_err());
+        assert!(field_element(&[0xff; 32]).is_err());
+    }
+
+    #[test]
+    fn encodes_in_circuit_order() {
+        let encoded = inputs().encode().unwrap();
+        assert_eq!(encoded[0], [1u8; 32]);
+        assert_eq!(encoded[2], [3u8; 32]);
+        assert_eq!(encoded[3], u64_field(4));
+        assert_eq!(encoded[6], u64_field(5));
+        assert_eq!(encoded[9], u64_field(6));
+        // Pubkey halves always fit, even for an all-ones key
+        assert!(encoded[4..6].iter().all(|f| field_element(f).is_ok()));
+    }
+
+    #[test]
+    fn rejects_aliased_nullifier() {
+        // 3 + r is the same field element as 3 under a different encoding
+        let mut aliased = SCALAR_FIELD_MODULUS;
+        aliased[31] += 3;
+        let mut ownership = inputs();
+        ownership.nullifier_hash = aliased;
+        assert!(ownership.encode().is_err());
+    }
+}
@@ -0,0 +1,312 @@
+/**
+ * Phantom Streams - Arcium Program Callback Tests
+ *
+ * Runs against the Arcium program (programs/phantom-streams-arcium) on a
+ * cluster with its MXE deployed: `arcium test`. Under `anchor test` the IDL
+ * is the main program's, and the suite is skipped.
+ *
+ * Each test takes a callback Arcium really delivered, then sends it again
+ * itself: as-is, with its output tampered with, or aimed at another
+ * computation. Only the genuine first delivery may be applied.
+ */
+
+import * as anchor from "@coral-xyz/anchor";
+import { Program, AnchorProvider, Wallet, BN } from "@coral-xyz/anchor";
+import {
+  PublicKey,
+  Keypair,
+  SystemProgram,
+  Connection,
+  Transaction,
+  TransactionInstruction,
+  AccountMeta,
+} from "@solana/web3.js";
+import {
+  awaitComputationFinalization,
+  getArciumEnv,
+  getArciumProgAddress,
+  getClusterAccAddress,
+  getCompDefAccAddress,
+  getCompDefAccOffset,
+  getComputationAccAddress,
+  getMempoolAccAddress,
+  getMXEAccAddress,
+} from "@arcium-hq/client";
+import { expect } from "chai";
+import { randomBytes } from "crypto";
+import * as fs from "fs";
+import * as path from "path";
+import * as os from "os";
+
+// Program ID from programs/phantom-streams-arcium/src/lib.rs
+const PROGRAM_ID = new PublicKey("PhntmStr3amsMPCxxxxxxxxxxxxxxxxxxxxxxxxxx");
+
+// Load IDL directly; both programs build to target/idl/phantom_streams.json
+const idlPath = path.join(__dirname, "..", "target", "idl", "phantom_streams.json");
+const idl = JSON.parse(fs.readFileSync(idlPath, "utf8"));
+const isArciumProgram = idl.address === PROGRAM_ID.toBase58();
+
+// RightsOwnership: wallet, track and token ids, 20 path nodes (4 scalars
+// each) and 20 path indices, one 32-byte ciphertext per scalar
+const OWNERSHIP_CIPHERTEXTS = 4 + 4 + 4 + 20 * 4 + 20;
+
+// Load wallet keypair directly (bypasses environment variable issues)
+const homeDir = os.homedir();
+const walletPath = path.join(homeDir, ".config", "solana", "id.json");
+const walletKeypair = Keypair.fromSecretKey(
+  Uint8Array.from(JSON.parse(fs.readFileSync(walletPath, "utf8")))
+);
+
+// Error name from a failed transaction, whether Anchor parsed it or not
+const expectError = (err: any, name: string) => {
+  const logs = (err.logs ?? []).join("\n");
+  expect(`${err.message}\n${logs}`).to.include(name);
+};
+
+describe("Phantom Streams Arcium - Callback Authentication", () => {
+  const connection = new Connection("http://127.0.0.1:8899", "confirmed");
+  const wallet = new Wallet(walletKeypair);
+  const provider = new AnchorProvider(connection, wallet, { commitment: "confirmed" });
+  anchor.setProvider(provider);
+
+  const program = isArciumProgram ? new Program(idl, provider) : null;
+
+  const [statePda] = PublicKey.findProgramAddressSync(
+    [Buffer.from("state")],
+    PROGRAM_ID
+  );
+  const compDefOffset = Buffer.from(getCompDefAccOffset("verify_ownership")).readUInt32LE();
+
+  const pendingFor = (computationAccount: PublicKey) =>
+    PublicKey.findProgramAddressSync(
+      [Buffer.from("pending_computation"), computationAccount.toBuffer()],
+      PROGRAM_ID
+    )[0];
+
+  // A verification Arcium ran and called back for
+  interface Delivered {
+    computationAccount: PublicKey;
+    pendingComputation: PublicKey;
+    keys: AccountMeta[];
+    data: Buffer;
+  }
+
+  // Queue a verification of random ciphertexts (the MPC instruction
+  // rejects it, but still signs and delivers the result) and capture the
+  // callback instruction Arcium sent
+  const deliverVerification = async (): Promise<Delivered> => {
+    const offset = new BN(randomBytes(8), "hex");
+    const computationAccount = getComputationAccAddress(PROGRAM_ID, offset);
+    const pendingComputation = pendingFor(computationAccount);
+
+    await program!.methods
+      .verifyOwnership(
+        offset,
+        randomBytes(32 * OWNERSHIP_CIPHERTEXTS),
+        Array.from(randomBytes(16))
+      )
+      .accountsPartial({
+        state: statePda,
+        payer: walletKeypair.publicKey,
+        mempool: getMempoolAccAddress(PROGRAM_ID),
+        cluster: getClusterAccAddress(getArciumEnv().arciumClusterOffset),
+        compDef: getCompDefAccAddress(PROGRAM_ID, compDefOffset),
+        arciumProgram: getArciumProgAddress(),
+        mxeAccount: getMXEAccAddress(PROGRAM_ID),
+        computationAccount,
+        pendingComputation,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([walletKeypair])
+      .rpc();
+
+    const signature = await awaitComputationFinalization(
+      provider,
+      offset,
+      PROGRAM_ID,
+      "confirmed"
+    );
+    const tx = await connection.getTransaction(signature, {
+      commitment: "confirmed",
+      maxSupportedTransactionVersion: 0,
+    });
+    const message = tx!.transaction.message;
+    const accountKeys = message.getAccountKeys({
+      accountsFromLookups: tx!.meta!.loadedAddresses,
+    });
+
+    // The callback is a top-level or inner instruction of our program
+    const candidates = [
+      ...message.compiledInstructions.map((ix) => ({
+        programIdIndex: ix.programIdIndex,
+        accounts: ix.accountKeyIndexes,
+        data: Buffer.from(ix.data),
+      })),
+      ...(tx!.meta!.innerInstructions ?? []).flatMap((inner) =>
+        inner.instructions.map((ix) => ({
+          programIdIndex: ix.programIdIndex,
+          accounts: ix.accounts,
+          data: Buffer.from(anchor.utils.bytes.bs58.decode(ix.data)),
+        }))
+      ),
+    ];
+    const callback = candidates.find((ix) =>
+      accountKeys.get(ix.programIdIndex)!.equals(PROGRAM_ID)
+    );
+    expect(callback, "callback instruction in finalization").to.exist;
+
+    const keys = callback!.accounts.map((index) => ({
+      pubkey: accountKeys.get(index)!,
+      isSigner: false,
+      isWritable: message.isAccountWritable(index),
+    }));
+    return { computationAccount, pendingComputation, keys, data: callback!.data };
+  };
+
+  // Index of a callback account, from the IDL
+  const callbackAccounts = isArciumProgram
+    ? idl.instructions.find((ix: any) => ix.name === "verify_ownership_callback").accounts
+    : [];
+  const accountIndex = (name: string) =>
+    callbackAccounts.findIndex((account: any) => account.name === name);
+
+  // Send a callback ourselves, paying as the test wallet
+  const sendCallback = async (keys: AccountMeta[], data: Buffer) => {
+    const payer = accountIndex("payer");
+    const ownKeys = keys.map((key, index) =>
+      index === payer
+        ? { pubkey: walletKeypair.publicKey, isSigner: true, isWritable: true }
+        : key
+    );
+    const ix = new TransactionInstruction({ programId: PROGRAM_ID, keys: ownKeys, data });
+    return provider.sendAndConfirm(new Transaction().add(ix), [walletKeypair]);
+  };
+
+  // Swap the computation account and its record into a callback's accounts
+  const retarget = (keys: AccountMeta[], computationAccount: PublicKey, pending: PublicKey) => {
+    const swapped = keys.slice();
+    swapped[accountIndex("computation_account")] = {
+      pubkey: computationAccount,
+      isSigner: false,
+      isWritable: false,
+    };
+    swapped[accountIndex("pending_computation")] = {
+      pubkey: pending,
+      isSigner: false,
+      isWritable: true,
+    };
+    return swapped;
+  };
+
+  let first: Delivered;
+  let second: Delivered;
+
+  before(async function () {
+    if (!isArciumProgram) this.skip();
+
+    try {
+      await program!.methods
+        .initialize()
+        .accounts({
+          state: statePda,
+          authority: walletKeypair.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([walletKeypair])
+        .rpc();
+    } catch (err: any) {
+      if (!err.message?.includes("already in use")) throw err;
+    }
+
+    first = await deliverVerification();
+    second = await deliverVerification();
+    console.log(`\n  Delivered: ${first.computationAccount.toBase58()}`);
+    console.log(`  Delivered: ${second.computationAccount.toBase58()}`);
+  });
+
+  describe("1. Delivered Callbacks", () => {
+    it("Resolves each computation once", async function () {
+      if (!isArciumProgram) this.skip();
+
+      for (const delivered of [first, second]) {
+        const pending = await program!.account.pendingComputation.fetch(
+          delivered.pendingComputation
+        );
+        expect(Object.keys(pending.computation.status)[0]).to.equal("failed");
+        expect(pending.computation.fee.toNumber()).to.equal(0);
+      }
+
+      console.log("    ✅ Arcium's callbacks applied and resolved their records");
+    });
+  });
+
+  describe("2. Forged Callbacks", () => {
+    it("Rejects an output that isn't signed by the cluster", async function () {
+      if (!isArciumProgram) this.skip();
+
+      // Flip the last byte, in the signed output
+      const forged = Buffer.from(first.data);
+      forged[forged.length - 1] ^= 0x01;
+
+      try {
+        await sendCallback(first.keys, forged);
+        expect.fail("Should have rejected a tampered output");
+      } catch (err: any) {
+        expectError(err, "InvalidComputationOutput");
+        console.log("    ✅ Tampered output rejected");
+      }
+    });
+
+    it("Rejects an output signed for another computation", async function () {
+      if (!isArciumProgram) this.skip();
+
+      // The first result, delivered to the second computation
+      const keys = retarget(first.keys, second.computationAccount, second.pendingComputation);
+
+      try {
+        await sendCallback(keys, first.data);
+        expect.fail("Should have rejected another computation's output");
+      } catch (err: any) {
+        expectError(err, "InvalidComputationOutput");
+        console.log("    ✅ Output signed for another computation rejected");
+      }
+    });
+
+    it("Rejects a computation account that isn't the record's", async function () {
+      if (!isArciumProgram) this.skip();
+
+      // The first record, with the second computation's account
+      const keys = retarget(first.keys, second.computationAccount, first.pendingComputation);
+
+      try {
+        await sendCallback(keys, first.data);
+        expect.fail("Should have rejected a mismatched computation account");
+      } catch (err: any) {
+        // The record's seeds or the derived address fail, whichever is checked first
+        expect(`${err.message}\n${(err.logs ?? []).join("\n")}`).to.match(
+          /ConstraintAddress|ConstraintSeeds/
+        );
+        console.log("    ✅ Mismatched computation account rejected");
+      }
+    });
+  });
+
+  describe("3. Replayed Callbacks", () => {
+    it("Rejects a genuine callback delivered again", async function () {
+      if (!isArciumProgram) this.skip();
+
+      try {
+        await sendCallback(first.keys, first.data);
+        expect.fail("Should have rejected a replayed callback");
+      } catch (err: any) {
+        expectError(err, "ComputationNotQueued");
+        console.log("    ✅ Replayed callback rejected");
+      }
+
+      const pending = await program!.account.pendingComputation.fetch(
+        first.pendingComputation
+      );
+      expect(Object.keys(pending.computation.status)[0]).to.equal("failed");
+    });
+  });
+});
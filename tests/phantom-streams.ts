@@ -28,6 +28,61 @@ const PROGRAM_ID = new PublicKey("2dtcKpRkN7UHADJoWeheHt3kN9T7JQntsGnCRDK9pi6X")
 const idlPath = path.join(__dirname, "..", "target", "idl", "phantom_streams.json");
 const idl = JSON.parse(fs.readFileSync(idlPath, "utf8"));
 
+// Groth16 fixture exported from the Noir circuit via Sunspot:
+// { vk: { alphaG1, betaG2, gammaG2, deltaG2, ic[] }, proof, merkleRoot, trackId, nullifierHash }
+// (all hex), plus the numeric epoch and rights tier it was proven for.
+// The proof names the test wallet as relayer with a zero fee and the
+// default pubkey as session key. Tests that need a valid proof are skipped
+// without it.
+const fixturePath = path.join(__dirname, "fixtures", "ownership_proof.json");
+const fixture = fs.existsSync(fixturePath)
+  ? JSON.parse(fs.readFileSync(fixturePath, "utf8"))
+  : null;
+
+const hex = (value: string) => Buffer.from(value.replace(/^0x/, ""), "hex");
+
+// BN254 scalar field modulus; public inputs must be below it
+const FIELD_MODULUS = BigInt(
+  "0x30644e72e131a029b85045b68181585d2833e84879b9709143e1f593f0000001"
+);
+
+// Clear the top bits of a digest so it's a canonical field element
+const toField = (digest: Buffer) => {
+  digest[0] &= 0x1f;
+  return digest;
+};
+
+// Rights registry the fixture's Merkle root belongs to
+const REGISTRY_ID = 1;
+
+// Ownership circuit the fixture was generated for
+const CIRCUIT_ID = 1;
+const CIRCUIT_VERSION = 1;
+
+// Nullifier epoch the fixture was proven for (0 while epochs are disabled)
+const EPOCH = fixture?.epoch ?? 0;
+
+// Rights tier committed in the fixture's leaf
+const RIGHTS_TIER = fixture?.rightsTier ?? 0;
+
+const u32le = (value: number) => {
+  const buf = Buffer.alloc(4);
+  buf.writeUInt32LE(value);
+  return buf;
+};
+
+const u64le = (value: number) => {
+  const buf = Buffer.alloc(8);
+  buf.writeBigUInt64LE(BigInt(value));
+  return buf;
+};
+
+const u16le = (value: number) => {
+  const buf = Buffer.alloc(2);
+  buf.writeUInt16LE(value);
+  return buf;
+};
+
 // Load wallet keypair directly (bypasses environment variable issues)
 const homeDir = os.homedir();
 const walletPath = path.join(homeDir, ".config", "solana", "id.json");
@@ -49,12 +104,41 @@ describe("Phantom Streams - Full Program Tests", () => {
   // This avoids airdrop issues
   let statePda: PublicKey;
   let stateBump: number;
+  let verifyingKeyPda: PublicKey;
+  let registryPda: PublicKey;
+  let treasuryPda: PublicKey;
 
   // Test data
   let merkleRoot: Buffer;
   let trackId: Buffer;
   let nullifierHash: Buffer;
 
+  // Policy PDA for the test track; passed even when it doesn't exist
+  const trackPolicyFor = (registry: PublicKey) =>
+    PublicKey.findProgramAddressSync(
+      [Buffer.from("track_policy"), registry.toBuffer(), trackId],
+      PROGRAM_ID
+    )[0];
+
+  // Nullifier archive PDA; passed even when it doesn't exist
+  const archiveFor = (registry: PublicKey) =>
+    PublicKey.findProgramAddressSync(
+      [Buffer.from("nullifier_archive"), registry.toBuffer()],
+      PROGRAM_ID
+    )[0];
+
+  // Accounts for get_verification_status; all are PDAs that may not exist
+  const statusAccountsFor = (registry: PublicKey, nullifier: Buffer) => {
+    const pda = (...seeds: Buffer[]) => PublicKey.findProgramAddressSync(seeds, PROGRAM_ID)[0];
+    return {
+      registry,
+      nullifier: pda(Buffer.from("nullifier"), registry.toBuffer(), nullifier),
+      nullifierShard: pda(Buffer.from("nullifier_shard"), registry.toBuffer(), nullifier.subarray(31), u16le(0)),
+      nullifierArchive: archiveFor(registry),
+      archiveExclusion: pda(Buffer.from("archive_exclusion"), registry.toBuffer(), nullifier),
+    };
+  };
+
   before(async () => {
     console.log("\n" + "=".repeat(60));
     console.log("  PHANTOM STREAMS - FULL ANCHOR PROGRAM TESTS");
@@ -71,25 +155,47 @@ describe("Phantom Streams - Full Program Tests", () => {
       PROGRAM_ID
     );
 
+    [registryPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("registry"), u64le(REGISTRY_ID)],
+      PROGRAM_ID
+    );
+
+    [treasuryPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("treasury")],
+      PROGRAM_ID
+    );
+
+    [verifyingKeyPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("verifying_key"), u32le(CIRCUIT_ID), u16le(CIRCUIT_VERSION)],
+      PROGRAM_ID
+    );
+
     // Generate test data based on real wallets
-    merkleRoot = createHash("sha256")
+    merkleRoot = toField(createHash("sha256")
       .update(Buffer.concat([
         Buffer.from(TRAVIS_WALLETS.PRIMARY),
         Buffer.from(TRAVIS_WALLETS.SECOND),
       ]))
-      .digest();
+      .digest());
 
-    trackId = createHash("sha256")
+    trackId = toField(createHash("sha256")
       .update("UNICORNY-FOUNDING-MEMBER")
-      .digest();
+      .digest());
 
-    nullifierHash = createHash("sha256")
+    nullifierHash = toField(createHash("sha256")
       .update(Buffer.concat([
         Buffer.from(TRAVIS_WALLETS.PRIMARY),
         trackId,
         randomBytes(16) // nonce
       ]))
-      .digest();
+      .digest());
+
+    // Public inputs must match the proof when we have a real one
+    if (fixture) {
+      merkleRoot = hex(fixture.merkleRoot);
+      trackId = hex(fixture.trackId);
+      nullifierHash = hex(fixture.nullifierHash);
+    }
 
     // Using provider wallet - already funded by localnet validator
     console.log("\nTest accounts ready...");
@@ -121,6 +227,7 @@ describe("Phantom Streams - Full Program Tests", () => {
 
         expect(state.authority.toBase58()).to.equal(walletKeypair.publicKey.toBase58());
         expect(state.verificationCount.toNumber()).to.equal(0);
+        expect(state.version).to.equal(2);
 
         console.log("    ✅ Protocol initialized successfully");
         console.log(`    Authority: ${state.authority.toBase58()}`);
@@ -134,18 +241,193 @@ describe("Phantom Streams - Full Program Tests", () => {
         }
       }
     });
+
+    it("Rejects migrating an account that is already current", async () => {
+      console.log("\n  Testing: migrate_state() on a v2 account");
+
+      try {
+        await program.methods
+          .migrateState()
+          .accounts({
+            account: statePda,
+            payer: walletKeypair.publicKey,
+            systemProgram: SystemProgram.programId,
+          })
+          .signers([walletKeypair])
+          .rpc();
+
+        expect.fail("Should have rejected a current-layout account");
+      } catch (err: any) {
+        expect(err.message).to.include("InvalidAccountVersion");
+        console.log("    ✅ Current-layout account rejected");
+      }
+    });
+
+    it("Registers the ownership circuit verifying key", async function () {
+      if (!fixture) this.skip();
+      console.log("\n  Testing: register_verifying_key()");
+
+      const vk = {
+        alphaG1: [...hex(fixture.vk.alphaG1)],
+        betaG2: [...hex(fixture.vk.betaG2)],
+        gammaG2: [...hex(fixture.vk.gammaG2)],
+        deltaG2: [...hex(fixture.vk.deltaG2)],
+        ic: fixture.vk.ic.map((point: string) => [...hex(point)]),
+      };
+
+      try {
+        await program.methods
+          .registerVerifyingKey(CIRCUIT_ID, CIRCUIT_VERSION, vk as any)
+          .accounts({
+            state: statePda,
+            verifyingKey: verifyingKeyPda,
+            authority: walletKeypair.publicKey,
+            multisig: null,
+            systemProgram: SystemProgram.programId,
+          })
+          .signers([walletKeypair])
+          .rpc();
+      } catch (err: any) {
+        if (!err.message?.includes("already in use")) throw err;
+        console.log("    ⚠️ Verifying key already stored (re-run)");
+      }
+
+      const stored = await program.account.verifyingKey.fetch(verifyingKeyPda);
+      expect(stored.circuitId).to.equal(CIRCUIT_ID);
+      expect(stored.version).to.equal(CIRCUIT_VERSION);
+      expect(stored.isActive).to.be.true;
+      expect((stored.vk as any).ic.length).to.equal(4);
+      console.log("    ✅ Verifying key registered");
+    });
+
+    it("Rejects verifying key registration from non-authority", async () => {
+      const badActor = Keypair.generate();
+      const [otherKeyPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("verifying_key"), u32le(CIRCUIT_ID), u16le(CIRCUIT_VERSION + 1)],
+        PROGRAM_ID
+      );
+      const vk = {
+        alphaG1: Array(64).fill(0),
+        betaG2: Array(128).fill(0),
+        gammaG2: Array(128).fill(0),
+        deltaG2: Array(128).fill(0),
+        ic: [Array(64).fill(0)],
+      };
+
+      try {
+        await program.methods
+          .registerVerifyingKey(CIRCUIT_ID, CIRCUIT_VERSION + 1, vk as any)
+          .accounts({
+            state: statePda,
+            verifyingKey: otherKeyPda,
+            authority: badActor.publicKey,
+            multisig: null,
+            systemProgram: SystemProgram.programId,
+          })
+          .signers([badActor])
+          .rpc();
+
+        expect.fail("Should have rejected unauthorized registration");
+      } catch (err: any) {
+        expect(err.message).to.not.include("Should have rejected");
+        console.log("    ✅ Unauthorized key registration rejected");
+      }
+    });
   });
 
   describe("2. Merkle Root Management", () => {
+    it("Creates a rights registry", async () => {
+      console.log("\n  Testing: create_registry()");
+
+      try {
+        await program.methods
+          .createRegistry(new anchor.BN(REGISTRY_ID), walletKeypair.publicKey)
+          .accounts({
+            state: statePda,
+            registry: registryPda,
+            authority: walletKeypair.publicKey,
+            multisig: null,
+            systemProgram: SystemProgram.programId,
+          })
+          .signers([walletKeypair])
+          .rpc();
+      } catch (err: any) {
+        if (!err.message?.includes("already in use")) throw err;
+        console.log("    ⚠️ Registry already created (re-run)");
+      }
+
+      const registry = await program.account.registry.fetch(registryPda);
+      expect(registry.registryId.toNumber()).to.equal(REGISTRY_ID);
+      expect(registry.authority.toBase58()).to.equal(walletKeypair.publicKey.toBase58());
+      console.log("    ✅ Registry created");
+    });
+
+    it("Keeps registries independent", async () => {
+      console.log("\n  Testing: registry isolation");
+
+      // Second catalog owned by a different label
+      const labelAuthority = Keypair.generate();
+      const otherId = 1000 + Math.floor(Math.random() * 1_000_000);
+      const [otherRegistryPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("registry"), u64le(otherId)],
+        PROGRAM_ID
+      );
+
+      await program.methods
+        .createRegistry(new anchor.BN(otherId), labelAuthority.publicKey)
+        .accounts({
+          state: statePda,
+          registry: otherRegistryPda,
+          authority: walletKeypair.publicKey,
+          multisig: null,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([walletKeypair])
+        .rpc();
+
+      // Our authority can't touch the other label's tree
+      try {
+        await program.methods
+          .updateMerkleRoot([...randomBytes(32)] as any, null)
+          .accounts({
+            state: statePda,
+            registry: otherRegistryPda,
+            authority: walletKeypair.publicKey,
+            multisig: null,
+          })
+          .signers([walletKeypair])
+          .rpc();
+
+        expect.fail("Should have rejected cross-registry update");
+      } catch (err: any) {
+        expect(err.message).to.include("Unauthorized");
+      }
+
+      // Same nullifier hash maps to different PDAs per registry
+      const [ours] = PublicKey.findProgramAddressSync(
+        [Buffer.from("nullifier"), registryPda.toBuffer(), nullifierHash],
+        PROGRAM_ID
+      );
+      const [theirs] = PublicKey.findProgramAddressSync(
+        [Buffer.from("nullifier"), otherRegistryPda.toBuffer(), nullifierHash],
+        PROGRAM_ID
+      );
+      expect(ours.equals(theirs)).to.be.false;
+
+      console.log("    ✅ Registries have separate authorities and nullifier sets");
+    });
+
     it("Updates merkle root (authority only)", async () => {
       console.log("\n  Testing: update_merkle_root()");
       console.log(`    New Root: ${merkleRoot.toString("hex").slice(0, 32)}...`);
 
       const tx = await program.methods
-        .updateMerkleRoot([...merkleRoot] as any)
+        .updateMerkleRoot([...merkleRoot] as any, null)
         .accounts({
           state: statePda,
+          registry: registryPda,
           authority: walletKeypair.publicKey,
+          multisig: null,
         })
         .signers([walletKeypair])
         .rpc();
@@ -153,8 +435,8 @@ describe("Phantom Streams - Full Program Tests", () => {
       console.log(`    TX: ${tx.slice(0, 20)}...`);
 
       // Verify update
-      const state = await program.account.protocolState.fetch(statePda);
-      expect(Buffer.from(state.merkleRoot as any)).to.deep.equal(merkleRoot);
+      const registry = await program.account.registry.fetch(registryPda);
+      expect(Buffer.from(registry.merkleRoot as any)).to.deep.equal(merkleRoot);
 
       console.log("    ✅ Merkle root updated successfully");
     });
@@ -170,10 +452,12 @@ describe("Phantom Streams - Full Program Tests", () => {
         // This will fail because badActor is not the authority
         // (The constraint check happens before fee payment)
         await program.methods
-          .updateMerkleRoot([...fakeMerkleRoot] as any)
+          .updateMerkleRoot([...fakeMerkleRoot] as any, null)
           .accounts({
             state: statePda,
+            registry: registryPda,
             authority: badActor.publicKey,
+            multisig: null,
           })
           .signers([badActor])
           .rpc();
@@ -185,121 +469,1575 @@ describe("Phantom Streams - Full Program Tests", () => {
         console.log("    ✅ Unauthorized update correctly rejected");
       }
     });
-  });
 
-  describe("3. Ownership Verification", () => {
-    let nullifierPda: PublicKey;
+    it("Keeps the previous root in the history window", async () => {
+      console.log("\n  Testing: root history window");
 
-    before(() => {
-      // Derive nullifier PDA
-      [nullifierPda] = PublicKey.findProgramAddressSync(
-        [Buffer.from("nullifier"), nullifierHash],
-        PROGRAM_ID
-      );
+      const nextRoot = randomBytes(32);
+      const expiresAt = Math.floor(Date.now() / 1000) + 3600;
+
+      await program.methods
+        .updateMerkleRoot([...nextRoot] as any, new anchor.BN(expiresAt))
+        .accounts({
+          state: statePda,
+          registry: registryPda,
+          authority: walletKeypair.publicKey,
+          multisig: null,
+        })
+        .signers([walletKeypair])
+        .rpc();
+
+      const registry = await program.account.registry.fetch(registryPda);
+      const history = (registry.rootHistory as any[]).map((entry) => Buffer.from(entry.root));
+      expect(Buffer.from(registry.merkleRoot as any)).to.deep.equal(nextRoot);
+      expect(history.some((root) => root.equals(merkleRoot))).to.be.true;
+      expect(history.some((root) => root.equals(nextRoot))).to.be.true;
+
+      console.log("    ✅ Proofs against the previous root stay valid");
     });
 
-    it("Verifies ownership with valid proof", async () => {
-      console.log("\n  Testing: verify_ownership()");
-      console.log(`    Track: UNICORNY-FOUNDING-MEMBER`);
-      console.log(`    Nullifier: ${nullifierHash.toString("hex").slice(0, 32)}...`);
+    it("Rejects a root expiry in the past", async () => {
+      try {
+        await program.methods
+          .updateMerkleRoot([...randomBytes(32)] as any, new anchor.BN(1))
+          .accounts({
+            state: statePda,
+            registry: registryPda,
+            authority: walletKeypair.publicKey,
+            multisig: null,
+          })
+          .signers([walletKeypair])
+          .rpc();
 
-      // Mock proof data (in production, this would be a real ZK proof)
-      const proofData = Buffer.concat([
-        Buffer.from("PROOF:"),
-        randomBytes(64)
-      ]);
+        expect.fail("Should have rejected past expiry");
+      } catch (err: any) {
+        expect(err.message).to.include("InvalidRootExpiry");
+        console.log("    ✅ Past expiry rejected");
+      }
+    });
+  });
 
-      const tx = await program.methods
-        .verifyOwnership(
-          proofData,
-          [...trackId] as any,
-          [...nullifierHash] as any,
-          [...merkleRoot] as any
-        )
+  describe("3. On-chain Rights Tree", () => {
+    const registryId = 4_000_000 + Math.floor(Math.random() * 1_000_000);
+    let treeRegistryPda: PublicKey;
+    let rightsTreePda: PublicKey;
+
+    // Random leaf commitment below the BN254 scalar field modulus
+    const fieldElement = () => {
+      const bytes = randomBytes(32);
+      bytes[0] = 0;
+      return bytes;
+    };
+
+    before(async () => {
+      [treeRegistryPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("registry"), u64le(registryId)],
+        PROGRAM_ID
+      );
+      [rightsTreePda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("rights_tree"), treeRegistryPda.toBuffer()],
+        PROGRAM_ID
+      );
+
+      await program.methods
+        .createRegistry(new anchor.BN(registryId), walletKeypair.publicKey)
         .accounts({
           state: statePda,
-          nullifier: nullifierPda,
-          payer: walletKeypair.publicKey,
+          registry: treeRegistryPda,
+          authority: walletKeypair.publicKey,
+          multisig: null,
           systemProgram: SystemProgram.programId,
         })
         .signers([walletKeypair])
         .rpc();
+    });
 
-      console.log(`    TX: ${tx.slice(0, 20)}...`);
-
-      // Verify nullifier was created
-      const nullifierAccount = await program.account.nullifierAccount.fetch(nullifierPda);
-      expect(nullifierAccount.isUsed).to.be.true;
+    it("Registers rights leaves and updates the root", async () => {
+      console.log("\n  Testing: register_rights()");
 
-      // Verify count incremented
-      const state = await program.account.protocolState.fetch(statePda);
-      expect(state.verificationCount.toNumber()).to.be.greaterThan(0);
+      await program.methods
+        .initializeRightsTree()
+        .accounts({
+          registry: treeRegistryPda,
+          rightsTree: rightsTreePda,
+          authority: walletKeypair.publicKey,
+          multisig: null,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([walletKeypair])
+        .rpc();
 
-      console.log("    ✅ Ownership verified successfully");
-      console.log(`    Verification Count: ${state.verificationCount.toNumber()}`);
-      console.log("\n    PRIVACY PRESERVED:");
-      console.log(`    • Wallet address: NOT on-chain`);
-      console.log(`    • Only nullifier hash stored: ${nullifierHash.toString("hex").slice(0, 16)}...`);
-    });
+      const roots: Buffer[] = [];
+      for (let i = 0; i < 2; i++) {
+        await program.methods
+          .registerRights([...fieldElement()] as any)
+          .accounts({
+            state: statePda,
+            registry: treeRegistryPda,
+            rightsTree: rightsTreePda,
+            authority: walletKeypair.publicKey,
+            multisig: null,
+          })
+          .signers([walletKeypair])
+          .rpc();
 
-    it("Prevents nullifier reuse (replay attack)", async () => {
-      console.log("\n  Testing: nullifier replay prevention");
+        const registry = await program.account.registry.fetch(treeRegistryPda);
+        roots.push(Buffer.from(registry.merkleRoot as any));
+      }
 
-      // Try to use the same nullifier again
-      const proofData = Buffer.concat([
-        Buffer.from("PROOF:"),
-        randomBytes(64)
-      ]);
+      const tree = await program.account.rightsTree.fetch(rightsTreePda);
+      expect(tree.nextIndex.toNumber()).to.equal(2);
+      expect(roots[0].equals(roots[1])).to.be.false;
+      console.log("    ✅ Leaves appended, root recomputed on-chain");
+    });
 
+    it("Rejects manual root updates on a tree-managed registry", async () => {
       try {
         await program.methods
-          .verifyOwnership(
-            proofData,
-            [...trackId] as any,
-            [...nullifierHash] as any,
-            [...merkleRoot] as any
-          )
+          .updateMerkleRoot([...randomBytes(32)] as any, null)
           .accounts({
             state: statePda,
-            nullifier: nullifierPda,
-            payer: walletKeypair.publicKey,
-            systemProgram: SystemProgram.programId,
+            registry: treeRegistryPda,
+            authority: walletKeypair.publicKey,
+            multisig: null,
           })
           .signers([walletKeypair])
           .rpc();
 
-        // Should not reach here
-        expect.fail("Should have rejected duplicate nullifier");
+        expect.fail("Should have rejected manual root");
       } catch (err: any) {
-        console.log("    ✅ Replay attack correctly prevented");
+        expect(err.message).to.include("RootManagedByTree");
+        console.log("    ✅ Root can't be swapped outside the tree");
       }
     });
   });
 
-  describe("4. Privacy Verification", () => {
-    it("Confirms wallet address never stored on-chain", async () => {
-      console.log("\n  Testing: privacy guarantees");
+  describe("4. Authority Management", () => {
+    it("Rejects acceptance by a key that wasn't proposed", async () => {
+      console.log("\n  Testing: two-step protocol authority transfer");
 
-      // Fetch all program accounts
-      const stateAccount = await program.account.protocolState.fetch(statePda);
+      const proposed = Keypair.generate();
+      await program.methods
+        .proposeAuthority(proposed.publicKey)
+        .accounts({
+          state: statePda,
+          authority: walletKeypair.publicKey,
+          multisig: null,
+        })
+        .signers([walletKeypair])
+        .rpc();
 
-      // Check that no wallet addresses appear in state
-      const stateData = JSON.stringify(stateAccount);
+      const badActor = Keypair.generate();
+      try {
+        await program.methods
+          .acceptAuthority()
+          .accounts({
+            state: statePda,
+            authority: badActor.publicKey,
+            multisig: null,
+          })
+          .signers([badActor])
+          .rpc();
 
-      expect(stateData).to.not.include(TRAVIS_WALLETS.PRIMARY);
-      expect(stateData).to.not.include(TRAVIS_WALLETS.SECOND);
-      expect(stateData).to.not.include(TRAVIS_WALLETS.UNICORNY_FOUNDER);
+        expect.fail("Should have rejected acceptance");
+      } catch (err: any) {
+        expect(err.message).to.not.include("Should have rejected");
+      }
 
-      console.log("    ✅ No wallet addresses stored in protocol state");
-      console.log("\n    ON-CHAIN DATA:");
-      console.log(`    • Authority (protocol admin): ${stateAccount.authority.toBase58()}`);
-      console.log(`    • Merkle Root: ${Buffer.from(stateAccount.merkleRoot as any).toString("hex").slice(0, 32)}...`);
-      console.log(`    • Verification Count: ${stateAccount.verificationCount.toNumber()}`);
-      console.log("\n    NOT ON-CHAIN:");
-      console.log(`    • Travis Primary: ${TRAVIS_WALLETS.PRIMARY.slice(0, 8)}... ❌`);
-      console.log(`    • Travis Second: ${TRAVIS_WALLETS.SECOND.slice(0, 8)}... ❌`);
-      console.log(`    • Token holdings ❌`);
-      console.log(`    • Transaction history ❌`);
+      // Withdraw the proposal by handing authority back to ourselves
+      await program.methods
+        .proposeAuthority(walletKeypair.publicKey)
+        .accounts({
+          state: statePda,
+          authority: walletKeypair.publicKey,
+          multisig: null,
+        })
+        .signers([walletKeypair])
+        .rpc();
+      await program.methods
+        .acceptAuthority()
+        .accounts({
+          state: statePda,
+          authority: walletKeypair.publicKey,
+          multisig: null,
+        })
+        .signers([walletKeypair])
+        .rpc();
+
+      const state = await program.account.protocolState.fetch(statePda);
+      expect(state.authority.toBase58()).to.equal(walletKeypair.publicKey.toBase58());
+      expect(state.pendingAuthority).to.be.null;
+      console.log("    ✅ Only the proposed authority can accept");
+    });
+
+    it("Hands a registry to a 2-of-3 multisig", async () => {
+      console.log("\n  Testing: multisig registry authority");
+
+      const memberB = Keypair.generate();
+      const memberC = Keypair.generate();
+      const multisigId = Math.floor(Math.random() * 1_000_000_000);
+      const [multisigPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("multisig"), u64le(multisigId)],
+        PROGRAM_ID
+      );
+      const registryId = 2_000_000 + Math.floor(Math.random() * 1_000_000);
+      const [msRegistryPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("registry"), u64le(registryId)],
+        PROGRAM_ID
+      );
+      const coSigner = (signer: Keypair) => ({
+        pubkey: signer.publicKey,
+        isSigner: true,
+        isWritable: false,
+      });
+
+      await program.methods
+        .createMultisig(
+          new anchor.BN(multisigId),
+          [walletKeypair.publicKey, memberB.publicKey, memberC.publicKey],
+          2
+        )
+        .accounts({
+          multisig: multisigPda,
+          payer: walletKeypair.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([walletKeypair])
+        .rpc();
+
+      await program.methods
+        .createRegistry(new anchor.BN(registryId), walletKeypair.publicKey)
+        .accounts({
+          state: statePda,
+          registry: msRegistryPda,
+          authority: walletKeypair.publicKey,
+          multisig: null,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([walletKeypair])
+        .rpc();
+
+      await program.methods
+        .proposeRegistryAuthority(multisigPda)
+        .accounts({
+          registry: msRegistryPda,
+          authority: walletKeypair.publicKey,
+          multisig: null,
+        })
+        .signers([walletKeypair])
+        .rpc();
+
+      await program.methods
+        .acceptRegistryAuthority()
+        .accounts({
+          registry: msRegistryPda,
+          authority: walletKeypair.publicKey,
+          multisig: multisigPda,
+        })
+        .remainingAccounts([coSigner(memberB)])
+        .signers([walletKeypair, memberB])
+        .rpc();
+
+      // One member alone is no longer enough
+      try {
+        await program.methods
+          .updateMerkleRoot([...randomBytes(32)] as any, null)
+          .accounts({
+            state: statePda,
+            registry: msRegistryPda,
+            authority: walletKeypair.publicKey,
+            multisig: multisigPda,
+          })
+          .signers([walletKeypair])
+          .rpc();
+
+        expect.fail("Should have required a second signer");
+      } catch (err: any) {
+        expect(err.message).to.include("MultisigThresholdNotMet");
+      }
+
+      const newRoot = randomBytes(32);
+      await program.methods
+        .updateMerkleRoot([...newRoot] as any, null)
+        .accounts({
+          state: statePda,
+          registry: msRegistryPda,
+          authority: walletKeypair.publicKey,
+          multisig: multisigPda,
+        })
+        .remainingAccounts([coSigner(memberC)])
+        .signers([walletKeypair, memberC])
+        .rpc();
+
+      const registry = await program.account.registry.fetch(msRegistryPda);
+      expect(registry.authority.toBase58()).to.equal(multisigPda.toBase58());
+      expect(Buffer.from(registry.merkleRoot as any)).to.deep.equal(newRoot);
+      console.log("    ✅ Root updates require 2 of 3 co-signers");
+    });
+  });
+
+  describe("5. Timelocked Root Updates", () => {
+    const guardian = Keypair.generate();
+    const registryId = 3_000_000 + Math.floor(Math.random() * 1_000_000);
+    let tlRegistryPda: PublicKey;
+
+    before(async () => {
+      [tlRegistryPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("registry"), u64le(registryId)],
+        PROGRAM_ID
+      );
+
+      await program.methods
+        .createRegistry(new anchor.BN(registryId), walletKeypair.publicKey)
+        .accounts({
+          state: statePda,
+          registry: tlRegistryPda,
+          authority: walletKeypair.publicKey,
+          multisig: null,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([walletKeypair])
+        .rpc();
+
+      await program.methods
+        .setRootTimelock(new anchor.BN(2), guardian.publicKey)
+        .accounts({
+          state: statePda,
+          registry: tlRegistryPda,
+          authority: walletKeypair.publicKey,
+          multisig: null,
+        })
+        .signers([walletKeypair])
+        .rpc();
+    });
+
+    const propose = (root: Buffer) =>
+      program.methods
+        .proposeMerkleRoot([...root] as any, null)
+        .accounts({
+          state: statePda,
+          registry: tlRegistryPda,
+          authority: walletKeypair.publicKey,
+          multisig: null,
+        })
+        .signers([walletKeypair])
+        .rpc();
+
+    const finalize = () =>
+      program.methods
+        .finalizeMerkleRoot()
+        .accounts({ state: statePda, registry: tlRegistryPda })
+        .rpc();
+
+    it("Blocks immediate updates on a timelocked registry", async () => {
+      console.log("\n  Testing: timelocked update_merkle_root()");
+      try {
+        await program.methods
+          .updateMerkleRoot([...randomBytes(32)] as any, null)
+          .accounts({
+            state: statePda,
+            registry: tlRegistryPda,
+            authority: walletKeypair.publicKey,
+            multisig: null,
+          })
+          .signers([walletKeypair])
+          .rpc();
+
+        expect.fail("Should have required propose/finalize");
+      } catch (err: any) {
+        expect(err.message).to.include("RootTimelockActive");
+        console.log("    ✅ Immediate update rejected");
+      }
+    });
+
+    it("Lets the guardian veto a pending root", async () => {
+      console.log("\n  Testing: veto_merkle_root()");
+      await propose(randomBytes(32));
+
+      await program.methods
+        .vetoMerkleRoot()
+        .accounts({
+          registry: tlRegistryPda,
+          authority: guardian.publicKey,
+          multisig: null,
+        })
+        .signers([guardian])
+        .rpc();
+
+      const registry = await program.account.registry.fetch(tlRegistryPda);
+      expect(registry.pendingRoot).to.be.null;
+      console.log("    ✅ Pending root vetoed");
+    });
+
+    it("Finalizes a root only after the delay", async () => {
+      console.log("\n  Testing: finalize_merkle_root()");
+      const newRoot = randomBytes(32);
+      await propose(newRoot);
+
+      try {
+        await finalize();
+        expect.fail("Should have enforced the delay");
+      } catch (err: any) {
+        expect(err.message).to.include("TimelockNotElapsed");
+      }
+
+      await new Promise((resolve) => setTimeout(resolve, 3000));
+      await finalize();
+
+      const registry = await program.account.registry.fetch(tlRegistryPda);
+      expect(Buffer.from(registry.merkleRoot as any)).to.deep.equal(newRoot);
+      expect(registry.pendingRoot).to.be.null;
+      console.log("    ✅ Root applied after the challenge period");
+    });
+  });
+
+  describe("6. Ownership Verification", () => {
+    let nullifierPda: PublicKey;
+
+    before(() => {
+      // Derive nullifier PDA (scoped to the registry)
+      [nullifierPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("nullifier"), registryPda.toBuffer(), nullifierHash],
+        PROGRAM_ID
+      );
+    });
+
+    it("Rejects a proof that doesn't verify", async () => {
+      console.log("\n  Testing: invalid proof rejection");
+
+      // Right length, but not a valid Groth16 proof for these inputs
+      const proofData = randomBytes(256);
+
+      try {
+        await program.methods
+          .verifyOwnership(
+            proofData,
+            [...trackId] as any,
+            [...nullifierHash] as any,
+            [...merkleRoot] as any,
+            new anchor.BN(EPOCH),
+            new anchor.BN(0),
+            PublicKey.default,
+            RIGHTS_TIER,
+            CIRCUIT_ID,
+            CIRCUIT_VERSION
+          )
+          .accounts({
+            state: statePda,
+            registry: registryPda,
+            verifyingKey: verifyingKeyPda,
+            nullifier: nullifierPda,
+            nullifierShard: null,
+            nullifierArchive: archiveFor(registryPda),
+            archiveExclusion: null,
+            payer: walletKeypair.publicKey,
+            relayer: null,
+            feeVault: null,
+            trackPolicy: trackPolicyFor(registryPda),
+            trackFeeRecipient: null,
+            accessPass: null,
+            treasury: treasuryPda,
+            payerTokenAccount: null,
+            treasuryTokenAccount: null,
+            tokenProgram: null,
+            systemProgram: SystemProgram.programId,
+          })
+          .signers([walletKeypair])
+          .rpc();
+
+        expect.fail("Should have rejected invalid proof");
+      } catch (err: any) {
+        expect(err.message).to.not.include("Should have rejected");
+        console.log("    ✅ Invalid proof correctly rejected");
+      }
+    });
+
+    it("Rejects a proof for a past epoch", async () => {
+      console.log("\n  Testing: epoch-scoped nullifiers");
+      const DAY = 86_400;
+
+      await program.methods
+        .setEpochLength(new anchor.BN(DAY))
+        .accounts({
+          state: statePda,
+          authority: walletKeypair.publicKey,
+          multisig: null,
+        })
+        .signers([walletKeypair])
+        .rpc();
+
+      const state = await program.account.protocolState.fetch(statePda);
+      expect(state.epochLength.toNumber()).to.equal(DAY);
+      const lastEpoch = Math.floor(Date.now() / 1000 / DAY) - 1;
+
+      try {
+        await program.methods
+          .verifyOwnership(
+            randomBytes(256),
+            [...trackId] as any,
+            [...nullifierHash] as any,
+            [...merkleRoot] as any,
+            new anchor.BN(lastEpoch),
+            new anchor.BN(0),
+            PublicKey.default,
+            RIGHTS_TIER,
+            CIRCUIT_ID,
+            CIRCUIT_VERSION
+          )
+          .accounts({
+            state: statePda,
+            registry: registryPda,
+            verifyingKey: verifyingKeyPda,
+            nullifier: nullifierPda,
+            nullifierShard: null,
+            nullifierArchive: archiveFor(registryPda),
+            archiveExclusion: null,
+            payer: walletKeypair.publicKey,
+            relayer: null,
+            feeVault: null,
+            trackPolicy: trackPolicyFor(registryPda),
+            trackFeeRecipient: null,
+            accessPass: null,
+            treasury: treasuryPda,
+            payerTokenAccount: null,
+            treasuryTokenAccount: null,
+            tokenProgram: null,
+            systemProgram: SystemProgram.programId,
+          })
+          .signers([walletKeypair])
+          .rpc();
+
+        expect.fail("Should have rejected stale epoch");
+      } catch (err: any) {
+        expect(err.message).to.include("InvalidEpoch");
+        console.log("    ✅ Yesterday's proof can't be replayed today");
+      } finally {
+        // Restore single-epoch mode for the fixture proof
+        await program.methods
+          .setEpochLength(new anchor.BN(0))
+          .accounts({
+            state: statePda,
+            authority: walletKeypair.publicKey,
+            multisig: null,
+          })
+          .signers([walletKeypair])
+          .rpc();
+      }
+    });
+
+    it("Rejects a non-canonical encoding of the nullifier", async () => {
+      console.log("\n  Testing: canonical public inputs");
+
+      // nullifierHash + r is the same field element, but would seed a
+      // different nullifier PDA
+      const aliased = Buffer.from(
+        (BigInt("0x" + nullifierHash.toString("hex")) + FIELD_MODULUS)
+          .toString(16)
+          .padStart(64, "0"),
+        "hex"
+      );
+      const [aliasedPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("nullifier"), registryPda.toBuffer(), aliased],
+        PROGRAM_ID
+      );
+
+      try {
+        await program.methods
+          .verifyOwnership(
+            randomBytes(256),
+            [...trackId] as any,
+            [...aliased] as any,
+            [...merkleRoot] as any,
+            new anchor.BN(EPOCH),
+            new anchor.BN(0),
+            PublicKey.default,
+            RIGHTS_TIER,
+            CIRCUIT_ID,
+            CIRCUIT_VERSION
+          )
+          .accounts({
+            state: statePda,
+            registry: registryPda,
+            verifyingKey: verifyingKeyPda,
+            nullifier: aliasedPda,
+            nullifierShard: null,
+            nullifierArchive: archiveFor(registryPda),
+            archiveExclusion: null,
+            payer: walletKeypair.publicKey,
+            relayer: null,
+            feeVault: null,
+            trackPolicy: trackPolicyFor(registryPda),
+            trackFeeRecipient: null,
+            accessPass: null,
+            treasury: treasuryPda,
+            payerTokenAccount: null,
+            treasuryTokenAccount: null,
+            tokenProgram: null,
+            systemProgram: SystemProgram.programId,
+          })
+          .signers([walletKeypair])
+          .rpc();
+
+        expect.fail("Should have rejected non-canonical nullifier");
+      } catch (err: any) {
+        expect(err.message).to.include("NonCanonicalFieldElement");
+        console.log("    ✅ Only the canonical encoding is accepted");
+      }
+    });
+
+    it("Only pays fees to registered relayers, up to the cap", async () => {
+      console.log("\n  Testing: relayer fees");
+
+      const [relayerPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("relayer"), walletKeypair.publicKey.toBuffer()],
+        PROGRAM_ID
+      );
+      const [feeVaultPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("fee_vault")],
+        PROGRAM_ID
+      );
+
+      const submit = (fee: number, relayer: PublicKey | null, feeVault: PublicKey | null) =>
+        program.methods
+          .verifyOwnership(
+            randomBytes(256),
+            [...trackId] as any,
+            [...nullifierHash] as any,
+            [...merkleRoot] as any,
+            new anchor.BN(EPOCH),
+            new anchor.BN(fee),
+            PublicKey.default,
+            RIGHTS_TIER,
+            CIRCUIT_ID,
+            CIRCUIT_VERSION
+          )
+          .accounts({
+            state: statePda,
+            registry: registryPda,
+            verifyingKey: verifyingKeyPda,
+            nullifier: nullifierPda,
+            nullifierShard: null,
+            nullifierArchive: archiveFor(registryPda),
+            archiveExclusion: null,
+            payer: walletKeypair.publicKey,
+            relayer,
+            feeVault,
+            trackPolicy: trackPolicyFor(registryPda),
+            trackFeeRecipient: null,
+            accessPass: null,
+            treasury: treasuryPda,
+            payerTokenAccount: null,
+            treasuryTokenAccount: null,
+            tokenProgram: null,
+            systemProgram: SystemProgram.programId,
+          })
+          .signers([walletKeypair])
+          .rpc();
+
+      try {
+        await submit(5_000, null, null);
+        expect.fail("Should have rejected unregistered relayer");
+      } catch (err: any) {
+        expect(err.message).to.include("RelayerNotRegistered");
+      }
+
+      await program.methods
+        .registerRelayer(walletKeypair.publicKey)
+        .accounts({
+          state: statePda,
+          relayer: relayerPda,
+          authority: walletKeypair.publicKey,
+          multisig: null,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([walletKeypair])
+        .rpc();
+
+      await program.methods
+        .initializeFeeVault(new anchor.BN(5_000))
+        .accounts({
+          state: statePda,
+          feeVault: feeVaultPda,
+          authority: walletKeypair.publicKey,
+          multisig: null,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([walletKeypair])
+        .rpc();
+
+      try {
+        await submit(10_000, relayerPda, feeVaultPda);
+        expect.fail("Should have rejected fee above cap");
+      } catch (err: any) {
+        expect(err.message).to.include("RelayerFeeTooHigh");
+      }
+
+      const relayer = await program.account.relayer.fetch(relayerPda);
+      expect(relayer.isActive).to.be.true;
+      console.log("    ✅ Relayer fees gated by registration and cap");
+    });
+
+    it("Enforces the track's access policy", async () => {
+      console.log("\n  Testing: track policy");
+
+      const trackPolicyPda = trackPolicyFor(registryPda);
+      const config = (accessDuration: number) => ({
+        isAllowed: true,
+        accessDuration: new anchor.BN(accessDuration),
+        requiredTier: 0,
+        fee: new anchor.BN(0),
+        feeRecipient: walletKeypair.publicKey,
+      });
+      const policyAccounts = {
+        registry: registryPda,
+        trackPolicy: trackPolicyPda,
+        authority: walletKeypair.publicKey,
+        multisig: null,
+      };
+
+      try {
+        await program.methods
+          .createTrackPolicy([...trackId] as any, config(0))
+          .accounts({ ...policyAccounts, systemProgram: SystemProgram.programId })
+          .signers([walletKeypair])
+          .rpc();
+
+        expect.fail("Should have rejected zero duration");
+      } catch (err: any) {
+        expect(err.message).to.include("InvalidAccessDuration");
+      }
+
+      await program.methods
+        .createTrackPolicy([...trackId] as any, config(3_600))
+        .accounts({ ...policyAccounts, systemProgram: SystemProgram.programId })
+        .signers([walletKeypair])
+        .rpc();
+
+      await program.methods
+        .updateTrackPolicy(config(86_400))
+        .accounts(policyAccounts)
+        .signers([walletKeypair])
+        .rpc();
+
+      const policy = await program.account.trackPolicy.fetch(trackPolicyPda);
+      expect(policy.config.accessDuration.toNumber()).to.equal(86_400);
+
+      // A paused track can't be verified at all
+      await program.methods
+        .setTrackPaused(true)
+        .accounts(policyAccounts)
+        .signers([walletKeypair])
+        .rpc();
+
+      try {
+        await program.methods
+          .verifyOwnership(
+            randomBytes(256),
+            [...trackId] as any,
+            [...nullifierHash] as any,
+            [...merkleRoot] as any,
+            new anchor.BN(EPOCH),
+            new anchor.BN(0),
+            PublicKey.default,
+            RIGHTS_TIER,
+            CIRCUIT_ID,
+            CIRCUIT_VERSION
+          )
+          .accounts({
+            state: statePda,
+            registry: registryPda,
+            verifyingKey: verifyingKeyPda,
+            nullifier: nullifierPda,
+            nullifierShard: null,
+            nullifierArchive: archiveFor(registryPda),
+            archiveExclusion: null,
+            payer: walletKeypair.publicKey,
+            relayer: null,
+            feeVault: null,
+            trackPolicy: trackPolicyPda,
+            trackFeeRecipient: null,
+            accessPass: null,
+            treasury: treasuryPda,
+            payerTokenAccount: null,
+            treasuryTokenAccount: null,
+            tokenProgram: null,
+            systemProgram: SystemProgram.programId,
+          })
+          .signers([walletKeypair])
+          .rpc();
+
+        expect.fail("Should have rejected paused track");
+      } catch (err: any) {
+        expect(err.message).to.include("TrackPaused");
+      } finally {
+        await program.methods
+          .setTrackPaused(false)
+          .accounts(policyAccounts)
+          .signers([walletKeypair])
+          .rpc();
+      }
+
+      console.log("    ✅ Track terms enforced, single track can be taken down");
+    });
+
+    it("Reports the status of an unused nullifier", async () => {
+      console.log("\n  Testing: get_verification_status()");
+      const unused = Buffer.alloc(32, 0x42);
+
+      const status = await program.methods
+        .getVerificationStatus([...unused] as any)
+        .accounts(statusAccountsFor(registryPda, unused))
+        .view();
+
+      expect(status.status).to.deep.equal({ unused: {} });
+      expect(status.registry.equals(registryPda)).to.be.true;
+      expect(status.trackId).to.be.null;
+      expect(status.verificationId).to.be.null;
+      console.log("    ✅ Unused nullifier reported without an account error");
+    });
+
+    it("Verifies ownership with valid proof", async function () {
+      if (!fixture) this.skip();
+      console.log("\n  Testing: verify_ownership()");
+      console.log(`    Track: ${trackId.toString("hex").slice(0, 32)}...`);
+      console.log(`    Nullifier: ${nullifierHash.toString("hex").slice(0, 32)}...`);
+
+      const tx = await program.methods
+        .verifyOwnership(
+          hex(fixture.proof),
+          [...trackId] as any,
+          [...nullifierHash] as any,
+          [...merkleRoot] as any,
+          new anchor.BN(EPOCH),
+          new anchor.BN(0),
+          PublicKey.default,
+          RIGHTS_TIER,
+          CIRCUIT_ID,
+          CIRCUIT_VERSION
+        )
+        .accounts({
+          state: statePda,
+          registry: registryPda,
+          verifyingKey: verifyingKeyPda,
+          nullifier: nullifierPda,
+          nullifierShard: null,
+          nullifierArchive: archiveFor(registryPda),
+          archiveExclusion: null,
+          payer: walletKeypair.publicKey,
+          relayer: null,
+          feeVault: null,
+          trackPolicy: trackPolicyFor(registryPda),
+          trackFeeRecipient: null,
+          accessPass: null,
+          treasury: treasuryPda,
+          payerTokenAccount: null,
+          treasuryTokenAccount: null,
+          tokenProgram: null,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([walletKeypair])
+        .rpc();
+
+      console.log(`    TX: ${tx.slice(0, 20)}...`);
+
+      // Verify nullifier was created
+      const nullifierAccount = await program.account.nullifierAccount.fetch(nullifierPda);
+      expect(nullifierAccount.isUsed).to.be.true;
+
+      // Receipt is returned for CPI callers
+      const txInfo = await provider.connection.getTransaction(tx, {
+        commitment: "confirmed",
+        maxSupportedTransactionVersion: 0,
+      });
+      const prefix = `Program return: ${PROGRAM_ID.toBase58()} `;
+      const returnLog = txInfo!.meta!.logMessages!.find((log) => log.startsWith(prefix));
+      const receipt = program.coder.types.decode(
+        "VerificationReceipt",
+        Buffer.from(returnLog!.slice(prefix.length), "base64")
+      );
+      expect(receipt.verifier.equals(PROGRAM_ID)).to.be.true;
+      expect(Buffer.from(receipt.nullifierHash).equals(nullifierHash)).to.be.true;
+
+      const status = await program.methods
+        .getVerificationStatus([...nullifierHash] as any)
+        .accounts(statusAccountsFor(registryPda, nullifierHash))
+        .view();
+      expect(status.status).to.deep.equal({ used: {} });
+      expect(Buffer.from(status.trackId).equals(trackId)).to.be.true;
+      expect(status.verificationId.toNumber()).to.equal(receipt.verificationId.toNumber());
+
+      // Verify count incremented
+      const state = await program.account.protocolState.fetch(statePda);
+      expect(state.verificationCount.toNumber()).to.be.greaterThan(0);
+
+      console.log("    ✅ Ownership verified successfully");
+      console.log(`    Verification Count: ${state.verificationCount.toNumber()}`);
+      console.log("\n    PRIVACY PRESERVED:");
+      console.log(`    • Wallet address: NOT on-chain`);
+      console.log(`    • Only nullifier hash stored: ${nullifierHash.toString("hex").slice(0, 16)}...`);
+    });
+
+    it("Prevents nullifier reuse (replay attack)", async function () {
+      if (!fixture) this.skip();
+      console.log("\n  Testing: nullifier replay prevention");
+
+      // Resubmit the same valid proof
+      try {
+        await program.methods
+          .verifyOwnership(
+            hex(fixture.proof),
+            [...trackId] as any,
+            [...nullifierHash] as any,
+            [...merkleRoot] as any,
+            new anchor.BN(EPOCH),
+            new anchor.BN(0),
+            PublicKey.default,
+            RIGHTS_TIER,
+            CIRCUIT_ID,
+            CIRCUIT_VERSION
+          )
+          .accounts({
+            state: statePda,
+            registry: registryPda,
+            verifyingKey: verifyingKeyPda,
+            nullifier: nullifierPda,
+            nullifierShard: null,
+            nullifierArchive: archiveFor(registryPda),
+            archiveExclusion: null,
+            payer: walletKeypair.publicKey,
+            relayer: null,
+            feeVault: null,
+            trackPolicy: trackPolicyFor(registryPda),
+            trackFeeRecipient: null,
+            accessPass: null,
+            treasury: treasuryPda,
+            payerTokenAccount: null,
+            treasuryTokenAccount: null,
+            tokenProgram: null,
+            systemProgram: SystemProgram.programId,
+          })
+          .signers([walletKeypair])
+          .rpc();
+
+        // Should not reach here
+        expect.fail("Should have rejected duplicate nullifier");
+      } catch (err: any) {
+        expect(err.message).to.not.include("Should have rejected");
+        console.log("    ✅ Replay attack correctly prevented");
+      }
+    });
+  });
+
+  describe("7. Sharded Nullifiers", () => {
+    const registryId = 5_000_000 + Math.floor(Math.random() * 1_000_000);
+    let shardedRegistryPda: PublicKey;
+    let shardPda: PublicKey;
+
+    const shardPage = (page: number) =>
+      PublicKey.findProgramAddressSync(
+        [
+          Buffer.from("nullifier_shard"),
+          shardedRegistryPda.toBuffer(),
+          Buffer.from([nullifierHash[31]]),
+          u16le(page),
+        ],
+        PROGRAM_ID
+      )[0];
+
+    before(async () => {
+      [shardedRegistryPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("registry"), u64le(registryId)],
+        PROGRAM_ID
+      );
+      // Shards are keyed by the nullifier's low byte, in pages
+      shardPda = shardPage(0);
+
+      await program.methods
+        .createRegistry(new anchor.BN(registryId), walletKeypair.publicKey)
+        .accounts({
+          state: statePda,
+          registry: shardedRegistryPda,
+          authority: walletKeypair.publicKey,
+          multisig: null,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([walletKeypair])
+        .rpc();
+    });
+
+    it("Switches a fresh registry to sharded nullifiers", async () => {
+      console.log("\n  Testing: set_nullifier_store()");
+
+      await program.methods
+        .setNullifierStore(true)
+        .accounts({
+          registry: shardedRegistryPda,
+          authority: walletKeypair.publicKey,
+          multisig: null,
+        })
+        .signers([walletKeypair])
+        .rpc();
+
+      await program.methods
+        .initializeNullifierShard(nullifierHash[31], 0)
+        .accounts({
+          registry: shardedRegistryPda,
+          nullifierShard: shardPda,
+          previousPage: null,
+          payer: walletKeypair.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([walletKeypair])
+        .rpc();
+
+      const registry = await program.account.registry.fetch(shardedRegistryPda);
+      expect(registry.shardedNullifiers).to.be.true;
+
+      const shard = await program.account.nullifierShard.fetch(shardPda);
+      expect(shard.prefix).to.equal(nullifierHash[31]);
+      expect(shard.page).to.equal(0);
+      expect(shard.count).to.equal(0);
+      console.log("    ✅ Registry records nullifiers in prefix shards");
+    });
+
+    it("Only opens the next page once the last one is full", async () => {
+      try {
+        await program.methods
+          .initializeNullifierShard(nullifierHash[31], 1)
+          .accounts({
+            registry: shardedRegistryPda,
+            nullifierShard: shardPage(1),
+            previousPage: shardPda,
+            payer: walletKeypair.publicKey,
+            systemProgram: SystemProgram.programId,
+          })
+          .signers([walletKeypair])
+          .rpc();
+
+        expect.fail("Should have rejected a second page");
+      } catch (err: any) {
+        expect(err.message).to.include("NullifierPageNotFull");
+        console.log("    ✅ Page 1 waits for page 0 to fill");
+      }
+    });
+
+    it("Reports unused nullifiers from the shard", async () => {
+      const used = await program.methods
+        .checkNullifier([...nullifierHash] as any)
+        .accounts({
+          registry: shardedRegistryPda,
+          nullifier: null,
+          nullifierShard: shardPda,
+        })
+        .view();
+
+      expect(used).to.be.false;
+      console.log("    ✅ check_nullifier reads the shard");
+    });
+
+    it("Rejects a per-nullifier PDA on a sharded registry", async () => {
+      const [pda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("nullifier"), shardedRegistryPda.toBuffer(), nullifierHash],
+        PROGRAM_ID
+      );
+
+      try {
+        await program.methods
+          .verifyOwnership(
+            randomBytes(256),
+            [...trackId] as any,
+            [...nullifierHash] as any,
+            [...merkleRoot] as any,
+            new anchor.BN(EPOCH),
+            new anchor.BN(0),
+            PublicKey.default,
+            RIGHTS_TIER,
+            CIRCUIT_ID,
+            CIRCUIT_VERSION
+          )
+          .accounts({
+            state: statePda,
+            registry: shardedRegistryPda,
+            verifyingKey: verifyingKeyPda,
+            nullifier: pda,
+            nullifierShard: null,
+            nullifierArchive: archiveFor(shardedRegistryPda),
+            archiveExclusion: null,
+            payer: walletKeypair.publicKey,
+            relayer: null,
+            feeVault: null,
+            trackPolicy: trackPolicyFor(shardedRegistryPda),
+            trackFeeRecipient: null,
+            accessPass: null,
+            treasury: treasuryPda,
+            payerTokenAccount: null,
+            treasuryTokenAccount: null,
+            tokenProgram: null,
+            systemProgram: SystemProgram.programId,
+          })
+          .signers([walletKeypair])
+          .rpc();
+
+        expect.fail("Should have rejected nullifier PDA");
+      } catch (err: any) {
+        expect(err.message).to.not.include("Should have rejected");
+        console.log("    ✅ Nullifier can't bypass the registry's store");
+      }
+    });
+  });
+
+  describe("8. Verification Fees", () => {
+    const feeAccounts = () => ({
+      state: statePda,
+      treasury: treasuryPda,
+      authority: walletKeypair.publicKey,
+      multisig: null,
+    });
+
+    it("Configures the verification fee", async () => {
+      console.log("\n  Testing: set_verification_fee()");
+
+      await program.methods
+        .initializeTreasury()
+        .accounts({ ...feeAccounts(), systemProgram: SystemProgram.programId })
+        .signers([walletKeypair])
+        .rpc();
+
+      await program.methods
+        .setVerificationFee(new anchor.BN(10_000), null)
+        .accounts({ ...feeAccounts(), treasuryTokenAccount: null })
+        .signers([walletKeypair])
+        .rpc();
+
+      const treasury = await program.account.treasury.fetch(treasuryPda);
+      expect(treasury.feeAmount.toNumber()).to.equal(10_000);
+      expect(treasury.feeMint).to.be.null;
+      console.log("    ✅ Verifications now cost 10,000 lamports");
+    });
+
+    it("Rejects an SPL fee without the treasury token account", async () => {
+      try {
+        await program.methods
+          .setVerificationFee(new anchor.BN(1), Keypair.generate().publicKey)
+          .accounts({ ...feeAccounts(), treasuryTokenAccount: null })
+          .signers([walletKeypair])
+          .rpc();
+
+        expect.fail("Should have rejected missing token account");
+      } catch (err: any) {
+        expect(err.message).to.include("InvalidFeeAccount");
+        console.log("    ✅ SPL fees need the treasury's token account");
+      }
+    });
+
+    it("Withdraws collected lamports above the rent reserve", async () => {
+      console.log("\n  Testing: withdraw_fees()");
+
+      // Simulate collected fees
+      await provider.sendAndConfirm(
+        new anchor.web3.Transaction().add(
+          SystemProgram.transfer({
+            fromPubkey: walletKeypair.publicKey,
+            toPubkey: treasuryPda,
+            lamports: 1_000_000,
+          })
+        ),
+        [walletKeypair]
+      );
+
+      const destination = Keypair.generate().publicKey;
+      const withdraw = (amount: number) =>
+        program.methods
+          .withdrawFees(new anchor.BN(amount))
+          .accounts({
+            ...feeAccounts(),
+            treasuryTokenAccount: null,
+            destination,
+            tokenProgram: null,
+          })
+          .signers([walletKeypair])
+          .rpc();
+
+      await withdraw(1_000_000);
+      expect(await provider.connection.getBalance(destination)).to.equal(1_000_000);
+
+      try {
+        await withdraw(1);
+        expect.fail("Should have protected the rent reserve");
+      } catch (err: any) {
+        expect(err.message).to.include("InsufficientTreasuryBalance");
+      }
+
+      // Leave verification free for the remaining tests
+      await program.methods
+        .setVerificationFee(new anchor.BN(0), null)
+        .accounts({ ...feeAccounts(), treasuryTokenAccount: null })
+        .signers([walletKeypair])
+        .rpc();
+      console.log("    ✅ Fees withdrawn to the authority's destination");
+    });
+  });
+
+  describe("9. Emergency Pause", () => {
+    const VERIFY_OWNERSHIP = 1 << 0;
+    const UPDATE_MERKLE_ROOT = 1 << 1;
+    const guardian = Keypair.generate();
+
+    const setPaused = (paused: number, signer: Keypair) =>
+      program.methods
+        .setPaused(paused)
+        .accounts({
+          state: statePda,
+          authority: signer.publicKey,
+          multisig: null,
+        })
+        .signers([signer])
+        .rpc();
+
+    before(async () => {
+      await program.methods
+        .setPauseGuardian(guardian.publicKey)
+        .accounts({
+          state: statePda,
+          authority: walletKeypair.publicKey,
+          multisig: null,
+        })
+        .signers([walletKeypair])
+        .rpc();
+    });
+
+    it("Lets the guardian pause individual instructions", async () => {
+      console.log("\n  Testing: set_paused()");
+
+      await setPaused(UPDATE_MERKLE_ROOT, guardian);
+
+      try {
+        await program.methods
+          .updateMerkleRoot([...merkleRoot] as any, null)
+          .accounts({
+            state: statePda,
+            registry: registryPda,
+            authority: walletKeypair.publicKey,
+            multisig: null,
+          })
+          .signers([walletKeypair])
+          .rpc();
+
+        expect.fail("Should have rejected paused instruction");
+      } catch (err: any) {
+        expect(err.message).to.include("Paused");
+      }
+
+      const state = await program.account.protocolState.fetch(statePda);
+      expect(state.paused).to.equal(UPDATE_MERKLE_ROOT);
+      expect(state.paused & VERIFY_OWNERSHIP).to.equal(0);
+      console.log("    ✅ Root updates halted, verification still live");
+    });
+
+    it("Only lets the authority unpause", async () => {
+      try {
+        await setPaused(0, guardian);
+        expect.fail("Should have rejected guardian unpause");
+      } catch (err: any) {
+        expect(err.message).to.include("Unauthorized");
+      }
+
+      await setPaused(0, walletKeypair);
+      const state = await program.account.protocolState.fetch(statePda);
+      expect(state.paused).to.equal(0);
+      console.log("    ✅ Guardian can pause but not unpause");
+    });
+  });
+
+  describe("10. Nullifier Archive", () => {
+    // Small enough to be a canonical field element, and above the sentinel
+    const absentNullifier = Buffer.alloc(32);
+    absentNullifier[31] = 7;
+
+    const exclusionPda = () =>
+      PublicKey.findProgramAddressSync(
+        [Buffer.from("archive_exclusion"), registryPda.toBuffer(), absentNullifier],
+        PROGRAM_ID
+      )[0];
+
+    // In an empty archive the sentinel leaf covers every nullifier, and its
+    // siblings are the empty subtree roots
+    const sentinelProof = (archive: any) => ({
+      lowLeaf: { value: new Array(32).fill(0), nextValue: new Array(32).fill(0) },
+      lowIndex: new anchor.BN(0),
+      siblings: archive.zeros,
+    });
+
+    it("Initializes the nullifier archive", async () => {
+      console.log("\n  Testing: initialize_nullifier_archive()");
+
+      await program.methods
+        .initializeNullifierArchive()
+        .accounts({
+          registry: registryPda,
+          nullifierArchive: archiveFor(registryPda),
+          authority: walletKeypair.publicKey,
+          multisig: null,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([walletKeypair])
+        .rpc();
+
+      const archive = await program.account.nullifierArchive.fetch(archiveFor(registryPda));
+      expect(archive.nextIndex.toNumber()).to.equal(1);
+      expect(Buffer.from(archive.root as any).equals(Buffer.alloc(32))).to.be.false;
+      console.log("    ✅ Archive starts with only the sentinel leaf");
+    });
+
+    it("Rejects a forged non-membership proof", async () => {
+      const archive = await program.account.nullifierArchive.fetch(archiveFor(registryPda));
+      const proof = sentinelProof(archive);
+      proof.siblings = [[...proof.siblings[0].slice(0, 31), 1], ...proof.siblings.slice(1)];
+
+      try {
+        await program.methods
+          .proveNotArchived([...absentNullifier] as any, proof as any)
+          .accounts({
+            registry: registryPda,
+            nullifierArchive: archiveFor(registryPda),
+            archiveExclusion: exclusionPda(),
+            payer: walletKeypair.publicKey,
+            systemProgram: SystemProgram.programId,
+          })
+          .signers([walletKeypair])
+          .rpc();
+
+        expect.fail("Should have rejected a forged archive proof");
+      } catch (err: any) {
+        expect(err.message).to.include("InvalidArchiveProof");
+        console.log("    ✅ Forged archive proof rejected");
+      }
+    });
+
+    it("Records a nullifier's absence from the archive", async () => {
+      console.log("\n  Testing: prove_not_archived()");
+      const archive = await program.account.nullifierArchive.fetch(archiveFor(registryPda));
+
+      await program.methods
+        .proveNotArchived([...absentNullifier] as any, sentinelProof(archive) as any)
+        .accounts({
+          registry: registryPda,
+          nullifierArchive: archiveFor(registryPda),
+          archiveExclusion: exclusionPda(),
+          payer: walletKeypair.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([walletKeypair])
+        .rpc();
+
+      const exclusion = await program.account.archiveExclusion.fetch(exclusionPda());
+      expect(exclusion.archiveSize.toNumber()).to.equal(archive.nextIndex.toNumber());
+      console.log("    ✅ Exclusion recorded at the current archive size");
+    });
+  });
+
+  describe("11. Batch Verification", () => {
+    let proofBufferPda: PublicKey;
+
+    // Placeholder entries; only their count matters until verification
+    const entries = (count: number, offset: number) =>
+      Array.from({ length: count }, (_, i) => ({
+        proof: new Array(256).fill(0),
+        trackId: [...trackId],
+        nullifierHash: new Array(32).fill(offset + i + 1),
+        rightsTier: 0,
+      }));
+
+    const append = (count: number, offset: number) =>
+      program.methods
+        .appendProofBuffer(entries(count, offset) as any)
+        .accounts({
+          proofBuffer: proofBufferPda,
+          payer: walletKeypair.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([walletKeypair])
+        .rpc();
+
+    before(() => {
+      [proofBufferPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("proof_buffer"), walletKeypair.publicKey.toBuffer()],
+        PROGRAM_ID
+      );
+    });
+
+    it("Stages proofs in the payer's proof buffer", async () => {
+      console.log("\n  Testing: open_proof_buffer() / append_proof_buffer()");
+
+      await program.methods
+        .openProofBuffer()
+        .accounts({
+          proofBuffer: proofBufferPda,
+          payer: walletKeypair.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([walletKeypair])
+        .rpc();
+
+      // A full proof entry is 321 bytes, so a transaction carries three
+      await append(3, 0);
+      await append(3, 3);
+
+      const buffer = await program.account.proofBuffer.fetch(proofBufferPda);
+      expect(buffer.entries.length).to.equal(6);
+      console.log("    ✅ Buffer holds 6 staged proofs");
+    });
+
+    it("Rejects a buffer larger than the batch limit", async () => {
+      await append(3, 6);
+
+      try {
+        await append(2, 9);
+        expect.fail("Should have rejected an oversized batch");
+      } catch (err: any) {
+        expect(err.message).to.include("InvalidBatchSize");
+        console.log("    ✅ Batch capped at MAX_BATCH_SIZE");
+      }
+    });
+
+    it("Rejects a batch without its per-proof accounts", async function () {
+      if (!fixture) this.skip();
+
+      try {
+        await program.methods
+          .verifyOwnershipBatch(
+            [...merkleRoot] as any,
+            new anchor.BN(EPOCH),
+            CIRCUIT_ID,
+            CIRCUIT_VERSION
+          )
+          .accounts({
+            state: statePda,
+            registry: registryPda,
+            verifyingKey: verifyingKeyPda,
+            proofBuffer: proofBufferPda,
+            nullifierArchive: archiveFor(registryPda),
+            treasury: treasuryPda,
+            payer: walletKeypair.publicKey,
+            payerTokenAccount: null,
+            treasuryTokenAccount: null,
+            tokenProgram: null,
+            systemProgram: SystemProgram.programId,
+          })
+          .signers([walletKeypair])
+          .rpc();
+
+        expect.fail("Should have rejected missing batch accounts");
+      } catch (err: any) {
+        expect(err.message).to.include("InvalidBatchAccounts");
+        console.log("    ✅ Batch requires every proof's accounts");
+      }
+    });
+
+    it("Closes the proof buffer", async () => {
+      await program.methods
+        .closeProofBuffer()
+        .accounts({
+          proofBuffer: proofBufferPda,
+          payer: walletKeypair.publicKey,
+        })
+        .signers([walletKeypair])
+        .rpc();
+
+      const info = await provider.connection.getAccountInfo(proofBufferPda);
+      expect(info).to.be.null;
+      console.log("    ✅ Buffer closed and rent refunded");
+    });
+  });
+
+  describe("12. Privacy Verification", () => {
+    it("Confirms wallet address never stored on-chain", async () => {
+      console.log("\n  Testing: privacy guarantees");
+
+      // Fetch all program accounts
+      const stateAccount = await program.account.protocolState.fetch(statePda);
+      const registryAccount = await program.account.registry.fetch(registryPda);
+
+      // Check that no wallet addresses appear in state
+      const stateData = JSON.stringify(stateAccount) + JSON.stringify(registryAccount);
+
+      expect(stateData).to.not.include(TRAVIS_WALLETS.PRIMARY);
+      expect(stateData).to.not.include(TRAVIS_WALLETS.SECOND);
+      expect(stateData).to.not.include(TRAVIS_WALLETS.UNICORNY_FOUNDER);
+
+      console.log("    ✅ No wallet addresses stored in protocol state");
+      console.log("\n    ON-CHAIN DATA:");
+      console.log(`    • Authority (protocol admin): ${stateAccount.authority.toBase58()}`);
+      console.log(`    • Merkle Root: ${Buffer.from(registryAccount.merkleRoot as any).toString("hex").slice(0, 32)}...`);
+      console.log(`    • Verification Count: ${stateAccount.verificationCount.toNumber()}`);
+      console.log("\n    NOT ON-CHAIN:");
+      console.log(`    • Travis Primary: ${TRAVIS_WALLETS.PRIMARY.slice(0, 8)}... ❌`);
+      console.log(`    • Travis Second: ${TRAVIS_WALLETS.SECOND.slice(0, 8)}... ❌`);
+      console.log(`    • Token holdings ❌`);
+      console.log(`    • Transaction history ❌`);
+    });
+  });
+
+  describe("13. Access Pass Revocation", () => {
+    // Passes are only issued by a verified proof, so the validator preloads
+    // one for registry 1 (tests/fixtures/access-pass.json, see Anchor.toml)
+    const passTrackId = Buffer.alloc(32, 0x51);
+    const passNullifier = Buffer.alloc(32, 0x52);
+    const passPayer = new PublicKey(Buffer.alloc(32, 0x54));
+
+    const accessPassPda = () =>
+      PublicKey.findProgramAddressSync(
+        [Buffer.from("access_pass"), registryPda.toBuffer(), passTrackId, passNullifier],
+        PROGRAM_ID
+      )[0];
+
+    const revoke = (authority: Keypair, payer: PublicKey) =>
+      program.methods
+        .revokeAccessPass()
+        .accounts({
+          registry: registryPda,
+          accessPass: accessPassPda(),
+          authority: authority.publicKey,
+          multisig: null,
+          payer,
+        })
+        .signers([authority])
+        .rpc();
+
+    it("Only lets the registry authority revoke a pass", async () => {
+      const pass = await program.account.accessPass.fetch(accessPassPda());
+      expect(pass.registry.toBase58()).to.equal(registryPda.toBase58());
+      expect(pass.payer.toBase58()).to.equal(passPayer.toBase58());
+
+      try {
+        await revoke(Keypair.generate(), passPayer);
+        expect.fail("Should have rejected a non-authority");
+      } catch (err: any) {
+        expect(err.message).to.include("Unauthorized");
+        console.log("    ✅ Non-authority revocation rejected");
+      }
+    });
+
+    it("Refunds the rent only to the pass's payer", async () => {
+      try {
+        await revoke(walletKeypair, walletKeypair.publicKey);
+        expect.fail("Should have rejected another rent recipient");
+      } catch (err: any) {
+        expect(err.message).to.include("InvalidRentRecipient");
+        console.log("    ✅ Rent can't be redirected");
+      }
+    });
+
+    it("Revokes the pass and refunds its payer", async () => {
+      console.log("\n  Testing: revoke_access_pass()");
+      const rent = await provider.connection.getBalance(accessPassPda());
+      const before = await provider.connection.getBalance(passPayer);
+
+      await revoke(walletKeypair, passPayer);
+
+      expect(await provider.connection.getAccountInfo(accessPassPda())).to.be.null;
+      expect(await provider.connection.getBalance(passPayer)).to.equal(before + rent);
+      console.log("    ✅ Pass closed and rent refunded");
     });
   });
 